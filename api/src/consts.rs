@@ -18,5 +18,10 @@ pub const MAX_NAME_LEN: usize = 32;
 pub const NUM_ACCOUNTS: usize = 32_000;
 
 pub const COMPRESSED_STATE_DEPTH: usize = 20;
+pub const COMPRESSED_STATE_CHANGELOG_SIZE: usize = 12;
 pub const RELAY_STATE_DEPTH: usize = 63;
-pub const RELAY_HISTORY_ITEMS: usize = 32;
\ No newline at end of file
+pub const RELAY_HISTORY_ITEMS: usize = 32;
+
+pub const MAX_MULTISIG_SIGNERS: usize = 10;
+pub const MAX_COMPRESS_BATCH_SIZE: usize = 16; // CompressEvent logs must fit in 10KB
+pub const MAX_FEE_BPS: u16 = 10_000;
\ No newline at end of file
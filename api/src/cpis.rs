@@ -5,7 +5,7 @@ use solana_program::{
 };
 use steel::*;
 
-use crate::helpers::check_condition;
+use crate::{error::CodeVmError, event::TokenTransferEvent, helpers::check_condition};
 
 pub fn create_token_account<'info>(
     mint: &AccountInfo<'info>,
@@ -53,6 +53,7 @@ pub fn create_account_with_size<'a, 'info, T: Discriminator + Pod>(
 ) -> ProgramResult {
     check_condition(
         size >= 8 + std::mem::size_of::<T>(),
+        CodeVmError::InvalidInstructionData,
         "provided size is too small",
     )?;
 
@@ -102,4 +103,76 @@ pub fn resize_account<'info>(
     target_account.realloc(new_size, false)?;
 
     Ok(())
-}
\ No newline at end of file
+}
+/// Transfer tokens out of an account controlled by the VM and log a
+/// TokenTransferEvent for it. All token transfers made by the VM go through
+/// here.
+pub fn transfer_signed_with_event<'info>(
+    authority_info: &AccountInfo<'info>,
+    from_info: &AccountInfo<'info>,
+    to_info: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    transfer_signed(
+        authority_info,
+        from_info,
+        to_info,
+        token_program,
+        amount,
+        signer_seeds,
+    )?;
+
+    TokenTransferEvent {
+        source: *from_info.key,
+        destination: *to_info.key,
+        amount,
+    }.log();
+
+    Ok(())
+}
+
+pub fn close_account<'info>(
+    target_account: &AccountInfo<'info>,
+    recipient: &AccountInfo<'info>,
+) -> ProgramResult {
+    // Move all lamports to the recipient.
+    let lamports = target_account.lamports();
+    **recipient.lamports.borrow_mut() = recipient
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **target_account.lamports.borrow_mut() = 0;
+
+    // Hand the (now empty) account back to the system program.
+    target_account.realloc(0, true)?;
+    target_account.assign(&solana_program::system_program::ID);
+
+    Ok(())
+}
+
+pub fn close_token_account<'info>(
+    target: &AccountInfo<'info>,
+    recipient: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    // The token account is its own authority (see create_token_account).
+    solana_program::program::invoke_signed(
+        &spl_token::instruction::close_account(
+            &spl_token::id(),
+            target.key,
+            recipient.key,
+            target.key,
+            &[],
+        ).unwrap(),
+        &[
+            target.clone(),
+            recipient.clone(),
+            target.clone(),
+            token_program.clone(),
+        ],
+        &[seeds],
+    )
+}
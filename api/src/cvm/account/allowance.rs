@@ -0,0 +1,36 @@
+use steel::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::types::Hash;
+
+#[repr(C)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq, Debug)]
+pub struct VirtualAllowanceAccount {
+    pub instance: Hash,         // unique identifier for this allowance (the nonce value used to approve it)
+    pub owner: Pubkey,          // owner of the timelock account that can be debited
+    pub delegate: Pubkey,       // can spend up to `remaining` on behalf of the owner
+    pub remaining: u64,         // the amount the delegate can still spend
+    pub expires_at: i64,        // unix timestamp after which the allowance can't be used
+}
+
+impl VirtualAllowanceAccount {
+    pub const LEN: usize = // 112 bytes
+        32 + // instance
+        32 + // owner
+        32 + // delegate
+        8 +  // remaining
+        8;   // expires_at
+
+    pub fn pack<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        BorshSerialize::serialize(self, &mut writer)
+    }
+
+    pub fn unpack(buf: &[u8]) -> std::io::Result<Self> {
+        let data = &buf[..VirtualAllowanceAccount::LEN];
+        BorshDeserialize::try_from_slice(data)
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now > self.expires_at
+    }
+}
@@ -0,0 +1,34 @@
+use steel::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::types::Hash;
+
+#[repr(C)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq, Debug)]
+pub struct VirtualEscrowAccount {
+    pub instance: Hash,         // unique identifier for this escrow (the nonce value used to fund it)
+    pub sender: Pubkey,         // owner of the funding timelock account, receives the refund
+    pub recipient: Pubkey,      // owner of the timelock account that can claim with the preimage
+    pub hashlock: Hash,         // sha256(preimage)
+    pub refund_after: i64,      // unix timestamp after which only a refund is possible
+    pub balance: u64,
+}
+
+impl VirtualEscrowAccount {
+    pub const LEN: usize = // 144 bytes
+        32 + // instance
+        32 + // sender
+        32 + // recipient
+        32 + // hashlock
+        8 +  // refund_after
+        8;   // balance
+
+    pub fn pack<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        BorshSerialize::serialize(self, &mut writer)
+    }
+
+    pub fn unpack(buf: &[u8]) -> std::io::Result<Self> {
+        let data = &buf[..VirtualEscrowAccount::LEN];
+        BorshDeserialize::try_from_slice(data)
+    }
+}
@@ -1,9 +1,15 @@
+mod allowance;
+mod escrow;
 mod nonce;
 mod relay;
+mod stream;
 mod timelock;
 mod virtual_account;
 
+pub use allowance::*;
+pub use escrow::*;
 pub use nonce::*;
 pub use relay::*;
+pub use stream::*;
 pub use timelock::*;
 pub use virtual_account::*;
\ No newline at end of file
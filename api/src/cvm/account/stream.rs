@@ -0,0 +1,86 @@
+use steel::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::types::Hash;
+
+#[repr(C)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq, Debug)]
+pub struct VirtualStreamAccount {
+    pub instance: Hash,         // unique identifier for this stream (the nonce value used to create it)
+    pub funder: Pubkey,         // owner of the funding timelock account, can cancel the stream
+    pub beneficiary: Pubkey,    // owner of the timelock account that receives the vested tokens
+    pub start_time: i64,        // unix timestamp at which tokens start vesting
+    pub end_time: i64,          // unix timestamp at which all tokens have vested
+    pub total_amount: u64,      // amount locked in the stream when it was created
+    pub withdrawn: u64,         // amount already paid out to the beneficiary
+}
+
+impl VirtualStreamAccount {
+    pub const LEN: usize = // 128 bytes
+        32 + // instance
+        32 + // funder
+        32 + // beneficiary
+        8 +  // start_time
+        8 +  // end_time
+        8 +  // total_amount
+        8;   // withdrawn
+
+    /// The total amount released by the stream at `now`, vesting linearly
+    /// between `start_time` and `end_time`.
+    pub fn get_vested_amount(&self, now: i64) -> u64 {
+        if now <= self.start_time {
+            return 0;
+        }
+        if now >= self.end_time {
+            return self.total_amount;
+        }
+
+        // The times are signed and can be anywhere in the i64 range, so their
+        // differences need i128. Both are positive here, as start < now < end.
+        let elapsed = (now as i128 - self.start_time as i128) as u128;
+        let duration = (self.end_time as i128 - self.start_time as i128) as u128;
+
+        (self.total_amount as u128 * elapsed / duration) as u64
+    }
+
+    /// The amount that has vested but has not been withdrawn yet.
+    pub fn get_withdrawable_amount(&self, now: i64) -> u64 {
+        self.get_vested_amount(now).saturating_sub(self.withdrawn)
+    }
+
+    /// The amount still held by the stream.
+    pub fn get_balance(&self) -> u64 {
+        self.total_amount.saturating_sub(self.withdrawn)
+    }
+
+    pub fn pack<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        BorshSerialize::serialize(self, &mut writer)
+    }
+
+    pub fn unpack(buf: &[u8]) -> std::io::Result<Self> {
+        let data = &buf[..VirtualStreamAccount::LEN];
+        BorshDeserialize::try_from_slice(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vested_amount_extreme_times() {
+        let stream = VirtualStreamAccount {
+            instance: Hash::default(),
+            funder: Pubkey::default(),
+            beneficiary: Pubkey::default(),
+            start_time: i64::MIN,
+            end_time: i64::MAX,
+            total_amount: u64::MAX,
+            withdrawn: 0,
+        };
+
+        assert_eq!(stream.get_vested_amount(i64::MIN), 0);
+        assert_eq!(stream.get_vested_amount(0), 1 << 63);
+        assert_eq!(stream.get_vested_amount(i64::MAX), u64::MAX);
+    }
+}
@@ -7,6 +7,9 @@ use super::{
     VirtualDurableNonce,
     VirtualTimelockAccount,
     VirtualRelayAccount,
+    VirtualEscrowAccount,
+    VirtualStreamAccount,
+    VirtualAllowanceAccount,
 };
 
 
@@ -15,6 +18,9 @@ pub enum VirtualAccount {
     Nonce(VirtualDurableNonce),
     Timelock(VirtualTimelockAccount),
     Relay(VirtualRelayAccount),
+    Escrow(VirtualEscrowAccount),
+    Stream(VirtualStreamAccount),
+    Allowance(VirtualAllowanceAccount),
 }
 
 impl VirtualAccount {
@@ -24,6 +30,9 @@ impl VirtualAccount {
             VirtualAccount::Nonce(_) => VirtualDurableNonce::LEN,
             VirtualAccount::Timelock(_) => VirtualTimelockAccount::LEN,
             VirtualAccount::Relay(_) => VirtualRelayAccount::LEN,
+            VirtualAccount::Escrow(_) => VirtualEscrowAccount::LEN,
+            VirtualAccount::Stream(_) => VirtualStreamAccount::LEN,
+            VirtualAccount::Allowance(_) => VirtualAllowanceAccount::LEN,
         })
     }
 
@@ -39,6 +48,31 @@ impl VirtualAccount {
         matches!(self, VirtualAccount::Nonce(_))
     }
 
+    pub fn is_escrow(&self) -> bool {
+        matches!(self, VirtualAccount::Escrow(_))
+    }
+
+    pub fn is_stream(&self) -> bool {
+        matches!(self, VirtualAccount::Stream(_))
+    }
+
+    pub fn is_allowance(&self) -> bool {
+        matches!(self, VirtualAccount::Allowance(_))
+    }
+
+    /// The tokens held by this VirtualAccount, zero for accounts that can't
+    /// hold any.
+    pub fn get_balance(&self) -> u64 {
+        match self {
+            VirtualAccount::Timelock(account) => account.balance,
+            VirtualAccount::Escrow(account) => account.balance,
+            VirtualAccount::Stream(account) => account.get_balance(),
+            VirtualAccount::Nonce(_)
+            | VirtualAccount::Relay(_)
+            | VirtualAccount::Allowance(_) => 0,
+        }
+    }
+
     /// Get the hash of this VirtualAccount
     pub fn get_hash(&self) -> Hash {
         utils::hash(self.pack().as_ref())
@@ -53,6 +87,9 @@ impl VirtualAccount {
             VirtualAccount::Nonce(_) => 0,
             VirtualAccount::Timelock(_) => 1,
             VirtualAccount::Relay(_) => 2,
+            VirtualAccount::Escrow(_) => 3,
+            VirtualAccount::Stream(_) => 4,
+            VirtualAccount::Allowance(_) => 5,
         };
 
         match self {
@@ -65,6 +102,15 @@ impl VirtualAccount {
             VirtualAccount::Relay(account) => {
                 account.pack(&mut bytes[1..]).unwrap();
             },
+            VirtualAccount::Escrow(account) => {
+                account.pack(&mut bytes[1..]).unwrap();
+            },
+            VirtualAccount::Stream(account) => {
+                account.pack(&mut bytes[1..]).unwrap();
+            },
+            VirtualAccount::Allowance(account) => {
+                account.pack(&mut bytes[1..]).unwrap();
+            },
         }
         bytes
     }
@@ -95,6 +141,15 @@ impl VirtualAccount {
             2 => Ok(VirtualAccount::Relay(
                 VirtualRelayAccount::unpack(&data).unwrap()
             )),
+            3 => Ok(VirtualAccount::Escrow(
+                VirtualEscrowAccount::unpack(&data).unwrap()
+            )),
+            4 => Ok(VirtualAccount::Stream(
+                VirtualStreamAccount::unpack(&data).unwrap()
+            )),
+            5 => Ok(VirtualAccount::Allowance(
+                VirtualAllowanceAccount::unpack(&data).unwrap()
+            )),
             _ => Err(ProgramError::InvalidAccountData)
         }
     }
@@ -124,6 +179,30 @@ impl VirtualAccount {
             None
         }
     }
+
+    pub fn into_inner_escrow(self) -> Option<VirtualEscrowAccount> {
+        if let VirtualAccount::Escrow(inner) = self {
+            Some(inner)
+        } else {
+            None
+        }
+    }
+
+    pub fn into_inner_stream(self) -> Option<VirtualStreamAccount> {
+        if let VirtualAccount::Stream(inner) = self {
+            Some(inner)
+        } else {
+            None
+        }
+    }
+
+    pub fn into_inner_allowance(self) -> Option<VirtualAllowanceAccount> {
+        if let VirtualAccount::Allowance(inner) = self {
+            Some(inner)
+        } else {
+            None
+        }
+    }
 }
 
 fn get_varient_size(variant: u8) -> usize {
@@ -131,6 +210,9 @@ fn get_varient_size(variant: u8) -> usize {
         0 => VirtualDurableNonce::LEN,
         1 => VirtualTimelockAccount::LEN,
         2 => VirtualRelayAccount::LEN,
+        3 => VirtualEscrowAccount::LEN,
+        4 => VirtualStreamAccount::LEN,
+        5 => VirtualAllowanceAccount::LEN,
         _ => 0,
     }
 }
\ No newline at end of file
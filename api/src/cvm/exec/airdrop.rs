@@ -0,0 +1,274 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    instruction::ExecIxData,
+    opcode::*,
+};
+use super::{check_num_accounts, credit_fee, ExecState};
+
+/// Send the same amount from one virtual timelock account to `count`
+/// destinations, signed by the owner of the source. See `AirdropOp`.
+pub fn airdrop(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<AirdropOp>(&data.data)?;
+    let args = AirdropOp::try_from_bytes(args_data)?.to_struct()?;
+
+    // The fee-collector account is expected last when the VM charges a fee.
+    let num_accounts = 2 + (args.count as usize) + (vm.has_fee() as usize);
+    check_num_accounts(data, num_accounts)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let total_amount = args.amount
+        .checked_mul(args.count as u64)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let fee = vm.get_fee(total_amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let total_debit = total_amount
+        .checked_add(fee)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    if src_vta.balance < total_debit {
+        return Err(CodeVmError::InsufficientFunds.into());
+    }
+
+    src_vta.balance = src_vta.balance
+        .checked_sub(total_debit)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let mut dst_pubkeys = Vec::new();
+    for i in 0..args.count as usize {
+        let dst_index = data.mem_indicies[2 + i];
+        let dst_mem = data.mem_banks[2 + i];
+
+        let va = state.try_read(dst_mem, dst_index)?;
+        let mut dst_vta = va.into_inner_timelock()
+            .ok_or(CodeVmError::WrongAccountVariant)?;
+
+        // Check if this destination is actually the source.
+        let is_same_account = (src_mem == dst_mem) && (src_index == dst_index);
+        if is_same_account {
+            // If the source is also in the destinations list, it receives the airdrop as well.
+            src_vta.balance = src_vta.balance
+                .checked_add(args.amount)
+                .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        } else {
+            // Normal destination: add the airdrop to its balance
+            dst_vta.balance = dst_vta.balance
+                .checked_add(args.amount)
+                .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+            // Write the updated destination back
+            state.try_write(
+                dst_mem,
+                dst_index,
+                &VirtualAccount::Timelock(dst_vta)
+            )?;
+        }
+
+        dst_pubkeys.push(dst_vta.owner);
+    }
+
+    let hash = create_airdrop_message(
+        vm,
+        &src_vta,
+        &dst_pubkeys,
+        args.amount,
+        &vdn,
+    );
+
+    let hash = if vm.has_fee() {
+        create_fee_message(&hash, &vm.fee_collector, fee)
+    } else {
+        hash
+    };
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    vdn.value = vm.get_current_poh();
+
+    // Finally, write back the updated source (which now includes
+    // any airdrop shares if the source was also in the destination list).
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    if vm.has_fee() {
+        credit_fee(
+            state,
+            vm,
+            data.mem_indicies[num_accounts - 1],
+            data.mem_banks[num_accounts - 1],
+            fee,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Send a different amount from one virtual timelock account to each
+/// destination, signed by the owner of the source. See `PayoutOp`.
+pub fn payout(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args, valid_until) = deserialize_valid_until::<PayoutOpData>(&data.data)?;
+
+    // The fee-collector account is expected last when the VM charges a fee.
+    let num_accounts = 2 + args.amounts.len() + (vm.has_fee() as usize);
+    check_num_accounts(data, num_accounts)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let total_amount = args.amounts
+        .iter()
+        .try_fold(0u64, |acc, amount| acc.checked_add(*amount))
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let fee = vm.get_fee(total_amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let total_debit = total_amount
+        .checked_add(fee)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    if src_vta.balance < total_debit {
+        return Err(CodeVmError::InsufficientFunds.into());
+    }
+
+    src_vta.balance = src_vta.balance
+        .checked_sub(total_debit)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let mut payouts = Vec::with_capacity(args.amounts.len());
+    for (i, amount) in args.amounts.iter().enumerate() {
+        let dst_index = data.mem_indicies[2 + i];
+        let dst_mem = data.mem_banks[2 + i];
+
+        let va = state.try_read(dst_mem, dst_index)?;
+        let mut dst_vta = va.into_inner_timelock()
+            .ok_or(CodeVmError::WrongAccountVariant)?;
+
+        // Check if this destination is actually the source.
+        let is_same_account = (src_mem == dst_mem) && (src_index == dst_index);
+        if is_same_account {
+            // If the source is also in the destinations list, it gets its share back.
+            src_vta.balance = src_vta.balance
+                .checked_add(*amount)
+                .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        } else {
+            // Normal destination: add its amount to the balance
+            dst_vta.balance = dst_vta.balance
+                .checked_add(*amount)
+                .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+            // Write the updated destination back
+            state.try_write(
+                dst_mem,
+                dst_index,
+                &VirtualAccount::Timelock(dst_vta)
+            )?;
+        }
+
+        payouts.push((dst_vta.owner, *amount));
+    }
+
+    let hash = create_payout_message(
+        vm,
+        &src_vta,
+        &payouts,
+        &vdn,
+    );
+
+    let hash = if vm.has_fee() {
+        create_fee_message(&hash, &vm.fee_collector, fee)
+    } else {
+        hash
+    };
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    vdn.value = vm.get_current_poh();
+
+    // Finally, write back the updated source (which now includes
+    // its own share if the source was also in the destination list).
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    if vm.has_fee() {
+        credit_fee(
+            state,
+            vm,
+            data.mem_indicies[num_accounts - 1],
+            data.mem_banks[num_accounts - 1],
+            fee,
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    helpers::check_condition,
+    instruction::ExecIxData,
+    opcode::*,
+};
+use super::{check_num_accounts, credit_fee, ExecState};
+
+/// Let a delegate spend up to an amount of a virtual timelock account until
+/// it expires, signed by the owner. See `ApproveOp`.
+pub fn approve(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<ApproveOp>(&data.data)?;
+    let args = ApproveOp::try_from_bytes(args_data)?.to_struct()?;
+
+    check_num_accounts(data, 3)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let allowance_index = data.mem_indicies[2];
+    let allowance_mem = data.mem_banks[2];
+
+    state.check_is_empty(allowance_mem, allowance_index)?;
+
+    check_condition(
+        args.amount > 0,
+        CodeVmError::InvalidInstructionData,
+        "the allowance amount must be greater than zero",
+    )?;
+
+    let now = state.get_unix_timestamp()?;
+
+    check_condition(
+        args.expires_at > now,
+        CodeVmError::InvalidTime,
+        "the expiry time must be in the future",
+    )?;
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let hash = create_approve_message(
+        vm,
+        &src_vta,
+        &args.delegate,
+        args.amount,
+        args.expires_at,
+        &vdn,
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    // The nonce value is unique per use, so it doubles as the allowance instance.
+    let allowance = VirtualAllowanceAccount {
+        instance: vdn.value,
+        owner: src_vta.owner,
+        delegate: args.delegate,
+        remaining: args.amount,
+        expires_at: args.expires_at,
+    };
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_write(
+        allowance_mem,
+        allowance_index,
+        &VirtualAccount::Allowance(allowance)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(())
+}
+
+/// Transfer tokens out of a virtual timelock account under an allowance,
+/// signed by its delegate. See `TransferFromOp`.
+pub fn transfer_from(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<TransferFromOp>(&data.data)?;
+    let args = TransferFromOp::try_from_bytes(args_data)?.to_struct()?;
+
+    // The fee-collector account is expected last when the VM charges a fee.
+    let num_accounts = if vm.has_fee() { 5 } else { 4 };
+    check_num_accounts(data, num_accounts)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let allowance_index = data.mem_indicies[1];
+    let allowance_mem = data.mem_banks[1];
+
+    let src_index = data.mem_indicies[2];
+    let src_mem = data.mem_banks[2];
+
+    let dst_index = data.mem_indicies[3];
+    let dst_mem = data.mem_banks[3];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(allowance_mem, allowance_index)?;
+    let mut allowance = va.into_inner_allowance()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut dst_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        allowance.owner.eq(&src_vta.owner),
+        CodeVmError::Unauthorized,
+        "the allowance was not granted by the source account owner",
+    )?;
+
+    let now = state.get_unix_timestamp()?;
+
+    check_condition(
+        !allowance.is_expired(now),
+        CodeVmError::Expired,
+        "the allowance has expired",
+    )?;
+
+    let hash = create_transfer_from_message(
+        vm,
+        &allowance,
+        &src_vta,
+        &dst_vta,
+        &vdn,
+        args.amount,
+    );
+
+    let fee = vm.get_fee(args.amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let hash = if vm.has_fee() {
+        create_fee_message(&hash, &vm.fee_collector, fee)
+    } else {
+        hash
+    };
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        allowance.delegate.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    let total_amount = args.amount
+        .checked_add(fee)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    // The fee is paid out of the allowance as well, so the delegate can never
+    // move more than the owner approved.
+    allowance.remaining = allowance.remaining
+        .checked_sub(total_amount)
+        .ok_or(CodeVmError::InsufficientFunds)?;
+
+    if src_vta.balance < total_amount {
+        return Err(CodeVmError::InsufficientFunds.into());
+    }
+
+    // If the source and destination accounts are the same, then we don't need
+    // to move any tokens, but the allowance is still spent.
+
+    let is_same_account = src_mem == dst_mem && src_index == dst_index;
+    if !is_same_account {
+        src_vta.balance = src_vta.balance
+            .checked_sub(args.amount)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        dst_vta.balance = dst_vta.balance
+            .checked_add(args.amount)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+    }
+
+    src_vta.balance = src_vta.balance
+        .checked_sub(fee)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    // The destination is a stale copy of the source when they are the same
+    // account, writing it would undo the fee.
+    if !is_same_account {
+        state.try_write(
+            dst_mem,
+            dst_index,
+            &VirtualAccount::Timelock(dst_vta)
+        )?;
+    }
+
+    if allowance.remaining == 0 {
+        state.try_delete(
+            allowance_mem,
+            allowance_index,
+        )?;
+    } else {
+        state.try_write(
+            allowance_mem,
+            allowance_index,
+            &VirtualAccount::Allowance(allowance)
+        )?;
+    }
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    if vm.has_fee() {
+        credit_fee(state, vm, data.mem_indicies[4], data.mem_banks[4], fee)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    helpers::check_condition,
+    instruction::ExecIxData,
+    opcode::*,
+};
+use super::{check_num_accounts, ExecState};
+
+/// Free a virtual durable nonce. See `CloseNonceOp`.
+pub fn close_nonce(
+    state: &mut impl ExecState,
+    data: &ExecIxData,
+) -> ProgramResult {
+    CloseNonceOp::try_from_bytes(&data.data)?;
+
+    check_num_accounts(data, 1)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    check_condition(
+        va.is_nonce(),
+        CodeVmError::WrongAccountVariant,
+        "the virtual account is not a nonce account",
+    )?;
+
+    state.try_delete(
+        nonce_mem,
+        nonce_index,
+    )
+}
+
+/// Free an empty virtual timelock account, signed by its owner. See
+/// `CloseTimelockOp`.
+pub fn close_timelock(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<CloseTimelockOp>(&data.data)?;
+    let args = CloseTimelockOp::try_from_bytes(args_data)?;
+
+    check_num_accounts(data, 1)?;
+
+    let vta_index = data.mem_indicies[0];
+    let vta_mem = data.mem_banks[0];
+
+    let va = state.try_read(vta_mem, vta_index)?;
+    let hash = create_close_message(state.get_vm_address(), &va);
+    let vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        vta.balance == 0,
+        CodeVmError::NonZeroBalance,
+        "the timelock account must have a zero balance",
+    )?;
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    state.try_delete(
+        vta_mem,
+        vta_index,
+    )
+}
+
+/// Free a virtual relay account. See `CloseRelayOp`.
+pub fn close_relay(
+    state: &mut impl ExecState,
+    data: &ExecIxData,
+) -> ProgramResult {
+    CloseRelayOp::try_from_bytes(&data.data)?;
+
+    check_num_accounts(data, 1)?;
+
+    let vra_index = data.mem_indicies[0];
+    let vra_mem = data.mem_banks[0];
+
+    let va = state.try_read(vra_mem, vra_index)?;
+    check_condition(
+        va.is_relay(),
+        CodeVmError::WrongAccountVariant,
+        "the virtual account is not a relay account",
+    )?;
+
+    state.try_delete(
+        vra_mem,
+        vra_index,
+    )
+}
+
+/// Free an empty escrow. See `CloseEscrowOp`.
+pub fn close_escrow(
+    state: &mut impl ExecState,
+    data: &ExecIxData,
+) -> ProgramResult {
+    CloseEscrowOp::try_from_bytes(&data.data)?;
+
+    check_num_accounts(data, 1)?;
+
+    let escrow_index = data.mem_indicies[0];
+    let escrow_mem = data.mem_banks[0];
+
+    let va = state.try_read(escrow_mem, escrow_index)?;
+    let escrow = va.into_inner_escrow()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        escrow.balance == 0,
+        CodeVmError::NonZeroBalance,
+        "the escrow account must have a zero balance",
+    )?;
+
+    state.try_delete(
+        escrow_mem,
+        escrow_index,
+    )
+}
+
+/// Free an empty stream. See `CloseStreamOp`.
+pub fn close_stream(
+    state: &mut impl ExecState,
+    data: &ExecIxData,
+) -> ProgramResult {
+    CloseStreamOp::try_from_bytes(&data.data)?;
+
+    check_num_accounts(data, 1)?;
+
+    let stream_index = data.mem_indicies[0];
+    let stream_mem = data.mem_banks[0];
+
+    let va = state.try_read(stream_mem, stream_index)?;
+    let stream = va.into_inner_stream()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        stream.get_balance() == 0,
+        CodeVmError::NonZeroBalance,
+        "the stream account must have a zero balance",
+    )?;
+
+    state.try_delete(
+        stream_mem,
+        stream_index,
+    )
+}
+
+/// Revoke an allowance, signed by the owner who granted it. See
+/// `CloseAllowanceOp`.
+pub fn close_allowance(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<CloseAllowanceOp>(&data.data)?;
+    let args = CloseAllowanceOp::try_from_bytes(args_data)?;
+
+    check_num_accounts(data, 1)?;
+
+    let allowance_index = data.mem_indicies[0];
+    let allowance_mem = data.mem_banks[0];
+
+    let va = state.try_read(allowance_mem, allowance_index)?;
+    let hash = create_close_message(state.get_vm_address(), &va);
+    let allowance = va.into_inner_allowance()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        allowance.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    state.try_delete(
+        allowance_mem,
+        allowance_index,
+    )
+}
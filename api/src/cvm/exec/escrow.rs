@@ -0,0 +1,228 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    helpers::check_condition,
+    instruction::ExecIxData,
+    opcode::*,
+    utils::hash,
+};
+use super::{check_num_accounts, ExecState};
+
+/// Lock tokens of a virtual timelock account in a new hashlocked escrow,
+/// signed by the owner of the source. See `EscrowFundOp`.
+pub fn escrow_fund(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<EscrowFundOp>(&data.data)?;
+    let args = EscrowFundOp::try_from_bytes(args_data)?.to_struct()?;
+
+    check_num_accounts(data, 3)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let escrow_index = data.mem_indicies[2];
+    let escrow_mem = data.mem_banks[2];
+
+    state.check_is_empty(escrow_mem, escrow_index)?;
+
+    let now = state.get_unix_timestamp()?;
+
+    check_condition(
+        args.refund_after > now,
+        CodeVmError::InvalidTime,
+        "the refund time must be in the future",
+    )?;
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let hash = create_escrow_fund_message(
+        vm,
+        &src_vta,
+        &args.recipient,
+        &args.hashlock,
+        args.refund_after,
+        args.amount,
+        &vdn,
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    if src_vta.balance < args.amount {
+        return Err(CodeVmError::InsufficientFunds.into());
+    }
+
+    src_vta.balance = src_vta.balance
+        .checked_sub(args.amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    // The nonce value is unique per use, so it doubles as the escrow instance.
+    let escrow = VirtualEscrowAccount {
+        instance: vdn.value,
+        sender: src_vta.owner,
+        recipient: args.recipient,
+        hashlock: args.hashlock,
+        refund_after: args.refund_after,
+        balance: args.amount,
+    };
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    state.try_write(
+        escrow_mem,
+        escrow_index,
+        &VirtualAccount::Escrow(escrow)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(())
+}
+
+/// Pay an escrow out to its recipient, given the preimage of its hashlock
+/// before the refund time. See `EscrowClaimOp`.
+pub fn escrow_claim(
+    state: &mut impl ExecState,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let args = EscrowClaimOp::try_from_bytes(&data.data)?;
+
+    check_num_accounts(data, 2)?;
+
+    let escrow_index = data.mem_indicies[0];
+    let escrow_mem = data.mem_banks[0];
+
+    let dst_index = data.mem_indicies[1];
+    let dst_mem = data.mem_banks[1];
+
+    let va = state.try_read(escrow_mem, escrow_index)?;
+    let escrow = va.into_inner_escrow()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut dst_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        hash(args.preimage.as_ref()).eq(&escrow.hashlock),
+        CodeVmError::InvalidCommitment,
+        "the preimage does not match the escrow hashlock",
+    )?;
+
+    let now = state.get_unix_timestamp()?;
+
+    check_condition(
+        now <= escrow.refund_after,
+        CodeVmError::Expired,
+        "the escrow can no longer be claimed",
+    )?;
+
+    check_condition(
+        dst_vta.owner.eq(&escrow.recipient),
+        CodeVmError::InvalidOwner,
+        "the destination is not owned by the escrow recipient",
+    )?;
+
+    dst_vta.balance = dst_vta.balance
+        .checked_add(escrow.balance)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    state.try_delete(
+        escrow_mem,
+        escrow_index
+    )?;
+
+    state.try_write(
+        dst_mem,
+        dst_index,
+        &VirtualAccount::Timelock(dst_vta)
+    )?;
+
+    Ok(())
+}
+
+/// Return an escrow to its sender once the refund time has passed. See
+/// `EscrowRefundOp`.
+pub fn escrow_refund(
+    state: &mut impl ExecState,
+    data: &ExecIxData,
+) -> ProgramResult {
+    EscrowRefundOp::try_from_bytes(&data.data)?;
+
+    check_num_accounts(data, 2)?;
+
+    let escrow_index = data.mem_indicies[0];
+    let escrow_mem = data.mem_banks[0];
+
+    let dst_index = data.mem_indicies[1];
+    let dst_mem = data.mem_banks[1];
+
+    let va = state.try_read(escrow_mem, escrow_index)?;
+    let escrow = va.into_inner_escrow()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut dst_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let now = state.get_unix_timestamp()?;
+
+    check_condition(
+        escrow.refund_after < now,
+        CodeVmError::TooEarly,
+        "the escrow refund time has not passed yet",
+    )?;
+
+    check_condition(
+        dst_vta.owner.eq(&escrow.sender),
+        CodeVmError::InvalidOwner,
+        "the destination is not owned by the escrow sender",
+    )?;
+
+    dst_vta.balance = dst_vta.balance
+        .checked_add(escrow.balance)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    state.try_delete(
+        escrow_mem,
+        escrow_index
+    )?;
+
+    state.try_write(
+        dst_mem,
+        dst_index,
+        &VirtualAccount::Timelock(dst_vta)
+    )?;
+
+    Ok(())
+}
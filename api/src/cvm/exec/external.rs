@@ -0,0 +1,247 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    helpers::check_condition,
+    instruction::ExecIxData,
+    opcode::*,
+};
+use super::{check_num_accounts, credit_fee, ExecState};
+
+/// Debit a virtual timelock account for a transfer to the `destination` token
+/// account, signed by the owner of the source. See `ExternalTransferOp`.
+///
+/// Returns the amount the omnibus must send to `destination`.
+pub fn external_transfer(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+    destination: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let (args_data, valid_until) = split_valid_until::<ExternalTransferOp>(&data.data)?;
+    let args = ExternalTransferOp::try_from_bytes(args_data)?.to_struct()?;
+
+    // The fee-collector account is expected last when the VM charges a fee.
+    let num_accounts = if vm.has_fee() { 3 } else { 2 };
+    check_num_accounts(data, num_accounts)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let fee = vm.get_fee(args.amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let total_amount = args.amount
+        .checked_add(fee)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    if src_vta.balance < total_amount {
+        return Err(CodeVmError::InsufficientFunds.into());
+    }
+
+    let hash = create_transfer_message_to_external(
+        vm,
+        &src_vta,
+        destination,
+        &vdn,
+        args.amount
+    );
+
+    let hash = if vm.has_fee() {
+        create_fee_message(&hash, &vm.fee_collector, fee)
+    } else {
+        hash
+    };
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    src_vta.balance = src_vta.balance
+        .checked_sub(total_amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    if vm.has_fee() {
+        credit_fee(state, vm, data.mem_indicies[2], data.mem_banks[2], fee)?;
+    }
+
+    Ok(args.amount)
+}
+
+/// Close a virtual timelock account for a withdraw of its full balance to the
+/// `destination` token account, signed by the owner. See
+/// `ExternalWithdrawOp`.
+///
+/// Returns the amount the omnibus must send to `destination`.
+pub fn external_withdraw(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+    destination: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let (args_data, valid_until) = split_valid_until::<ExternalWithdrawOp>(&data.data)?;
+    let args = ExternalWithdrawOp::try_from_bytes(args_data)?;
+
+    check_num_accounts(data, 2)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let amount = src_vta.balance;
+
+    let hash = create_withdraw_message_to_external(
+        vm,
+        &src_vta,
+        destination,
+        &vdn,
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_delete(
+        src_mem,
+        src_index,
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(amount)
+}
+
+/// Debit a virtual timelock account for a payment to the target of a virtual
+/// relay account, signed by the owner of the source. The tokens go to the
+/// relay's `destination`, which must be `destination`. See
+/// `ConditionalTransferOp`.
+///
+/// Returns the amount the omnibus must send to `destination`.
+pub fn conditional_transfer(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+    destination: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let (args_data, valid_until) = split_valid_until::<ConditionalTransferOp>(&data.data)?;
+    let args = ConditionalTransferOp::try_from_bytes(args_data)?.to_struct()?;
+
+    check_num_accounts(data, 3)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let vra_index = data.mem_indicies[2];
+    let vra_mem = data.mem_banks[2];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(vra_mem, vra_index)?;
+    let vra = va.into_inner_relay()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        vra.destination.eq(destination),
+        CodeVmError::AccountMismatch,
+        "the virtual relay destination must match the external address",
+    )?;
+
+    let hash = create_transfer_message_to_external(
+        vm,
+        &src_vta,
+        &vra.target,
+        &vdn,
+        args.amount
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    src_vta.balance = src_vta
+        .balance
+        .checked_sub(args.amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(args.amount)
+}
@@ -0,0 +1,141 @@
+//! The state transitions of the VM opcodes.
+//!
+//! The program and the off-chain simulator both run these functions, each
+//! with its own `ExecState`, so that a simulated opcode ends up in exactly the
+//! state the program would produce. Token transfers are not part of the state
+//! transition: opcodes that move real tokens return the amount, and the
+//! program makes the transfer.
+
+mod airdrop;
+mod allowance;
+mod close;
+mod escrow;
+mod external;
+mod multisig;
+mod relay;
+mod stream;
+mod transfer;
+
+pub use airdrop::*;
+pub use allowance::*;
+pub use close::*;
+pub use escrow::*;
+pub use external::*;
+pub use multisig::*;
+pub use relay::*;
+pub use stream::*;
+pub use transfer::*;
+
+use steel::{ProgramError, ProgramResult, Pubkey};
+
+use crate::{
+    cvm::{create_versioned_message, CodeVmAccount, VirtualAccount},
+    error::CodeVmError,
+    helpers::check_condition,
+    instruction::ExecIxData,
+    types::Hash,
+};
+
+/// The memory banks and environment an opcode runs against. `bank` is an
+/// entry of the opcode's `mem_banks`, 0 to 3 for memory A to D.
+pub trait ExecState {
+    fn get_vm_address(&self) -> &Pubkey;
+
+    fn try_read(&self, bank: u8, index: u16) -> Result<VirtualAccount, ProgramError>;
+
+    fn try_write(&mut self, bank: u8, index: u16, va: &VirtualAccount) -> ProgramResult;
+
+    fn try_delete(&mut self, bank: u8, index: u16) -> ProgramResult;
+
+    fn check_is_empty(&self, bank: u8, index: u16) -> ProgramResult;
+
+    /// Verify an ed25519 signature using the path configured on the VM.
+    fn sig_verify(
+        &self,
+        vm: &CodeVmAccount,
+        pubkey: &[u8],
+        sig: &[u8],
+        message: &[u8],
+    ) -> ProgramResult;
+
+    /// The current unix timestamp, as the clock sysvar reports it.
+    fn get_unix_timestamp(&self) -> Result<i64, ProgramError>;
+
+    /// Wrap a signed message in the v1 envelope when the opcode data carries a
+    /// `valid_until`, rejecting the intent if it has expired. A v0 message is
+    /// rejected when the VM requires versioned messages.
+    fn versioned_message(
+        &self,
+        vm: &CodeVmAccount,
+        message: Hash,
+        valid_until: Option<i64>,
+    ) -> Result<Hash, ProgramError> {
+        match valid_until {
+            None => {
+                check_condition(
+                    !vm.requires_versioned_messages(),
+                    CodeVmError::UnversionedMessage,
+                    "the VM requires a versioned signed message",
+                )?;
+
+                Ok(message)
+            }
+            Some(valid_until) => {
+                let now = self.get_unix_timestamp()?;
+
+                check_condition(
+                    now <= valid_until,
+                    CodeVmError::Expired,
+                    "the signed intent has expired",
+                )?;
+
+                Ok(create_versioned_message(self.get_vm_address(), valid_until, &message))
+            }
+        }
+    }
+}
+
+/// Credit the operator fee to the fee-collector virtual timelock account.
+/// This should be the last write of an opcode, so that the collector can
+/// also appear elsewhere in the same opcode (e.g. as a destination).
+pub fn credit_fee(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    fee_index: u16,
+    fee_mem: u8,
+    fee: u64,
+) -> ProgramResult {
+    let va = state.try_read(fee_mem, fee_index)?;
+    let mut fee_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        fee_vta.owner.eq(&vm.fee_collector),
+        CodeVmError::InvalidOwner,
+        "the fee account must be owned by the fee collector",
+    )?;
+
+    fee_vta.balance = fee_vta.balance
+        .checked_add(fee)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    state.try_write(
+        fee_mem,
+        fee_index,
+        &VirtualAccount::Timelock(fee_vta)
+    )
+}
+
+fn check_num_accounts(data: &ExecIxData, num_accounts: usize) -> ProgramResult {
+    check_condition(
+        data.mem_indicies.len() == num_accounts,
+        CodeVmError::InvalidInstructionData,
+        "invalid number of memory indicies",
+    )?;
+
+    check_condition(
+        data.mem_banks.len() == num_accounts,
+        CodeVmError::InvalidInstructionData,
+        "invalid number of memory banks",
+    )
+}
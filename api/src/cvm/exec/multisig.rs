@@ -0,0 +1,184 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    instruction::ExecIxData,
+    opcode::*,
+    utils::multisig_verify_with,
+};
+use super::{check_num_accounts, ExecState};
+
+/// A transfer from a virtual timelock account owned by a multisig, signed by
+/// at least `threshold` of its signers. See `MultisigTransferOp`.
+pub fn multisig_transfer(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args, valid_until) = deserialize_valid_until::<MultisigTransferOpData>(&data.data)?;
+
+    check_num_accounts(data, 3)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let dst_index = data.mem_indicies[2];
+    let dst_mem = data.mem_banks[2];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut dst_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let hash = create_transfer_message(
+        vm,
+        &src_vta,
+        &dst_vta,
+        &vdn,
+        args.amount
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    multisig_verify_with(
+        &src_vta.owner,
+        &args.multisig,
+        &args.signatures,
+        hash.as_ref(),
+        |pubkey, sig, message| state.sig_verify(vm, pubkey, sig, message),
+    )?;
+
+    if src_vta.balance < args.amount {
+        return Err(CodeVmError::InsufficientFunds.into());
+    }
+
+    // If the source and destination accounts are the same, then we don't need
+    // to do anything.
+
+    let is_same_account = src_mem == dst_mem && src_index == dst_index;
+    if !is_same_account {
+        src_vta.balance = src_vta.balance
+            .checked_sub(args.amount)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        dst_vta.balance = dst_vta.balance
+            .checked_add(args.amount)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+    }
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    state.try_write(
+        dst_mem,
+        dst_index,
+        &VirtualAccount::Timelock(dst_vta)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(())
+}
+
+/// A withdraw from a virtual timelock account owned by a multisig, signed by
+/// at least `threshold` of its signers. See `MultisigWithdrawOp`.
+pub fn multisig_withdraw(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args, valid_until) = deserialize_valid_until::<MultisigWithdrawOpData>(&data.data)?;
+
+    check_num_accounts(data, 3)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let dst_index = data.mem_indicies[2];
+    let dst_mem = data.mem_banks[2];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut dst_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let amount = src_vta.balance;
+
+    let hash = create_withdraw_message(
+        vm,
+        &src_vta,
+        &dst_vta,
+        &vdn,
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    multisig_verify_with(
+        &src_vta.owner,
+        &args.multisig,
+        &args.signatures,
+        hash.as_ref(),
+        |pubkey, sig, message| state.sig_verify(vm, pubkey, sig, message),
+    )?;
+
+    // The source is deleted, so only the destination is credited. If they are
+    // the same account, then we don't need to do anything.
+
+    let is_same_account = src_mem == dst_mem && src_index == dst_index;
+    if !is_same_account {
+        dst_vta.balance = dst_vta.balance
+            .checked_add(amount)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+    }
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_delete(
+        src_mem,
+        src_index
+    )?;
+
+    state.try_write(
+        dst_mem,
+        dst_index,
+        &VirtualAccount::Timelock(dst_vta)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    helpers::check_condition,
+    instruction::ExecIxData,
+    opcode::*,
+    pdas::*,
+    types::Hash,
+};
+use super::{check_num_accounts, ExecState};
+
+/// Credit a private payment from the relay to a virtual timelock account and
+/// record it with a new virtual relay account. See `RelayOp`.
+///
+/// Returns the amount the relay vault must send to the omnibus.
+pub fn relay(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+    relay_address: &Pubkey,
+    relay: &mut RelayAccount,
+) -> Result<u64, ProgramError> {
+    let args = RelayOp::try_from_bytes(&data.data)?.to_struct()?;
+
+    check_num_accounts(data, 2)?;
+
+    let dst_index = data.mem_indicies[0];
+    let dst_mem = data.mem_banks[0];
+
+    let vra_index = data.mem_indicies[1];
+    let vra_mem = data.mem_banks[1];
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    vta.balance = vta.balance
+        .checked_add(args.amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    state.check_is_empty(vra_mem, vra_index)?;
+
+    let timelock_address = vta.get_timelock_address(
+        &vm.get_mint(),
+        &vm.get_authority(),
+        vm.get_lock_duration(),
+    );
+    let destination = vta.get_token_address(&timelock_address);
+
+    let vra = try_relay_payment(
+        relay_address,
+        relay,
+        args.amount,
+        &args.transcript,
+        &args.recent_root,
+        &args.commitment,
+        &destination,
+    )?;
+
+    state.try_write(
+        dst_mem,
+        dst_index,
+        &VirtualAccount::Timelock(vta)
+    )?;
+
+    state.try_write(
+        vra_mem,
+        vra_index,
+        &VirtualAccount::Relay(vra)
+    )?;
+
+    Ok(args.amount)
+}
+
+/// Record a private payment from the relay to the `destination` token account
+/// with a new virtual relay account. See `ExternalRelayOp`.
+///
+/// Returns the amount the relay vault must send to `destination`.
+pub fn external_relay(
+    state: &mut impl ExecState,
+    data: &ExecIxData,
+    relay_address: &Pubkey,
+    relay: &mut RelayAccount,
+    destination: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let args = ExternalRelayOp::try_from_bytes(&data.data)?.to_struct()?;
+
+    check_num_accounts(data, 1)?;
+
+    let vra_index = data.mem_indicies[0];
+    let vra_mem = data.mem_banks[0];
+
+    state.check_is_empty(vra_mem, vra_index)?;
+
+    let vra = try_relay_payment(
+        relay_address,
+        relay,
+        args.amount,
+        &args.transcript,
+        &args.recent_root,
+        &args.commitment,
+        destination,
+    )?;
+
+    state.try_write(
+        vra_mem,
+        vra_index,
+        &VirtualAccount::Relay(vra)
+    )?;
+
+    Ok(args.amount)
+}
+
+/// Check a relay payment to `destination` and add its commitment to the
+/// relay. Returns the virtual relay account that records the payment.
+fn try_relay_payment(
+    relay_address: &Pubkey,
+    relay: &mut RelayAccount,
+    amount: u64,
+    transcript: &Hash,
+    recent_root: &Hash,
+    commitment: &Pubkey,
+    destination: &Pubkey,
+) -> Result<VirtualRelayAccount, ProgramError> {
+    check_condition(
+        relay.recent_roots.contains(recent_root.as_ref()),
+        CodeVmError::StaleMerkleProof,
+        "the provided recent_root was not found in the relay recent_root list",
+    )?;
+
+    let (expected, _) = find_relay_commitment_address( // <- expensive
+        relay_address,
+        recent_root,
+        transcript, // Contains the "source" but is hashed :)
+        destination,
+        amount,
+    );
+
+    check_condition(
+        expected.eq(commitment),
+        CodeVmError::InvalidCommitment,
+        "the provided commitment does not match the calculated commitment",
+    )?;
+
+    // Add the commitment address to the merkle tree
+    relay.add_commitment(&expected)?;
+
+    // Find the virtual relay address
+    let (proof_address, _) = find_relay_proof_address( // <- expensive
+        relay_address,
+        recent_root,
+        commitment,
+    );
+
+    let (vault_address, _) = find_relay_destination( // <- expensive
+        &proof_address,
+    );
+
+    Ok(VirtualRelayAccount {
+        target: vault_address,
+        destination: relay.treasury.vault,
+    })
+}
@@ -0,0 +1,294 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    helpers::check_condition,
+    instruction::ExecIxData,
+    opcode::*,
+};
+use super::{check_num_accounts, ExecState};
+
+/// Lock tokens of a virtual timelock account in a new stream that vests them
+/// to a beneficiary, signed by the owner of the source. See `StreamCreateOp`.
+pub fn stream_create(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<StreamCreateOp>(&data.data)?;
+    let args = StreamCreateOp::try_from_bytes(args_data)?.to_struct()?;
+
+    check_num_accounts(data, 3)?;
+
+    check_condition(
+        args.start_time < args.end_time,
+        CodeVmError::InvalidTime,
+        "the stream end time must be after the start time",
+    )?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let stream_index = data.mem_indicies[2];
+    let stream_mem = data.mem_banks[2];
+
+    state.check_is_empty(stream_mem, stream_index)?;
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let hash = create_stream_create_message(
+        vm,
+        &src_vta,
+        &args.beneficiary,
+        args.amount,
+        args.start_time,
+        args.end_time,
+        &vdn,
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    if src_vta.balance < args.amount {
+        return Err(CodeVmError::InsufficientFunds.into());
+    }
+
+    src_vta.balance = src_vta.balance
+        .checked_sub(args.amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    // The nonce value is unique per use, so it doubles as the stream instance.
+    let stream = VirtualStreamAccount {
+        instance: vdn.value,
+        funder: src_vta.owner,
+        beneficiary: args.beneficiary,
+        start_time: args.start_time,
+        end_time: args.end_time,
+        total_amount: args.amount,
+        withdrawn: 0,
+    };
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    state.try_write(
+        stream_mem,
+        stream_index,
+        &VirtualAccount::Stream(stream)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(())
+}
+
+/// Pay what has vested so far out to the beneficiary of a stream. See
+/// `StreamWithdrawOp`.
+pub fn stream_withdraw(
+    state: &mut impl ExecState,
+    data: &ExecIxData,
+) -> ProgramResult {
+    StreamWithdrawOp::try_from_bytes(&data.data)?;
+
+    check_num_accounts(data, 2)?;
+
+    let stream_index = data.mem_indicies[0];
+    let stream_mem = data.mem_banks[0];
+
+    let dst_index = data.mem_indicies[1];
+    let dst_mem = data.mem_banks[1];
+
+    let va = state.try_read(stream_mem, stream_index)?;
+    let mut stream = va.into_inner_stream()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut dst_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        dst_vta.owner.eq(&stream.beneficiary),
+        CodeVmError::InvalidOwner,
+        "the destination is not owned by the stream beneficiary",
+    )?;
+
+    let now = state.get_unix_timestamp()?;
+    let amount = stream.get_withdrawable_amount(now);
+
+    check_condition(
+        amount > 0,
+        CodeVmError::TooEarly,
+        "nothing has vested since the last withdrawal",
+    )?;
+
+    stream.withdrawn = stream.withdrawn
+        .checked_add(amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    dst_vta.balance = dst_vta.balance
+        .checked_add(amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    if stream.get_balance() == 0 {
+        state.try_delete(
+            stream_mem,
+            stream_index
+        )?;
+    } else {
+        state.try_write(
+            stream_mem,
+            stream_index,
+            &VirtualAccount::Stream(stream)
+        )?;
+    }
+
+    state.try_write(
+        dst_mem,
+        dst_index,
+        &VirtualAccount::Timelock(dst_vta)
+    )?;
+
+    Ok(())
+}
+
+/// Cancel a stream, paying what has vested to the beneficiary and the rest
+/// back to the funder, signed by the funder. See `StreamCancelOp`.
+pub fn stream_cancel(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<StreamCancelOp>(&data.data)?;
+    let args = StreamCancelOp::try_from_bytes(args_data)?;
+
+    check_num_accounts(data, 4)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let stream_index = data.mem_indicies[1];
+    let stream_mem = data.mem_banks[1];
+
+    let funder_index = data.mem_indicies[2];
+    let funder_mem = data.mem_banks[2];
+
+    let beneficiary_index = data.mem_indicies[3];
+    let beneficiary_mem = data.mem_banks[3];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(stream_mem, stream_index)?;
+    let stream = va.into_inner_stream()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(funder_mem, funder_index)?;
+    let mut funder_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(beneficiary_mem, beneficiary_index)?;
+    let mut beneficiary_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    check_condition(
+        funder_vta.owner.eq(&stream.funder),
+        CodeVmError::InvalidOwner,
+        "the funder account is not owned by the stream funder",
+    )?;
+
+    check_condition(
+        beneficiary_vta.owner.eq(&stream.beneficiary),
+        CodeVmError::InvalidOwner,
+        "the beneficiary account is not owned by the stream beneficiary",
+    )?;
+
+    let hash = create_stream_cancel_message(
+        &stream,
+        &vdn,
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        stream.funder.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    let now = state.get_unix_timestamp()?;
+    let vested = stream.get_withdrawable_amount(now);
+    let unvested = stream.get_balance()
+        .checked_sub(vested)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    // Credit both sides in a single place, the funder and beneficiary could
+    // be the same account.
+    let is_same_account = (funder_mem == beneficiary_mem) && (funder_index == beneficiary_index);
+    if is_same_account {
+        funder_vta.balance = funder_vta.balance
+            .checked_add(stream.get_balance())
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+    } else {
+        funder_vta.balance = funder_vta.balance
+            .checked_add(unvested)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        beneficiary_vta.balance = beneficiary_vta.balance
+            .checked_add(vested)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        state.try_write(
+            beneficiary_mem,
+            beneficiary_index,
+            &VirtualAccount::Timelock(beneficiary_vta)
+        )?;
+    }
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_delete(
+        stream_mem,
+        stream_index
+    )?;
+
+    state.try_write(
+        funder_mem,
+        funder_index,
+        &VirtualAccount::Timelock(funder_vta)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(())
+}
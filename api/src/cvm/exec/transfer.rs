@@ -0,0 +1,210 @@
+use steel::*;
+
+use crate::{
+    cvm::*,
+    error::CodeVmError,
+    instruction::ExecIxData,
+    opcode::*,
+};
+use super::{check_num_accounts, credit_fee, ExecState};
+
+/// Transfer tokens between two virtual timelock accounts, signed by the owner
+/// of the source. See `TransferOp`.
+pub fn transfer(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<TransferOp>(&data.data)?;
+    let args = TransferOp::try_from_bytes(args_data)?.to_struct()?;
+
+    // The fee-collector account is expected last when the VM charges a fee.
+    let num_accounts = if vm.has_fee() { 4 } else { 3 };
+    check_num_accounts(data, num_accounts)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let dst_index = data.mem_indicies[2];
+    let dst_mem = data.mem_banks[2];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let mut src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut dst_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let fee = vm.get_fee(args.amount)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    let hash = create_transfer_message(
+        vm,
+        &src_vta,
+        &dst_vta,
+        &vdn,
+        args.amount
+    );
+
+    let hash = if vm.has_fee() {
+        create_fee_message(&hash, &vm.fee_collector, fee)
+    } else {
+        hash
+    };
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    let total_amount = args.amount
+        .checked_add(fee)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    if src_vta.balance < total_amount {
+        return Err(CodeVmError::InsufficientFunds.into());
+    }
+
+    // If the source and destination accounts are the same, then we don't need
+    // to do anything.
+
+    let is_same_account = src_mem == dst_mem && src_index == dst_index;
+    if !is_same_account {
+        src_vta.balance = src_vta.balance
+            .checked_sub(args.amount)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        dst_vta.balance = dst_vta.balance
+            .checked_add(args.amount)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+    }
+
+    src_vta.balance = src_vta.balance
+        .checked_sub(fee)
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_write(
+        src_mem,
+        src_index,
+        &VirtualAccount::Timelock(src_vta)
+    )?;
+
+    // The destination is a stale copy of the source when they are the same
+    // account, writing it would undo the fee.
+    if !is_same_account {
+        state.try_write(
+            dst_mem,
+            dst_index,
+            &VirtualAccount::Timelock(dst_vta)
+        )?;
+    }
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    if vm.has_fee() {
+        credit_fee(state, vm, data.mem_indicies[3], data.mem_banks[3], fee)?;
+    }
+
+    Ok(())
+}
+
+/// Move the full balance of a virtual timelock account into another one and
+/// close it, signed by the owner of the source. See `WithdrawOp`.
+pub fn withdraw(
+    state: &mut impl ExecState,
+    vm: &CodeVmAccount,
+    data: &ExecIxData,
+) -> ProgramResult {
+    let (args_data, valid_until) = split_valid_until::<WithdrawOp>(&data.data)?;
+    let args = WithdrawOp::try_from_bytes(args_data)?;
+
+    check_num_accounts(data, 3)?;
+
+    let nonce_index = data.mem_indicies[0];
+    let nonce_mem = data.mem_banks[0];
+
+    let src_index = data.mem_indicies[1];
+    let src_mem = data.mem_banks[1];
+
+    let dst_index = data.mem_indicies[2];
+    let dst_mem = data.mem_banks[2];
+
+    let va = state.try_read(nonce_mem, nonce_index)?;
+    let mut vdn = va.into_inner_nonce()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(src_mem, src_index)?;
+    let src_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let va = state.try_read(dst_mem, dst_index)?;
+    let mut dst_vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
+
+    let amount = src_vta.balance;
+
+    let hash = create_withdraw_message(
+        vm,
+        &src_vta,
+        &dst_vta,
+        &vdn,
+    );
+
+    let hash = state.versioned_message(vm, hash, valid_until)?;
+
+    state.sig_verify(
+        vm,
+        src_vta.owner.as_ref(),
+        args.signature.as_ref(),
+        hash.as_ref(),
+    )?;
+
+    // The source is deleted, so only the destination is credited. If they are
+    // the same account, then we don't need to do anything.
+
+    let is_same_account = src_mem == dst_mem && src_index == dst_index;
+    if !is_same_account {
+        dst_vta.balance = dst_vta.balance
+            .checked_add(amount)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+    }
+
+    vdn.value = vm.get_current_poh();
+
+    state.try_delete(
+        src_mem,
+        src_index
+    )?;
+
+    state.try_write(
+        dst_mem,
+        dst_index,
+        &VirtualAccount::Timelock(dst_vta)
+    )?;
+
+    state.try_write(
+        nonce_mem,
+        nonce_index,
+        &VirtualAccount::Nonce(vdn)
+    )?;
+
+    Ok(())
+}
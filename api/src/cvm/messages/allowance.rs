@@ -0,0 +1,113 @@
+use steel::*;
+
+use crate::utils;
+use crate::types::Hash;
+use crate::cvm::{
+    CodeVmAccount,
+    VirtualAllowanceAccount,
+    VirtualDurableNonce, 
+    VirtualTimelockAccount
+};
+
+pub fn compact_approve_message(
+    src_timelock_address: &Pubkey,
+    delegate: &Pubkey,
+    amount: u64,
+    expires_at: i64,
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+    let message = &[
+        b"approve",
+        src_timelock_address.as_ref(),
+        delegate.as_ref(),
+        &amount.to_le_bytes(),
+        &expires_at.to_le_bytes(),
+        vdn.address.as_ref(),
+        vdn.value.as_ref(), // this value is auto-advanced upon use
+    ];
+
+    utils::hashv(message)
+}
+
+pub fn create_approve_message(
+    vm: &CodeVmAccount,
+    src_vta: &VirtualTimelockAccount,
+    delegate: &Pubkey,
+    amount: u64,
+    expires_at: i64,
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+
+    let src_timelock_address = src_vta.get_timelock_address(
+        &vm.get_mint(),
+        &vm.get_authority(),
+        vm.get_lock_duration(),
+    );
+    let src_token_address = src_vta.get_token_address(
+        &src_timelock_address,
+    );
+
+    compact_approve_message(
+        &src_token_address,
+        delegate,
+        amount,
+        expires_at,
+        vdn,
+    )
+}
+
+pub fn compact_transfer_from_message(
+    allowance: &VirtualAllowanceAccount,
+    src_timelock_address: &Pubkey,
+    dst_timelock_address: &Pubkey,
+    amount: u64,
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+    let message = &[
+        b"transfer_from",
+        allowance.instance.as_ref(),
+        src_timelock_address.as_ref(),
+        dst_timelock_address.as_ref(),
+        &amount.to_le_bytes(),
+        vdn.address.as_ref(),
+        vdn.value.as_ref(), // this value is auto-advanced upon use
+    ];
+
+    utils::hashv(message)
+}
+
+pub fn create_transfer_from_message(
+    vm: &CodeVmAccount,
+    allowance: &VirtualAllowanceAccount,
+    src_vta: &VirtualTimelockAccount,
+    dst_vta: &VirtualTimelockAccount,
+    vdn: &VirtualDurableNonce,
+    amount: u64,
+) -> Hash {
+
+    let src_timelock_address = src_vta.get_timelock_address(
+        &vm.get_mint(),
+        &vm.get_authority(),
+        vm.get_lock_duration(),
+    );
+    let src_token_address = src_vta.get_token_address(
+        &src_timelock_address,
+    );
+
+    let dst_timelock_address = dst_vta.get_timelock_address(
+        &vm.get_mint(),
+        &vm.get_authority(),
+        vm.get_lock_duration(),
+    );
+    let dst_token_address = dst_vta.get_token_address(
+        &dst_timelock_address,
+    );
+
+    compact_transfer_from_message(
+        allowance,
+        &src_token_address,
+        &dst_token_address,
+        amount,
+        vdn,
+    )
+}
@@ -0,0 +1,22 @@
+use steel::*;
+
+use crate::utils;
+use crate::types::Hash;
+use crate::cvm::VirtualAccount;
+
+/// The message an owner signs to close one of their virtual accounts. It
+/// commits to the exact account state, so it can't be replayed against a
+/// different account in the same slot.
+pub fn create_close_message(
+    vm_address: &Pubkey,
+    va: &VirtualAccount,
+) -> Hash {
+    let va_hash = va.get_hash();
+    let message: &[&[u8]] = &[
+        b"close",
+        vm_address.as_ref(),
+        va_hash.as_ref(),
+    ];
+
+    utils::hashv(message)
+}
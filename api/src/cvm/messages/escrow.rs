@@ -0,0 +1,60 @@
+use steel::*;
+
+use crate::utils;
+use crate::types::Hash;
+use crate::cvm::{
+    CodeVmAccount,
+    VirtualDurableNonce, 
+    VirtualTimelockAccount
+};
+
+pub fn compact_escrow_fund_message(
+    src_timelock_address: &Pubkey,
+    recipient: &Pubkey,
+    hashlock: &Hash,
+    refund_after: i64,
+    amount: u64,
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+    let message = &[
+        b"escrow_fund",
+        src_timelock_address.as_ref(),
+        recipient.as_ref(),
+        hashlock.as_ref(),
+        &refund_after.to_le_bytes(),
+        &amount.to_le_bytes(),
+        vdn.address.as_ref(),
+        vdn.value.as_ref(), // this value is auto-advanced upon use
+    ];
+
+    utils::hashv(message)
+}
+
+pub fn create_escrow_fund_message(
+    vm: &CodeVmAccount,
+    src_vta: &VirtualTimelockAccount,
+    recipient: &Pubkey,
+    hashlock: &Hash,
+    refund_after: i64,
+    amount: u64,
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+
+    let src_timelock_address = src_vta.get_timelock_address(
+        &vm.get_mint(),
+        &vm.get_authority(),
+        vm.get_lock_duration(),
+    );
+    let src_token_address = src_vta.get_token_address(
+        &src_timelock_address,
+    );
+
+    compact_escrow_fund_message(
+        &src_token_address,
+        recipient,
+        hashlock,
+        refund_after,
+        amount,
+        vdn,
+    )
+}
@@ -0,0 +1,21 @@
+use steel::*;
+
+use crate::utils;
+use crate::types::Hash;
+
+/// Binds an operator fee to a signed message, so the owner explicitly
+/// authorizes both the fee amount and who collects it.
+pub fn create_fee_message(
+    message: &Hash,
+    fee_collector: &Pubkey,
+    fee: u64,
+) -> Hash {
+    let message: &[&[u8]] = &[
+        b"fee",
+        message.as_ref(),
+        fee_collector.as_ref(),
+        &fee.to_le_bytes(),
+    ];
+
+    utils::hashv(message)
+}
@@ -1,7 +1,23 @@
 mod airdrop;
+mod allowance;
+mod close;
+mod escrow;
+mod fee;
+mod payout;
+mod stream;
 mod transfer;
+mod unlock;
+mod version;
 mod withdraw;
 
 pub use airdrop::*;
+pub use allowance::*;
+pub use close::*;
+pub use escrow::*;
+pub use fee::*;
+pub use payout::*;
+pub use stream::*;
 pub use transfer::*;
+pub use unlock::*;
+pub use version::*;
 pub use withdraw::*;
\ No newline at end of file
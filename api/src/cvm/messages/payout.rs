@@ -0,0 +1,60 @@
+use steel::*;
+
+use crate::utils;
+use crate::types::Hash;
+use crate::cvm::{
+    CodeVmAccount,
+    VirtualDurableNonce, 
+    VirtualTimelockAccount
+};
+
+pub fn compact_payout_message(
+    src_timelock_address: &Pubkey,
+    payouts: &[(Pubkey, u64)],
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+    let mut msg = vec![
+        b"payout" as &[u8],
+        src_timelock_address.as_ref(),
+        vdn.address.as_ref(),
+        vdn.value.as_ref(),
+    ];
+
+    // Store the little-endian bytes in a local variable so they won't go out of scope
+    let amount_bytes: Vec<[u8; 8]> = payouts
+        .iter()
+        .map(|(_, amount)| amount.to_le_bytes())
+        .collect();
+
+    // Push each (destination, amount) pair
+    for (i, (dst_pubkey, _)) in payouts.iter().enumerate() {
+        msg.push(dst_pubkey.as_ref());
+        msg.push(&amount_bytes[i]);
+    }
+
+    utils::hashv(&msg)
+}
+
+pub fn create_payout_message(
+    vm: &CodeVmAccount,
+    src_vta: &VirtualTimelockAccount,
+    payouts: &[(Pubkey, u64)],
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+
+    let src_timelock_address = src_vta.get_timelock_address(
+        &vm.get_mint(),
+        &vm.get_authority(),
+        vm.get_lock_duration(),
+    );
+
+    let src_token_address = src_vta.get_token_address(
+        &src_timelock_address,
+    );
+
+    compact_payout_message(
+        &src_token_address,
+        payouts,
+        vdn,
+    )
+}
@@ -0,0 +1,75 @@
+use steel::*;
+
+use crate::utils;
+use crate::types::Hash;
+use crate::cvm::{
+    CodeVmAccount,
+    VirtualDurableNonce, 
+    VirtualStreamAccount,
+    VirtualTimelockAccount
+};
+
+pub fn compact_stream_create_message(
+    src_timelock_address: &Pubkey,
+    beneficiary: &Pubkey,
+    amount: u64,
+    start_time: i64,
+    end_time: i64,
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+    let message = &[
+        b"stream_create",
+        src_timelock_address.as_ref(),
+        beneficiary.as_ref(),
+        &amount.to_le_bytes(),
+        &start_time.to_le_bytes(),
+        &end_time.to_le_bytes(),
+        vdn.address.as_ref(),
+        vdn.value.as_ref(), // this value is auto-advanced upon use
+    ];
+
+    utils::hashv(message)
+}
+
+pub fn create_stream_create_message(
+    vm: &CodeVmAccount,
+    src_vta: &VirtualTimelockAccount,
+    beneficiary: &Pubkey,
+    amount: u64,
+    start_time: i64,
+    end_time: i64,
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+
+    let src_timelock_address = src_vta.get_timelock_address(
+        &vm.get_mint(),
+        &vm.get_authority(),
+        vm.get_lock_duration(),
+    );
+    let src_token_address = src_vta.get_token_address(
+        &src_timelock_address,
+    );
+
+    compact_stream_create_message(
+        &src_token_address,
+        beneficiary,
+        amount,
+        start_time,
+        end_time,
+        vdn,
+    )
+}
+
+pub fn create_stream_cancel_message(
+    stream: &VirtualStreamAccount,
+    vdn: &VirtualDurableNonce,
+) -> Hash {
+    let message = &[
+        b"stream_cancel",
+        stream.instance.as_ref(),
+        vdn.address.as_ref(),
+        vdn.value.as_ref(), // this value is auto-advanced upon use
+    ];
+
+    utils::hashv(message)
+}
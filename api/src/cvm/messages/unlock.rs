@@ -0,0 +1,54 @@
+use steel::*;
+
+use crate::utils;
+use crate::types::Hash;
+
+/// The message the signers of a multisig owner sign to start the unlock of
+/// its timelock account. The unlock PDA can only be created once, so it can't
+/// be replayed.
+pub fn create_init_unlock_message(
+    vm_address: &Pubkey,
+    owner: &Pubkey,
+) -> Hash {
+    let message: &[&[u8]] = &[
+        b"init_unlock",
+        vm_address.as_ref(),
+        owner.as_ref(),
+    ];
+
+    utils::hashv(message)
+}
+
+/// The message the signers of a multisig owner sign to finalize the unlock of
+/// its timelock account.
+pub fn create_unlock_message(
+    vm_address: &Pubkey,
+    owner: &Pubkey,
+) -> Hash {
+    let message: &[&[u8]] = &[
+        b"unlock",
+        vm_address.as_ref(),
+        owner.as_ref(),
+    ];
+
+    utils::hashv(message)
+}
+
+/// The message the signers of a multisig owner sign to withdraw its unlocked
+/// tokens. It commits to the destination only, so the same signatures can be
+/// used to withdraw each of the owner's accounts and deposits, but never to
+/// send them anywhere else.
+pub fn create_withdraw_unlocked_message(
+    vm_address: &Pubkey,
+    owner: &Pubkey,
+    external_address: &Pubkey,
+) -> Hash {
+    let message: &[&[u8]] = &[
+        b"withdraw_unlocked",
+        vm_address.as_ref(),
+        owner.as_ref(),
+        external_address.as_ref(),
+    ];
+
+    utils::hashv(message)
+}
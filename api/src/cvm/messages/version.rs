@@ -0,0 +1,72 @@
+use borsh::BorshDeserialize;
+use steel::*;
+
+use crate::utils;
+use crate::types::Hash;
+
+pub const MESSAGE_V1: u8 = 1;
+pub const VALID_UNTIL_LEN: usize = 8;
+
+/// Wraps a (v0) message so that the signature is only valid for this program,
+/// the given VM, and until `valid_until` (unix timestamp, inclusive).
+pub fn create_versioned_message(
+    vm_address: &Pubkey,
+    valid_until: i64,
+    message: &Hash,
+) -> Hash {
+    let message: &[&[u8]] = &[
+        b"code_vm_message",
+        &[MESSAGE_V1],
+        crate::ID.as_ref(),
+        vm_address.as_ref(),
+        &valid_until.to_le_bytes(),
+        message.as_ref(),
+    ];
+
+    utils::hashv(message)
+}
+
+/// Opcode data for `T` can optionally be followed by a `valid_until` (i64),
+/// which means the signature is over a v1 message. Returns the opcode data
+/// and the expiry, if any.
+pub fn split_valid_until<T: Pod>(data: &[u8]) -> Result<(&[u8], Option<i64>), ProgramError> {
+    let len = std::mem::size_of::<T>();
+
+    if data.len() == len {
+        return Ok((data, None));
+    }
+
+    if data.len() != len + VALID_UNTIL_LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let (args, suffix) = data.split_at(len);
+    let valid_until = i64::from_le_bytes(suffix.try_into().unwrap());
+
+    Ok((args, Some(valid_until)))
+}
+
+/// Same as `split_valid_until`, for opcodes whose data is borsh encoded.
+/// Returns the decoded opcode data and the expiry, if any.
+pub fn deserialize_valid_until<T: BorshDeserialize>(data: &[u8]) -> Result<(T, Option<i64>), ProgramError> {
+    let mut rest = data;
+    let args = T::deserialize(&mut rest)?;
+
+    if rest.is_empty() {
+        return Ok((args, None));
+    }
+
+    if rest.len() != VALID_UNTIL_LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let valid_until = i64::from_le_bytes(rest.try_into().unwrap());
+
+    Ok((args, Some(valid_until)))
+}
+
+/// Appends a `valid_until` to serialized opcode data (see `split_valid_until`).
+pub fn append_valid_until(mut data: Vec<u8>, valid_until: i64) -> Vec<u8> {
+    data.extend_from_slice(&valid_until.to_le_bytes());
+    data
+}
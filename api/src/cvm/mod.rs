@@ -2,8 +2,10 @@ pub mod account;
 pub mod pool;
 pub mod state;
 pub mod messages;
+pub mod exec;
 
 pub use account::*;
 pub use pool::*;
 pub use state::*;
-pub use messages::*;
\ No newline at end of file
+pub use messages::*;
+pub use exec::ExecState;
\ No newline at end of file
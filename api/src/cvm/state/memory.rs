@@ -2,6 +2,7 @@ use steel::*;
 use std::{cell::{Ref, RefMut}, marker::PhantomData};
 use crate::{
     consts::*, 
+    cvm::VirtualAccount,
     types::SliceAllocator
 };
 
@@ -12,6 +13,28 @@ pub enum MemoryVersion {
     Current = 1,
 }
 
+/// The single virtual account type a legacy memory account was created for,
+/// stored in the last byte of `packed_info`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LegacyMemoryLayout {
+    Unknown = 0,
+    Timelock = 1,
+    Nonce = 2,
+    Relay = 3,
+}
+
+impl LegacyMemoryLayout {
+    pub fn allows(&self, va: &VirtualAccount) -> bool {
+        match self {
+            LegacyMemoryLayout::Unknown => true,
+            LegacyMemoryLayout::Timelock => va.is_timelock(),
+            LegacyMemoryLayout::Nonce => va.is_nonce(),
+            LegacyMemoryLayout::Relay => va.is_relay(),
+        }
+    }
+}
+
 #[repr(C, packed)] 
 #[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
 pub struct MemoryAccount {
@@ -82,4 +105,34 @@ impl MemoryAccount {
         }
     }
 
+    pub fn get_legacy_layout(&self) -> LegacyMemoryLayout {
+        match self.packed_info[5] {
+            1 => LegacyMemoryLayout::Timelock,
+            2 => LegacyMemoryLayout::Nonce,
+            3 => LegacyMemoryLayout::Relay,
+            _ => LegacyMemoryLayout::Unknown,
+        }
+    }
+
+    /// The index of the next account to migrate, while a legacy memory account
+    /// is being upgraded to the current format (see MigrateMemoryIx).
+    pub fn get_migration_cursor(&self) -> u32 {
+        let cursor = self.packed_info;
+        u32::from_le_bytes([cursor[0], cursor[1], cursor[2], cursor[3]])
+    }
+
+    pub fn set_migration_cursor(&mut self, cursor: u32) {
+        self.packed_info[..4].copy_from_slice(&cursor.to_le_bytes());
+    }
+
+    /// Whether the VM counts this account in `num_children`. Legacy memory
+    /// accounts, and any created before the count was added, are not counted.
+    pub fn is_counted(&self) -> bool {
+        self.packed_info[4] != 0
+    }
+
+    pub fn set_counted(&mut self) {
+        self.packed_info[4] = 1;
+    }
+
 }
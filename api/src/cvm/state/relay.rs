@@ -18,8 +18,9 @@ pub struct RelayAccount {
     pub bump: u8,
     pub num_levels: u8,
     pub num_history: u8,
+    pub counted: u8, // set when the VM counts this account in num_children
 
-    _padding: [u8; 4],
+    _padding: [u8; 3],
 
     pub recent_roots: CircularBuffer<{RELAY_HISTORY_ITEMS}, {Hash::LEN}>,
     pub history: MerkleTree<{RELAY_STATE_DEPTH}>,
@@ -1,6 +1,22 @@
 use steel::*;
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
 
-use crate::{consts::*, types::MerkleTree};
+use std::cell::RefMut;
+
+use crate::{consts::*, types::{ChangeLog, Hash, MerkleTree}};
+
+pub type StorageChangeLog = ChangeLog<{COMPRESSED_STATE_DEPTH}, {COMPRESSED_STATE_CHANGELOG_SIZE}>;
+
+// Storage accounts are created with a CPI, which can't allocate more than
+// MAX_PERMITTED_DATA_INCREASE bytes.
+const _: () = assert!(StorageAccount::get_size_with_changelog() <= MAX_PERMITTED_DATA_INCREASE);
+
+// A storage account created without a changelog gets one on its first
+// rollover, and an instruction can only grow an account by as much.
+const _: () = assert!(
+    StorageAccount::get_size_with_archived_trees(1) - StorageAccount::get_size()
+        <= MAX_PERMITTED_DATA_INCREASE
+);
 
 #[repr(C, align(8))]
 #[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
@@ -9,8 +25,10 @@ pub struct StorageAccount {
     pub name: [u8; MAX_NAME_LEN],
     pub bump: u8,
     pub depth: u8,
+    pub tree_id: u16,
+    pub counted: u8, // set when the VM counts this account in num_children
 
-    _padding: [u8; 6],
+    _padding: [u8; 3],
     pub compressed_state: MerkleTree<{COMPRESSED_STATE_DEPTH}>,
 }
 
@@ -21,6 +39,54 @@ impl StorageAccount {
         8 + std::mem::size_of::<Self>()
     }
 
+    /// The size of a storage account that also keeps a changelog of recent
+    /// tree updates after the account state. Older storage accounts were
+    /// created without one.
+    pub const fn get_size_with_changelog() -> usize {
+        Self::get_size() + StorageChangeLog::get_size()
+    }
+
+    pub fn get_changelog_mut<'a>(info: &'a AccountInfo) 
+        -> Result<Option<RefMut<'a, StorageChangeLog>>, ProgramError> {
+        if info.data_len() < Self::get_size_with_changelog() {
+            return Ok(None);
+        }
+
+        let data = info.try_borrow_mut_data()?;
+        let offset = Self::get_size();
+        let changelog = RefMut::map(data, |d| {
+            bytemuck::from_bytes_mut::<StorageChangeLog>(
+                &mut d[offset..offset + StorageChangeLog::get_size()]
+            )
+        });
+        Ok(Some(changelog))
+    }
+
+    /// The size of a storage account that has rolled over `num_archived`
+    /// trees. The root of each archived tree is kept after the changelog,
+    /// indexed by tree id.
+    pub const fn get_size_with_archived_trees(num_archived: usize) -> usize {
+        Self::get_size_with_changelog() + num_archived * std::mem::size_of::<Hash>()
+    }
+
+    pub fn get_archived_roots_mut<'a>(info: &'a AccountInfo) 
+        -> Result<RefMut<'a, [Hash]>, ProgramError> {
+        let num_archived = info.to_account::<Self>(&crate::ID)?.tree_id as usize;
+
+        if info.data_len() < Self::get_size_with_archived_trees(num_archived) {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let data = info.try_borrow_mut_data()?;
+        let offset = Self::get_size_with_changelog();
+        let roots = RefMut::map(data, |d| {
+            bytemuck::cast_slice_mut::<u8, Hash>(
+                &mut d[offset..offset + num_archived * std::mem::size_of::<Hash>()]
+            )
+        });
+        Ok(roots)
+    }
+
     pub fn get_compressed_state_mut<'a>(info: &'a AccountInfo) 
         -> Result<&'a mut MerkleTree<{COMPRESSED_STATE_DEPTH}>, ProgramError> {
         let storage = info.to_account_mut::<Self>(&crate::ID)?;
@@ -1,12 +1,22 @@
 use steel::*;
 
 use crate::{
+    consts::MAX_FEE_BPS,
     cvm::TokenPool, 
+    error::CodeVmError,
+    event::InstructionEvent,
     instruction::CodeInstruction, 
     types::Hash, 
     utils
 };
 
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromPrimitive)]
+pub enum SigVerifyMode {
+    Program = 0,    // ed25519 verified in-program with curve25519 syscalls
+    Precompile,     // ed25519 verified by a preceding Ed25519 program instruction
+}
+
 #[repr(C, align(8))]
 #[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
 pub struct CodeVmAccount {
@@ -17,8 +27,26 @@ pub struct CodeVmAccount {
     pub omnibus: TokenPool,
     pub lock_duration: u8,  // in days
     pub bump: u8,
+    pub sig_verify_mode: u8,
+
+    _padding: [u8; 4],
+
+    pub fee_collector: Pubkey, // owner of the fee-collector virtual timelock account
+    pub fee_flat: u64,
+    pub fee_bps: u16,
+
+    _fee_padding: [u8; 6],
+
+    pub current_authority: Pubkey, // zero until the authority is first rotated
+    pub pending_authority: Pubkey,  // proposed by the current authority, zero if none
+
+    pub disabled_opcodes: [u8; 32], // one bit per opcode, set when the opcode is disabled
+    pub paused: u8,
+    pub require_versioned: u8, // set when signed messages must use the v1 envelope
 
-    _padding: [u8; 5],
+    _pause_padding: [u8; 2],
+
+    pub num_children: u32, // memory, storage and relay accounts that are still open
 }
 
 impl CodeVmAccount {
@@ -26,6 +54,15 @@ impl CodeVmAccount {
         8 + std::mem::size_of::<Self>()
     }
 
+    /// The size of a VM account created before the sig verify mode, fee,
+    /// authority rotation and pause fields were added. Everything after `bump`
+    /// was padding, and all of these fields default to zero, so a legacy
+    /// account only needs to grow (see MigrateVmIx). The fee fields were the
+    /// first to grow the account, so the legacy size ends where they start.
+    pub const fn get_legacy_size() -> usize {
+        8 + std::mem::offset_of!(Self, fee_collector)
+    }
+
     pub fn unpack(data: &[u8]) -> Self {
         let data = &data[..Self::get_size()];
         Self::try_from_bytes(data).unwrap().clone()
@@ -46,19 +83,35 @@ impl CodeVmAccount {
         accounts: &[AccountInfo],
         data: &[u8],
     ) {
+        self.poh = self.get_next_poh(ix, accounts.iter().map(|account| account.key), data);
+        self.advance_slot();
+
+        InstructionEvent {
+            instruction: ix as u8,
+            slot: self.slot,
+            poh: self.poh,
+        }.log();
+    }
+
+    /// The poh after an instruction with these account keys and data, without
+    /// the instruction discriminator.
+    pub fn get_next_poh<'a>(
+        &self,
+        ix: CodeInstruction,
+        keys: impl IntoIterator<Item = &'a Pubkey>,
+        data: &[u8],
+    ) -> Hash {
         let mut message = Vec::new();
-        for account in accounts {
-            message.extend_from_slice(account.key.as_ref());
+        for key in keys {
+            message.extend_from_slice(key.as_ref());
         }
         message.extend_from_slice(data);
 
-        self.poh = utils::hashv(&[
+        utils::hashv(&[
             self.poh.as_ref(),
             &[ix as u8],
             &message
-        ]);
-
-        self.advance_slot();
+        ])
     }
 
     #[inline]
@@ -66,6 +119,27 @@ impl CodeVmAccount {
         self.authority
     }
 
+    /// The key that currently controls the VM. This starts out as `authority`,
+    /// which stays fixed because the VM address and timelock addresses are
+    /// derived from it.
+    #[inline]
+    pub fn get_current_authority(&self) -> Pubkey {
+        if self.current_authority == Pubkey::default() {
+            self.authority
+        } else {
+            self.current_authority
+        }
+    }
+
+    #[inline]
+    pub fn get_pending_authority(&self) -> Option<Pubkey> {
+        if self.pending_authority == Pubkey::default() {
+            None
+        } else {
+            Some(self.pending_authority)
+        }
+    }
+
     #[inline]
     pub fn get_mint(&self) -> Pubkey {
         self.mint
@@ -86,6 +160,61 @@ impl CodeVmAccount {
         self.lock_duration
     }
 
+    #[inline]
+    pub fn get_sig_verify_mode(&self) -> SigVerifyMode {
+        SigVerifyMode::try_from(self.sig_verify_mode).unwrap_or(SigVerifyMode::Program)
+    }
+
+    #[inline]
+    pub fn has_fee(&self) -> bool {
+        self.fee_bps > 0 || self.fee_flat > 0
+    }
+
+    /// The operator fee owed on `amount`, or `None` on overflow.
+    pub fn get_fee(&self, amount: u64) -> Option<u64> {
+        let fee = (amount as u128)
+            .checked_mul(self.fee_bps as u128)?
+            .checked_div(MAX_FEE_BPS as u128)?
+            .checked_add(self.fee_flat as u128)?;
+
+        u64::try_from(fee).ok()
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused != 0
+    }
+
+    #[inline]
+    pub fn requires_versioned_messages(&self) -> bool {
+        self.require_versioned != 0
+    }
+
+    #[inline]
+    pub fn is_opcode_enabled(&self, opcode: u8) -> bool {
+        self.disabled_opcodes[(opcode / 8) as usize] & (1 << (opcode % 8)) == 0
+    }
+
+    /// Count a memory, storage or relay account created for this VM.
+    pub fn add_child(&mut self) -> ProgramResult {
+        self.num_children = self.num_children
+            .checked_add(1)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    /// Only call this for a child that was counted when it was created (see
+    /// `is_counted` on memory accounts, `counted` on storage and relay
+    /// accounts). Older children were never added to the count.
+    pub fn remove_child(&mut self) -> ProgramResult {
+        self.num_children = self.num_children
+            .checked_sub(1)
+            .ok_or(CodeVmError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
     #[inline]
     pub fn get_current_poh(&self) -> Hash {
         self.poh
@@ -0,0 +1,118 @@
+use steel::*;
+
+/// Errors returned by the VM, as `ProgramError::Custom(code)`.
+///
+/// The codes are stable. Variants are only ever added, with a new code in the
+/// range of their group, and never renumbered or reused, so clients can match
+/// on them without parsing the program logs.
+#[repr(u32)]
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+pub enum CodeVmError {
+    // Accounts and instruction data
+
+    #[error("A required account was not provided")]
+    MissingAccount = 0,
+    #[error("An account does not match the expected address")]
+    AccountMismatch = 1,
+    #[error("An account is not owned by the expected owner")]
+    InvalidOwner = 2,
+    #[error("The same account was provided more than once")]
+    DuplicateAccount = 3,
+    #[error("The instruction data is invalid")]
+    InvalidInstructionData = 4,
+    #[error("The signer is not allowed to perform this action")]
+    Unauthorized = 5,
+
+    // The VM
+
+    #[error("The VM is paused")]
+    Paused = 100,
+    #[error("The opcode is not known to the VM")]
+    InvalidOpcode = 101,
+    #[error("The opcode is disabled")]
+    OpcodeDisabled = 102,
+    #[error("An arithmetic operation overflowed")]
+    ArithmeticOverflow = 103,
+    #[error("The VM account is not in the legacy format")]
+    NotLegacyVm = 104,
+
+    // Memory and virtual accounts
+
+    #[error("The virtual account is not of the expected type")]
+    WrongAccountVariant = 200,
+    #[error("The virtual account is not allocated")]
+    AccountNotAllocated = 201,
+    #[error("The virtual account is already allocated")]
+    AccountAlreadyAllocated = 202,
+    #[error("The account still holds state and can't be closed")]
+    AccountNotEmpty = 203,
+    #[error("The memory account does not have the layout this instruction expects")]
+    InvalidMemoryLayout = 204,
+
+    // Balances
+
+    #[error("The account has insufficient funds")]
+    InsufficientFunds = 300,
+    #[error("The account must have a zero balance")]
+    NonZeroBalance = 301,
+
+    // Merkle trees
+
+    #[error("The merkle proof is invalid")]
+    InvalidMerkleProof = 400,
+    #[error("The merkle proof was made against a root that is no longer recent")]
+    StaleMerkleProof = 401,
+    #[error("The merkle tree is full")]
+    MerkleTreeFull = 402,
+    #[error("The storage tree id is unknown")]
+    UnknownTreeId = 403,
+    #[error("The commitment does not match the calculated commitment")]
+    InvalidCommitment = 404,
+
+    // Signatures
+
+    #[error("The signature is invalid")]
+    InvalidSignature = 500,
+    #[error("Not enough signatures were provided")]
+    NotEnoughSignatures = 501,
+    #[error("The multisig is invalid")]
+    InvalidMultisig = 502,
+    #[error("The VM only accepts versioned signed messages")]
+    UnversionedMessage = 503,
+
+    // Time
+
+    #[error("The time at which this is allowed has not passed yet")]
+    TooEarly = 600,
+    #[error("The signed message or account has expired")]
+    Expired = 601,
+    #[error("The given time is invalid")]
+    InvalidTime = 602,
+    #[error("The timelock is not in the expected state")]
+    InvalidUnlockState = 603,
+}
+
+error!(CodeVmError);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_codes() {
+        // These values are part of the client interface and must not change.
+        assert_eq!(u32::from(CodeVmError::MissingAccount), 0);
+        assert_eq!(u32::from(CodeVmError::WrongAccountVariant), 200);
+        assert_eq!(u32::from(CodeVmError::InsufficientFunds), 300);
+        assert_eq!(u32::from(CodeVmError::InvalidMerkleProof), 400);
+        assert_eq!(u32::from(CodeVmError::StaleMerkleProof), 401);
+        assert_eq!(u32::from(CodeVmError::InvalidTime), 602);
+
+        assert_eq!(
+            ProgramError::from(CodeVmError::StaleMerkleProof),
+            ProgramError::Custom(401),
+        );
+        assert_eq!(CodeVmError::try_from(300), Ok(CodeVmError::InsufficientFunds));
+        assert!(CodeVmError::try_from(7).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use borsh::{BorshSerialize, BorshDeserialize};
+
+use steel::*;
+use crate::types::{Hash, Signature};
+
+/// The version of the event layout. Every event is logged as its
+/// discriminator, this version and then the borsh encoded event. Fields are
+/// only ever appended, in a new version, so a decoder can tell which fields
+/// to expect.
+pub const EVENT_VERSION: u8 = 1;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromPrimitive)]
+pub enum CodeEvent {
+    Unknown = 0,
+
+    CompressEvent,
+    DecompressEvent,
+
+    InstructionEvent,
+    OpcodeEvent,
+
+    AccountWriteEvent,
+    AccountDeleteEvent,
+    AccountMoveEvent,
+
+    TokenTransferEvent,
+    UnlockEvent,
+    MemoryMigrateEvent,
+}
+
+macro_rules! code_event {
+    ($struct_name:ident) => {
+        impl $struct_name {
+            pub fn try_to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+                let discriminator = CodeEvent::$struct_name as u8;
+                let data = self.try_to_vec()?;
+                let mut result = Vec::with_capacity(2 + data.len());
+                result.push(discriminator);
+                result.push(EVENT_VERSION);
+                result.extend_from_slice(&data);
+                Ok(result)
+            }
+
+            /// Log this event with sol_log_data.
+            pub fn log(&self) {
+                // Serializing into a Vec can't fail.
+                if let Ok(data) = self.try_to_bytes() {
+                    solana_program::log::sol_log_data(&[&data]);
+                }
+            }
+        }
+    };
+}
+
+/// Logged for every account put into a storage tree. The leaf only commits to
+/// the account, so this is the one on-chain record of the account state and
+/// signature needed to decompress or withdraw it later.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct CompressEvent {
+    pub vm: Pubkey,
+    pub vm_storage: Pubkey,
+    pub tree_id: u16,
+    pub leaf_index: u64,
+    pub packed_va: Vec<u8>,
+    pub signature: Signature,
+}
+
+/// Logged for every account taken out of a storage tree, by decompress or by
+/// a non-custodial withdraw. `leaf` is the value that was removed (before
+/// `as_leaf`), which is what ties the event to the account at `leaf_index`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct DecompressEvent {
+    pub vm: Pubkey,
+    pub vm_storage: Pubkey,
+    pub tree_id: u16,
+    pub leaf_index: u64,
+    pub leaf: Hash,
+}
+
+/// Logged once by every instruction, after its other events, when the VM
+/// state is advanced.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct InstructionEvent {
+    pub instruction: u8,  // CodeInstruction
+    pub slot: u64,        // The VM slot after the instruction
+    pub poh: Hash,        // The VM poh after the instruction
+}
+
+/// Logged by every opcode run by exec or exec_batch, after the events of the
+/// accounts it changed.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct OpcodeEvent {
+    pub opcode: u8,  // Opcode
+    pub mem_indicies: Vec<u16>,
+    pub mem_banks: Vec<u8>,
+}
+
+/// Logged whenever a virtual account is written to memory, with the full new
+/// state. The difference between the balances is the amount moved in or out
+/// of the account.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct AccountWriteEvent {
+    pub vm_memory: Pubkey,
+    pub account_index: u16,
+    pub previous_balance: u64,
+    pub balance: u64,
+    pub packed_va: Vec<u8>,
+}
+
+/// Logged whenever a virtual account is removed from memory.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct AccountDeleteEvent {
+    pub vm_memory: Pubkey,
+    pub account_index: u16,
+    pub previous_balance: u64,
+}
+
+/// Logged whenever a virtual account is moved to another index of the same
+/// memory account, as done by compact.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct AccountMoveEvent {
+    pub vm_memory: Pubkey,
+    pub from_index: u16,
+    pub to_index: u16,
+}
+
+/// Logged for every token transfer made by the VM, between its omnibus, relay
+/// vaults, deposit accounts and external token accounts.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct TokenTransferEvent {
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+/// Logged when an unlock is requested and when it takes effect.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct UnlockEvent {
+    pub unlock_pda: Pubkey,
+    pub owner: Pubkey,
+    pub address: Pubkey,  // The timelock address
+    pub state: u8,        // TimelockState
+    pub unlock_at: i64,
+}
+
+/// Logged by every chunk of a legacy memory account migration. The account is
+/// in the current format once `migrated` reaches `capacity`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct MemoryMigrateEvent {
+    pub vm_memory: Pubkey,
+    pub migrated: u32,  // The number of slots checked so far
+    pub capacity: u32,
+}
+
+code_event!(CompressEvent);
+code_event!(DecompressEvent);
+code_event!(InstructionEvent);
+code_event!(OpcodeEvent);
+code_event!(AccountWriteEvent);
+code_event!(AccountDeleteEvent);
+code_event!(AccountMoveEvent);
+code_event!(TokenTransferEvent);
+code_event!(UnlockEvent);
+code_event!(MemoryMigrateEvent);
+
+/// An event read back from the data of a `Program data:` log line.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ParsedEvent {
+    Compress(CompressEvent),
+    Decompress(DecompressEvent),
+    Instruction(InstructionEvent),
+    Opcode(OpcodeEvent),
+    AccountWrite(AccountWriteEvent),
+    AccountDelete(AccountDeleteEvent),
+    AccountMove(AccountMoveEvent),
+    TokenTransfer(TokenTransferEvent),
+    Unlock(UnlockEvent),
+    MemoryMigrate(MemoryMigrateEvent),
+}
+
+impl ParsedEvent {
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, std::io::Error> {
+        let invalid = |message| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+        let [discriminator, version, rest @ ..] = data else {
+            return Err(invalid("event is too short"));
+        };
+
+        if *version == 0 || *version > EVENT_VERSION {
+            return Err(invalid("unsupported event version"));
+        }
+
+        let event = CodeEvent::try_from(*discriminator)
+            .map_err(|_| invalid("unknown event"))?;
+
+        match event {
+            CodeEvent::CompressEvent =>
+                Ok(Self::Compress(CompressEvent::try_from_slice(rest)?)),
+            CodeEvent::DecompressEvent =>
+                Ok(Self::Decompress(DecompressEvent::try_from_slice(rest)?)),
+            CodeEvent::InstructionEvent =>
+                Ok(Self::Instruction(InstructionEvent::try_from_slice(rest)?)),
+            CodeEvent::OpcodeEvent =>
+                Ok(Self::Opcode(OpcodeEvent::try_from_slice(rest)?)),
+            CodeEvent::AccountWriteEvent =>
+                Ok(Self::AccountWrite(AccountWriteEvent::try_from_slice(rest)?)),
+            CodeEvent::AccountDeleteEvent =>
+                Ok(Self::AccountDelete(AccountDeleteEvent::try_from_slice(rest)?)),
+            CodeEvent::AccountMoveEvent =>
+                Ok(Self::AccountMove(AccountMoveEvent::try_from_slice(rest)?)),
+            CodeEvent::TokenTransferEvent =>
+                Ok(Self::TokenTransfer(TokenTransferEvent::try_from_slice(rest)?)),
+            CodeEvent::UnlockEvent =>
+                Ok(Self::Unlock(UnlockEvent::try_from_slice(rest)?)),
+            CodeEvent::MemoryMigrateEvent =>
+                Ok(Self::MemoryMigrate(MemoryMigrateEvent::try_from_slice(rest)?)),
+            CodeEvent::Unknown => Err(invalid("unknown event")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let event = AccountWriteEvent {
+            vm_memory: Pubkey::new_unique(),
+            account_index: 7,
+            previous_balance: 100,
+            balance: 40,
+            packed_va: vec![1, 2, 3],
+        };
+
+        let data = event.try_to_bytes().unwrap();
+        assert_eq!(data[0], CodeEvent::AccountWriteEvent as u8);
+        assert_eq!(data[1], EVENT_VERSION);
+        assert_eq!(ParsedEvent::try_from_bytes(&data).unwrap(), ParsedEvent::AccountWrite(event));
+
+        // A newer version than this decoder knows about
+        let mut newer = data.clone();
+        newer[1] = EVENT_VERSION + 1;
+        assert!(ParsedEvent::try_from_bytes(&newer).is_err());
+
+        // Truncated or unknown events
+        assert!(ParsedEvent::try_from_bytes(&data[..1]).is_err());
+        assert!(ParsedEvent::try_from_bytes(&data[..data.len() - 1]).is_err());
+        assert!(ParsedEvent::try_from_bytes(&[0xff, EVENT_VERSION]).is_err());
+    }
+}
@@ -3,10 +3,13 @@ use solana_program::msg;
 
 use crate::{
     consts::*, 
+    error::CodeVmError,
     cvm::{
-        CodeVmAccount, MemoryAccount, RelayAccount, StorageAccount, VirtualAccount 
+        CodeVmAccount, MemoryAccount, MemoryVersion, RelayAccount, SigVerifyMode, StorageAccount, StorageChangeLog, VirtualAccount 
     },
-    types::{Hash, SliceAllocator, SliceAllocatorMut},
+    event::{AccountDeleteEvent, AccountWriteEvent},
+    types::{ChangeLogEntry, Hash, MerkleTree, Signature, SliceAllocator, SliceAllocatorMut},
+    utils,
 };
 
 pub fn optional_meta(account: Option<Pubkey>, is_signer: bool) -> AccountMeta {
@@ -31,10 +34,10 @@ pub fn get_optional<'a, 'b>(account: &'a AccountInfo<'b>)
     }
 }
 
-pub fn check_condition(condition: bool, message: &str) -> ProgramResult {
+pub fn check_condition(condition: bool, error: CodeVmError, message: &str) -> ProgramResult {
     if !condition {
         msg!("Failed condition: {}", message);
-        return Err(ProgramError::InvalidArgument);
+        return Err(error.into());
     }
     Ok(())
 }
@@ -55,7 +58,7 @@ pub fn check_unique(accounts: &[&AccountInfo], message: &str) -> ProgramResult {
         for j in (i + 1)..num_accounts {
             if accounts[i].key == accounts[j].key {
                 msg!("Failed unique constraint: {}", message);
-                return Err(ProgramError::InvalidArgument);
+                return Err(CodeVmError::DuplicateAccount.into());
             }
         }
     }
@@ -97,6 +100,51 @@ pub fn check_sysvar(account: &AccountInfo, sysvar_id: &Pubkey) -> ProgramResult
     Ok(())
 }
 
+/// Verify an ed25519 signature using the verification path configured on the
+/// VM. The instructions sysvar is required for `SigVerifyMode::Precompile`.
+pub fn vm_sig_verify(
+    vm: &CodeVmAccount,
+    instructions_info: Option<&AccountInfo>,
+    pubkey: &[u8],
+    sig: &[u8],
+    message: &[u8],
+) -> ProgramResult {
+    match vm.get_sig_verify_mode() {
+        SigVerifyMode::Program => utils::sig_verify(pubkey, sig, message)
+            .map_err(|_| CodeVmError::InvalidSignature.into()),
+        SigVerifyMode::Precompile => {
+            check_condition(
+                instructions_info.is_some(),
+                CodeVmError::MissingAccount,
+                "the instructions sysvar must be provided",
+            )?;
+
+            let instructions_info = instructions_info.ok_or(CodeVmError::MissingAccount)?;
+            utils::sig_verify_precompile(instructions_info, pubkey, sig, message)
+        }
+    }
+}
+
+/// Verify that `owner` is the multisig and that at least its threshold of
+/// signers signed the message, each using the verification path configured on
+/// the VM (see `vm_sig_verify`).
+pub fn vm_multisig_verify(
+    vm: &CodeVmAccount,
+    instructions_info: Option<&AccountInfo>,
+    owner: &Pubkey,
+    multisig: &utils::MultisigOwner,
+    signatures: &[utils::MultisigSignature],
+    message: &[u8],
+) -> ProgramResult {
+    utils::multisig_verify_with(
+        owner,
+        multisig,
+        signatures,
+        message,
+        |pubkey, sig, message| vm_sig_verify(vm, instructions_info, pubkey, sig, message),
+    )
+}
+
 pub fn load_vm<'a>(
     vm_info: &'a AccountInfo<'_>,
 ) -> Result<&'a mut CodeVmAccount, ProgramError> {
@@ -128,7 +176,8 @@ pub fn load_vm_checked<'a>(
         vm_info.to_account_mut::<CodeVmAccount>(&crate::ID)?;
 
     check_condition(
-        vm.authority.eq(vm_authority_info.key),
+        vm.get_current_authority().eq(vm_authority_info.key),
+        CodeVmError::Unauthorized,
         "vm_authority does not match the authority of the VM account",
     )?;
 
@@ -168,6 +217,7 @@ pub fn load_memory<'a>(
 
     check_condition(
         memory.vm.eq(vm_info.key),
+        CodeVmError::AccountMismatch,
         "vm does not match the VM account",
     )?;
 
@@ -195,11 +245,13 @@ pub fn load_storage<'a>(
 
     check_condition(
         storage.vm.eq(vm_info.key),
+        CodeVmError::AccountMismatch,
         "vm does not match the VM account",
     )?;
 
     check_condition(
         storage.depth == COMPRESSED_STATE_DEPTH as u8,
+        CodeVmError::InvalidMemoryLayout,
         "storage depth is not equal to COMPRESSED_STATE_DEPTH",
     )?;
 
@@ -227,17 +279,29 @@ pub fn load_relay<'a>(
 
     check_condition(
         relay.vm.eq(vm_info.key),
+        CodeVmError::AccountMismatch,
         "vm does not match the VM account",
     )?;
 
     check_condition(
         relay.num_levels == RELAY_STATE_DEPTH as u8,
+        CodeVmError::InvalidMemoryLayout,
         "relay depth is not equal to RELAY_STATE_DEPTH",
     )?;
 
     Ok(relay)
 }
 
+/// Fails if the VM has been paused by its authority. This must never be used by
+/// the non-custodial exits (InitUnlockIx, UnlockIx and WithdrawIx).
+pub fn check_not_paused(vm: &CodeVmAccount) -> ProgramResult {
+    check_condition(
+        !vm.is_paused(),
+        CodeVmError::Paused,
+        "the VM is paused",
+    )
+}
+
 pub fn check_memory(
     vm_memory_info: &AccountInfo<'_>, 
     vm_info: &AccountInfo<'_>
@@ -293,6 +357,7 @@ pub fn check_is_empty<'a>(
 
     check_condition(
         mem.is_empty(account_index),
+        CodeVmError::AccountAlreadyAllocated,
         "the virtual account is already allocated",
     )?;
 
@@ -310,12 +375,14 @@ pub fn try_read<'a>(
 
     check_condition(
         mem.has_item(account_index),
+        CodeVmError::AccountNotAllocated,
         "the virtual account is not allocated",
     )?;
 
     let account = mem.read_item(account_index);
     check_condition(
         account.is_some(),
+        CodeVmError::AccountNotAllocated,
         "unable to read the virtual account from the memory",
     )?;
 
@@ -329,17 +396,41 @@ pub fn try_write<'a>(
     account: &VirtualAccount,
 ) -> ProgramResult {
 
+    let memory = MemoryAccount::unpack(&vm_memory.data.borrow());
+
+    // Legacy memory accounts only hold their one account type until they are
+    // migrated (see MigrateMemoryIx).
+    check_condition(
+        memory.get_version() == MemoryVersion::Current
+            || memory.get_legacy_layout().allows(account),
+        CodeVmError::InvalidMemoryLayout,
+        "the virtual account does not match the legacy memory layout",
+    )?;
+
     let (n, m) = MemoryAccount::get_capacity_and_size(vm_memory);
     let mut data = MemoryAccount::get_data_mut(vm_memory)?;
     let mut mem = SliceAllocatorMut::try_from_slice_mut(&mut *data, n, m)?;
     
     let data = &account.pack();
 
+    let previous_balance = match mem.read_item(account_index) {
+        Some(item) => VirtualAccount::unpack(&item).map_or(0, |va| va.get_balance()),
+        None => 0,
+    };
+
     if mem.is_empty(account_index) {
         mem.try_alloc_item(account_index, data.len())?;
     }
     mem.try_write_item(account_index, data)?;
 
+    AccountWriteEvent {
+        vm_memory: *vm_memory.key,
+        account_index,
+        previous_balance,
+        balance: account.get_balance(),
+        packed_va: data.clone(),
+    }.log();
+
     Ok(())
 } 
 
@@ -352,41 +443,366 @@ pub fn try_delete<'a>(
     let mut data = MemoryAccount::get_data_mut(vm_memory)?;
     let mut mem = SliceAllocatorMut::try_from_slice_mut(&mut *data, n, m)?;
 
-    if mem.has_item(account_index) {
+    if let Some(item) = mem.read_item(account_index) {
+        let previous_balance = VirtualAccount::unpack(&item).map_or(0, |va| va.get_balance());
         mem.try_free_item(account_index)?;
+
+        AccountDeleteEvent {
+            vm_memory: *vm_memory.key,
+            account_index,
+            previous_balance,
+        }.log();
     }
 
     Ok(())
 } 
 
-pub fn try_compress<'a>(
+/// Add a compressed account to the active storage tree and return its leaf
+/// index.
+pub fn try_compress(
     vm_storage: &AccountInfo<'_>,
-    leaf: Hash,
-) -> ProgramResult {
+    signature: &Signature,
+    va_hash: &Hash,
+) -> Result<u64, ProgramError> {
     let storage = 
         StorageAccount::get_compressed_state_mut(vm_storage)?;
 
-    storage.try_insert(leaf)?;
+    let mut changelog = StorageAccount::get_changelog_mut(vm_storage)?;
+    compress_leaf(storage, changelog.as_deref_mut(), signature, va_hash)
+}
 
-    Ok(())
+/// Add a compressed account to a storage tree and record it in the changelog,
+/// if there is one. This is the part of `try_compress` that doesn't need the
+/// account.
+pub fn compress_leaf(
+    tree: &mut MerkleTree<{COMPRESSED_STATE_DEPTH}>,
+    changelog: Option<&mut StorageChangeLog>,
+    signature: &Signature,
+    va_hash: &Hash,
+) -> Result<u64, ProgramError> {
+    let leaf_index = tree.get_next_index();
+    let leaf = utils::get_storage_leaf_value(signature, va_hash, leaf_index);
+
+    let path = tree.try_insert_with_path(leaf)?;
+
+    if let Some(changelog) = changelog {
+        changelog.push(ChangeLogEntry::new(leaf_index, &path));
+    }
+
+    Ok(leaf_index)
 }
 
-pub fn try_decompress<'a>(
+/// Remove a compressed account from storage and return the value that was
+/// removed. On storage accounts with a changelog, a proof made against a
+/// recent root is fast-forwarded to the current root first. Leaves in a tree
+/// that was rolled over are removed from its archived root.
+pub fn try_decompress(
     vm_storage: &AccountInfo<'_>,
-    leaf: Hash,
+    tree_id: u16,
+    signature: &Signature,
+    va_hash: &Hash,
+    leaf_index: u64,
     proof: &[Hash],
-) -> ProgramResult {
+) -> Result<Hash, ProgramError> {
+    if is_archived_tree(vm_storage, tree_id)? {
+        let values = try_decompress_archived(vm_storage, tree_id, &[leaf_index], &[*signature], &[*va_hash], proof)?;
+        return Ok(values[0]);
+    }
+
     let storage = 
         StorageAccount::get_compressed_state_mut(vm_storage)?;
 
-    storage.try_remove(proof, leaf)?;
+    let mut changelog = StorageAccount::get_changelog_mut(vm_storage)?;
+    decompress_leaf(storage, changelog.as_deref_mut(), signature, va_hash, leaf_index, proof)
+}
 
-    Ok(())
+/// Remove a compressed account from the active storage tree, fast-forwarding
+/// a proof made against a recent root if there is a changelog. This is the
+/// part of `try_decompress` that doesn't need the account.
+pub fn decompress_leaf(
+    tree: &mut MerkleTree<{COMPRESSED_STATE_DEPTH}>,
+    changelog: Option<&mut StorageChangeLog>,
+    signature: &Signature,
+    va_hash: &Hash,
+    leaf_index: u64,
+    proof: &[Hash],
+) -> Result<Hash, ProgramError> {
+    check_condition(
+        leaf_index < tree.get_next_index(),
+        CodeVmError::InvalidMerkleProof,
+        "leaf index is out of range",
+    )?;
+
+    let value = utils::get_storage_leaf_value(signature, va_hash, leaf_index);
+
+    // Only trees without a changelog can hold leaves from before the leaf
+    // index was part of the value. Their index can't be checked, but there
+    // is no changelog that it could end up in either.
+    let Some(changelog) = changelog else {
+        let legacy_value = utils::get_legacy_storage_leaf_value(signature, va_hash);
+        return tree.try_remove(proof, value)
+            .map(|_| value)
+            .or_else(|_| tree.try_remove(proof, legacy_value).map(|_| legacy_value));
+    };
+
+    let leaf = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::as_leaf(value);
+    let mut proof = proof.to_vec();
+
+    let path = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::compute_path(&proof, leaf);
+    let root = *path.last().unwrap();
+
+    if proof.len() == COMPRESSED_STATE_DEPTH && root != tree.get_root() {
+        changelog.try_fast_forward(root, leaf_index, &mut proof)?;
+    }
+
+    // The value commits to the leaf index, so the entry is for the leaf that
+    // was actually removed, and proofs still in flight can be fast-forwarded
+    // past it.
+    let path = tree.try_replace_leaf_with_path(&proof, leaf, tree.get_empty_leaf())?;
+    changelog.push(ChangeLogEntry::new(leaf_index, &path));
+
+    Ok(value)
+}
+
+/// Remove several compressed accounts from storage with one multi-proof,
+/// which must be made against the current root, and return the values that
+/// were removed. On storage accounts with a changelog, the leaves are removed
+/// one at a time so that each removal gets its own entry.
+pub fn try_decompress_multi(
+    vm_storage: &AccountInfo<'_>,
+    tree_id: u16,
+    leaf_indices: &[u64],
+    signatures: &[Signature],
+    va_hashes: &[Hash],
+    proof: &[Hash],
+) -> Result<Vec<Hash>, ProgramError> {
+    if is_archived_tree(vm_storage, tree_id)? {
+        return try_decompress_archived(vm_storage, tree_id, leaf_indices, signatures, va_hashes, proof);
+    }
+
+    let storage = 
+        StorageAccount::get_compressed_state_mut(vm_storage)?;
+
+    let (values, legacy_values) = get_storage_leaf_values(leaf_indices, signatures, va_hashes);
+
+    // See decompress_leaf, the leaves of a batch are either all from before
+    // or all from after the leaf index was part of the value.
+    let Some(mut changelog) = StorageAccount::get_changelog_mut(vm_storage)? else {
+        return match storage.try_remove_multi(proof, leaf_indices, &values) {
+            Ok(()) => Ok(values),
+            Err(_) => storage.try_remove_multi(proof, leaf_indices, &legacy_values)
+                .map(|_| legacy_values),
+        };
+    };
+
+    let leaves: Vec<Hash> = values.iter()
+        .map(|value| MerkleTree::<{COMPRESSED_STATE_DEPTH}>::as_leaf(*value))
+        .collect();
+
+    let layers = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::compute_multi_layers(
+        proof,
+        leaf_indices,
+        &leaves,
+    )?;
+
+    check_condition(
+        layers[COMPRESSED_STATE_DEPTH][0].1 == storage.get_root(),
+        CodeVmError::InvalidMerkleProof,
+        "invalid multi-proof for original leaves",
+    )?;
+
+    let mut entries: Vec<ChangeLogEntry<{COMPRESSED_STATE_DEPTH}>> = Vec::with_capacity(leaves.len());
+
+    for (leaf_index, leaf) in leaf_indices.iter().zip(leaves.iter()) {
+        let mut proof = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::get_proof_from_layers(&layers, *leaf_index);
+
+        // Account for the leaves already removed by this batch
+        for entry in entries.iter() {
+            entry.try_update_proof(*leaf_index, &mut proof)?;
+        }
+
+        let path = storage.try_replace_leaf_with_path(&proof, *leaf, storage.get_empty_leaf())?;
+        let entry = ChangeLogEntry::new(*leaf_index, &path);
+
+        changelog.push(entry);
+        entries.push(entry);
+    }
+
+    Ok(values)
+}
+
+/// The storage values of several compressed accounts, and the values they
+/// would have had before the leaf index was part of them.
+fn get_storage_leaf_values(
+    leaf_indices: &[u64],
+    signatures: &[Signature],
+    va_hashes: &[Hash],
+) -> (Vec<Hash>, Vec<Hash>) {
+    leaf_indices.iter()
+        .zip(signatures.iter().zip(va_hashes.iter()))
+        .map(|(leaf_index, (signature, va_hash))| (
+            utils::get_storage_leaf_value(signature, va_hash, *leaf_index),
+            utils::get_legacy_storage_leaf_value(signature, va_hash),
+        ))
+        .unzip()
+}
+
+/// True if `tree_id` names a tree that was rolled over, false if it names the
+/// active tree.
+fn is_archived_tree(vm_storage: &AccountInfo<'_>, tree_id: u16) -> Result<bool, ProgramError> {
+    let storage = vm_storage.to_account::<StorageAccount>(&crate::ID)?;
+
+    check_condition(
+        tree_id <= storage.tree_id,
+        CodeVmError::UnknownTreeId,
+        "unknown storage tree id",
+    )?;
+
+    Ok(tree_id < storage.tree_id)
+}
+
+/// Remove compressed accounts from a tree that was rolled over. Only the root
+/// of an archived tree is kept, so the proof must be made against it and the
+/// new root is computed from the same proof with the leaves emptied.
+fn try_decompress_archived(
+    vm_storage: &AccountInfo<'_>,
+    tree_id: u16,
+    leaf_indices: &[u64],
+    signatures: &[Signature],
+    va_hashes: &[Hash],
+    proof: &[Hash],
+) -> Result<Vec<Hash>, ProgramError> {
+    let empty_leaf = 
+        StorageAccount::get_compressed_state_mut(vm_storage)?.get_empty_leaf();
+
+    let mut roots = StorageAccount::get_archived_roots_mut(vm_storage)?;
+    decompress_archived_leaves(&mut roots[tree_id as usize], empty_leaf, leaf_indices, signatures, va_hashes, proof)
+}
+
+/// Remove compressed accounts from an archived tree, given its root. This is
+/// the part of `try_decompress_archived` that doesn't need the account.
+/// Archived trees have no changelog, so they may hold leaves from before the
+/// leaf index was part of the value. Returns the values that were removed.
+pub fn decompress_archived_leaves(
+    root: &mut Hash,
+    empty_leaf: Hash,
+    leaf_indices: &[u64],
+    signatures: &[Signature],
+    va_hashes: &[Hash],
+    proof: &[Hash],
+) -> Result<Vec<Hash>, ProgramError> {
+    let (values, legacy_values) = get_storage_leaf_values(leaf_indices, signatures, va_hashes);
+
+    match remove_archived_values(root, empty_leaf, leaf_indices, &values, proof) {
+        Ok(()) => Ok(values),
+        Err(_) => remove_archived_values(root, empty_leaf, leaf_indices, &legacy_values, proof)
+            .map(|_| legacy_values),
+    }
 }
 
+fn remove_archived_values(
+    root: &mut Hash,
+    empty_leaf: Hash,
+    leaf_indices: &[u64],
+    values: &[Hash],
+    proof: &[Hash],
+) -> ProgramResult {
+    let leaves: Vec<Hash> = values.iter()
+        .map(|value| MerkleTree::<{COMPRESSED_STATE_DEPTH}>::as_leaf(*value))
+        .collect();
+    let empty_leaves = vec![empty_leaf; leaves.len()];
+
+    let layers = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::compute_multi_layers(
+        proof,
+        leaf_indices,
+        &leaves,
+    )?;
+
+    check_condition(
+        layers[COMPRESSED_STATE_DEPTH][0].1 == *root,
+        CodeVmError::InvalidMerkleProof,
+        "invalid merkle proof for the archived tree",
+    )?;
+
+    let layers = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::compute_multi_layers(
+        proof,
+        leaf_indices,
+        &empty_leaves,
+    )?;
+
+    *root = layers[COMPRESSED_STATE_DEPTH][0].1;
+
+    Ok(())
+}
 
 pub fn create_name(name: &str) -> [u8; MAX_NAME_LEN] {
     let mut name_bytes = [0u8; MAX_NAME_LEN];
     name_bytes[..name.len()].copy_from_slice(name.as_bytes());
     name_bytes
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type StorageTree = MerkleTree<{COMPRESSED_STATE_DEPTH}>;
+
+    #[test]
+    fn test_decompress_race() {
+        let mut tree = StorageTree::new(&[b"test"]);
+        let mut changelog = StorageChangeLog::zeroed();
+
+        let signature = Signature::from([1; 64]);
+        let va_hashes: Vec<Hash> = (0..2u8).map(|i| utils::hash(&[i])).collect();
+
+        let mut leaves = Vec::new();
+        for (i, va_hash) in va_hashes.iter().enumerate() {
+            assert_eq!(compress_leaf(&mut tree, Some(&mut changelog), &signature, va_hash), Ok(i as u64));
+            leaves.push(StorageTree::as_leaf(utils::get_storage_leaf_value(&signature, va_hash, i as u64)));
+        }
+
+        let proof_a = tree.get_merkle_proof(&leaves, 0);
+        let proof_b = tree.get_merkle_proof(&leaves, 1);
+
+        // The proof alone fits either index, the leaf value doesn't
+        assert!(decompress_leaf(&mut tree, Some(&mut changelog), &signature, &va_hashes[0], 1, &proof_a).is_err());
+        assert!(decompress_leaf(&mut tree, Some(&mut changelog), &signature, &va_hashes[0], 0, &proof_a).is_ok());
+
+        // Every removal is logged, so the other proof is fast-forwarded
+        assert!(decompress_leaf(&mut tree, Some(&mut changelog), &signature, &va_hashes[1], 1, &proof_b).is_ok());
+        assert!(tree.is_empty());
+        assert_eq!(changelog.len(), 4);
+    }
+
+    #[test]
+    fn test_decompress_legacy_leaf() {
+        let signature = Signature::from([1; 64]);
+        let va_hash = utils::hash(b"legacy");
+        let legacy_value = utils::get_legacy_storage_leaf_value(&signature, &va_hash);
+
+        let mut tree = StorageTree::new(&[b"test"]);
+        tree.try_insert(legacy_value).unwrap();
+        let proof = tree.get_merkle_proof(&[StorageTree::as_leaf(legacy_value)], 0);
+
+        // Only a tree without a changelog can hold a leaf from before the
+        // index was part of it
+        let mut changelog = StorageChangeLog::zeroed();
+        let before = tree;
+        assert!(decompress_leaf(&mut tree, Some(&mut changelog), &signature, &va_hash, 0, &proof).is_err());
+        assert_eq!(tree, before);
+
+        assert_eq!(decompress_leaf(&mut tree, None, &signature, &va_hash, 0, &proof), Ok(legacy_value));
+        assert!(tree.is_empty());
+
+        // The same goes for archived trees
+        let mut tree = StorageTree::new(&[b"test"]);
+        tree.try_insert(legacy_value).unwrap();
+        let mut root = tree.get_root();
+        let empty_leaf = tree.get_empty_leaf();
+        assert_eq!(
+            decompress_archived_leaves(&mut root, empty_leaf, &[0], &[signature], &[va_hash], &proof),
+            Ok(vec![legacy_value]),
+        );
+        assert_eq!(root, tree.get_empty_root());
+    }
+}
@@ -3,7 +3,9 @@ use std::marker::PhantomData;
 
 use steel::*;
 use crate::{
-    consts::*, types::{Hash, Signature}
+    consts::*,
+    types::{Hash, Signature},
+    utils::{MultisigOwner, MultisigSignature},
 };
 
 #[repr(u8)]
@@ -29,6 +31,34 @@ pub enum CodeInstruction {
     DepositIx,
     WithdrawIx,
     UnlockIx,
+
+    ExecBatchIx,
+    SetSigVerifyModeIx,
+    SetFeeConfigIx,
+    ProposeAuthorityIx,
+    AcceptAuthorityIx,
+    SetPauseIx,
+
+    CloseMemoryIx,
+    CloseStorageIx,
+    CloseRelayIx,
+    CloseVmIx,
+
+    CompactMemoryIx,
+    MigrateMemoryIx,
+    RelocateIx,
+
+    CompressBatchIx,
+    DecompressBatchIx,
+
+    RolloverStorageIx,
+
+    MultisigInitUnlockIx,
+    MultisigUnlockIx,
+    MultisigWithdrawIx,
+
+    MigrateVmIx,
+    SetRequireVersionedIx,
 }
 
 instruction!(CodeInstruction, InitVmIx);
@@ -50,6 +80,34 @@ instruction!(CodeInstruction, DepositIx);
 instruction!(CodeInstruction, WithdrawIx);
 instruction!(CodeInstruction, UnlockIx);
 
+instruction!(CodeInstruction, ExecBatchIx);
+instruction!(CodeInstruction, SetSigVerifyModeIx);
+instruction!(CodeInstruction, SetFeeConfigIx);
+instruction!(CodeInstruction, ProposeAuthorityIx);
+instruction!(CodeInstruction, AcceptAuthorityIx);
+instruction!(CodeInstruction, SetPauseIx);
+
+instruction!(CodeInstruction, CloseMemoryIx);
+instruction!(CodeInstruction, CloseStorageIx);
+instruction!(CodeInstruction, CloseRelayIx);
+instruction!(CodeInstruction, CloseVmIx);
+
+instruction!(CodeInstruction, CompactMemoryIx);
+instruction!(CodeInstruction, MigrateMemoryIx);
+instruction!(CodeInstruction, RelocateIx);
+
+instruction!(CodeInstruction, CompressBatchIx);
+instruction!(CodeInstruction, DecompressBatchIx);
+
+instruction!(CodeInstruction, RolloverStorageIx);
+
+instruction!(CodeInstruction, MultisigInitUnlockIx);
+instruction!(CodeInstruction, MultisigUnlockIx);
+instruction!(CodeInstruction, MultisigWithdrawIx);
+
+instruction!(CodeInstruction, MigrateVmIx);
+instruction!(CodeInstruction, SetRequireVersionedIx);
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct InitVmIx {
@@ -160,6 +218,34 @@ pub struct ExecIxData {
     pub data: Vec<u8>,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ExecBatchIx {
+    // Dynamically sized data, not supported by Pod (or steel)
+    _data: PhantomData<ExecBatchIxData>,
+}
+
+impl ExecBatchIx {
+    pub fn try_from_slice(data: &[u8]) -> Result<ExecBatchIxData, std::io::Error> {
+        ExecBatchIxData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: ExecBatchIxData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = CodeInstruction::ExecBatchIx as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct ExecBatchIxData {
+    pub ops: Vec<ExecIxData>, // Executed in order, against the same accounts
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct InitNonceIx {
@@ -248,6 +334,35 @@ pub struct ParsedCompressIx {
     pub signature: Signature,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CompressBatchIx {
+    // Dynamically sized data, not supported by Pod (or steel)
+    _data: PhantomData<CompressBatchIxData>,
+}
+
+impl CompressBatchIx {
+    pub fn try_from_slice(data: &[u8]) -> Result<CompressBatchIxData, std::io::Error> {
+        CompressBatchIxData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: CompressBatchIxData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = CodeInstruction::CompressBatchIx as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct CompressBatchIxData {
+    pub account_indices: Vec<u16>,
+    pub signature: Signature, // Signs get_compress_batch_message(<account hashes>)
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct DecompressIx {
@@ -275,10 +390,51 @@ impl DecompressIx {
 pub struct DecompressIxData {
     pub account_index: u16,
     pub packed_va: Vec<u8>,
+    pub tree_id: u16,      // The storage tree that holds the account
+    pub leaf_index: u64,   // Used to fast-forward a proof made against a recent root
     pub proof: Vec<Hash>,
     pub signature: Signature,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct DecompressBatchIx {
+    // Dynamically sized data, not supported by Pod (or steel)
+    _data: PhantomData<DecompressBatchIxData>,
+}
+
+impl DecompressBatchIx {
+    pub fn try_from_slice(data: &[u8]) -> Result<DecompressBatchIxData, std::io::Error> {
+        DecompressBatchIxData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: DecompressBatchIxData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = CodeInstruction::DecompressBatchIx as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct DecompressBatchIxData {
+    pub tree_id: u16,                          // The storage tree that holds every account
+    pub accounts: Vec<DecompressBatchAccount>, // Sorted by leaf_index
+    pub proof: Vec<Hash>,                      // A multi-proof for all leaves
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct DecompressBatchAccount {
+    pub account_index: u16,
+    pub leaf_index: u64,
+    pub packed_va: Vec<u8>,
+    pub signature: Signature,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct InitRelayIx {
@@ -292,6 +448,163 @@ pub struct InitRelayIx {
 pub struct SnapshotIx { // SaveRecentRoot
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SetSigVerifyModeIx {
+    pub mode: u8, // SigVerifyMode
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SetFeeConfigIx {
+    pub fee_collector: Pubkey,
+    pub fee_flat: [u8; 8],      // Pack u64 as [u8; 8]
+    pub fee_bps: [u8; 2],       // Pack u16 as [u8; 2]
+}
+
+impl SetFeeConfigIx {
+    pub fn to_struct(&self) -> Result<ParsedSetFeeConfigIx, std::io::Error> {
+        Ok(ParsedSetFeeConfigIx {
+            fee_collector: self.fee_collector,
+            fee_flat: u64::from_le_bytes(self.fee_flat),
+            fee_bps: u16::from_le_bytes(self.fee_bps),
+        })
+    }
+
+    pub fn from_struct(parsed: ParsedSetFeeConfigIx) -> Self {
+        SetFeeConfigIx {
+            fee_collector: parsed.fee_collector,
+            fee_flat: parsed.fee_flat.to_le_bytes(),
+            fee_bps: parsed.fee_bps.to_le_bytes(),
+        }
+    }
+}
+
+pub struct ParsedSetFeeConfigIx {
+    pub fee_collector: Pubkey,
+    pub fee_flat: u64,
+    pub fee_bps: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ProposeAuthorityIx {
+    pub new_authority: Pubkey, // Pubkey::default() cancels a pending proposal
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct AcceptAuthorityIx {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SetPauseIx {
+    pub paused: u8,                 // 0 or 1
+    pub disabled_opcodes: [u8; 32], // one bit per opcode, set to disable
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseMemoryIx {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseStorageIx {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseRelayIx {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseVmIx {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CompactMemoryIx {
+    pub num_accounts: [u8; 4], // Pack u32 as [u8; 4]
+}
+
+impl CompactMemoryIx {
+    pub fn to_struct(&self) -> Result<ParsedCompactMemoryIx, std::io::Error> {
+        Ok(ParsedCompactMemoryIx {
+            num_accounts: u32::from_le_bytes(self.num_accounts),
+        })
+    }
+
+    pub fn from_struct(parsed: ParsedCompactMemoryIx) -> Self {
+        CompactMemoryIx {
+            num_accounts: parsed.num_accounts.to_le_bytes(),
+        }
+    }
+}
+
+pub struct ParsedCompactMemoryIx {
+    pub num_accounts: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MigrateMemoryIx {
+    pub num_accounts: [u8; 4], // Pack u32 as [u8; 4]
+}
+
+impl MigrateMemoryIx {
+    pub fn to_struct(&self) -> Result<ParsedMigrateMemoryIx, std::io::Error> {
+        Ok(ParsedMigrateMemoryIx {
+            num_accounts: u32::from_le_bytes(self.num_accounts),
+        })
+    }
+
+    pub fn from_struct(parsed: ParsedMigrateMemoryIx) -> Self {
+        MigrateMemoryIx {
+            num_accounts: parsed.num_accounts.to_le_bytes(),
+        }
+    }
+}
+
+pub struct ParsedMigrateMemoryIx {
+    pub num_accounts: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct RelocateIx {
+    pub src_index: [u8; 2], // Pack u16 as [u8; 2]
+    pub dst_index: [u8; 2], // Pack u16 as [u8; 2]
+}
+
+impl RelocateIx {
+    pub fn to_struct(&self) -> Result<ParsedRelocateIx, std::io::Error> {
+        Ok(ParsedRelocateIx {
+            src_index: u16::from_le_bytes(self.src_index),
+            dst_index: u16::from_le_bytes(self.dst_index),
+        })
+    }
+
+    pub fn from_struct(parsed: ParsedRelocateIx) -> Self {
+        RelocateIx {
+            src_index: parsed.src_index.to_le_bytes(),
+            dst_index: parsed.dst_index.to_le_bytes(),
+        }
+    }
+}
+
+pub struct ParsedRelocateIx {
+    pub src_index: u16,
+    pub dst_index: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct RolloverStorageIx {
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct DepositIx {
@@ -363,10 +676,99 @@ pub enum WithdrawIxData {
     } = 0,
     FromStorage {
         packed_va: Vec<u8>,
+        tree_id: u16,
+        leaf_index: u64,
         proof: Vec<Hash>,
         signature: Signature,
     } = 1,
     FromDeposit {
         bump: u8,
     } = 2,
-}
\ No newline at end of file
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MultisigInitUnlockIx {
+    _data: PhantomData<MultisigUnlockIxData>,
+}
+
+impl MultisigInitUnlockIx {
+    pub fn try_from_slice(data: &[u8]) -> Result<MultisigUnlockIxData, std::io::Error> {
+        MultisigUnlockIxData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: MultisigUnlockIxData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = CodeInstruction::MultisigInitUnlockIx as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MultisigUnlockIx {
+    _data: PhantomData<MultisigUnlockIxData>,
+}
+
+impl MultisigUnlockIx {
+    pub fn try_from_slice(data: &[u8]) -> Result<MultisigUnlockIxData, std::io::Error> {
+        MultisigUnlockIxData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: MultisigUnlockIxData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = CodeInstruction::MultisigUnlockIx as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct MultisigUnlockIxData {
+    pub multisig: MultisigOwner,
+    pub signatures: Vec<MultisigSignature>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MultisigWithdrawIx {
+    _data: PhantomData<MultisigWithdrawIxData>,
+}
+
+impl MultisigWithdrawIx {
+    pub fn try_from_slice(data: &[u8]) -> Result<MultisigWithdrawIxData, std::io::Error> {
+        MultisigWithdrawIxData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: MultisigWithdrawIxData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = CodeInstruction::MultisigWithdrawIx as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct MultisigWithdrawIxData {
+    pub multisig: MultisigOwner,
+    pub signatures: Vec<MultisigSignature>,
+    pub withdraw: WithdrawIxData,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MigrateVmIx {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SetRequireVersionedIx {
+    pub require_versioned: u8, // 1 to reject v0 signed messages
+}
@@ -1,5 +1,7 @@
 pub mod consts;
+pub mod error;
 pub mod instruction;
+pub mod event;
 pub mod state;
 pub mod cpis;
 pub mod helpers;
@@ -13,9 +15,17 @@ pub mod external;
 #[cfg(not(feature = "solana"))]
 pub mod sdk;
 
+#[cfg(not(feature = "solana"))]
+pub mod recovery;
+
+#[cfg(not(feature = "solana"))]
+pub mod simulator;
+
 pub mod prelude {
     pub use crate::consts::*;
+    pub use crate::error::*;
     pub use crate::instruction::*;
+    pub use crate::event::*;
     pub use crate::state::*; 
     pub use crate::cpis::*;
     pub use crate::helpers::*;
@@ -28,6 +38,12 @@ pub mod prelude {
 
     #[cfg(not(feature = "solana"))]
     pub use crate::sdk::*;
+
+    #[cfg(not(feature = "solana"))]
+    pub use crate::recovery::*;
+
+    #[cfg(not(feature = "solana"))]
+    pub use crate::simulator::*;
 }
 
 use steel::*;
@@ -1,4 +1,8 @@
-use crate::types::Hash;
+use borsh::{BorshSerialize, BorshDeserialize};
+use std::marker::PhantomData;
+
+use crate::types::{Hash, Signature};
+use crate::utils::{MultisigOwner, MultisigSignature};
 use steel::*;
 
 #[repr(u8)]
@@ -8,6 +12,8 @@ pub enum Opcode {
 
   TransferOp = 11,
   WithdrawOp = 14,
+  MultisigTransferOp = 15,
+  MultisigWithdrawOp = 16,
   RelayOp = 21,
 
   ExternalTransferOp = 10,
@@ -17,16 +23,52 @@ pub enum Opcode {
   ConditionalTransferOp = 12,
 
   AirdropOp = 30,
+  PayoutOp = 31,
+
+  EscrowFundOp = 40,
+  EscrowClaimOp = 41,
+  EscrowRefundOp = 42,
+
+  StreamCreateOp = 50,
+  StreamWithdrawOp = 51,
+  StreamCancelOp = 52,
+
+  ApproveOp = 60,
+  TransferFromOp = 61,
+
+  CloseNonceOp = 70,
+  CloseTimelockOp = 71,
+  CloseRelayOp = 72,
+  CloseEscrowOp = 73,
+  CloseStreamOp = 74,
+  CloseAllowanceOp = 75,
 }
 
 instruction!(Opcode, TransferOp);
 instruction!(Opcode, WithdrawOp);
+instruction!(Opcode, MultisigTransferOp);
+instruction!(Opcode, MultisigWithdrawOp);
 instruction!(Opcode, RelayOp);
 instruction!(Opcode, ExternalTransferOp);
 instruction!(Opcode, ExternalWithdrawOp);
 instruction!(Opcode, ExternalRelayOp);
 instruction!(Opcode, ConditionalTransferOp);
 instruction!(Opcode, AirdropOp);
+instruction!(Opcode, PayoutOp);
+instruction!(Opcode, EscrowFundOp);
+instruction!(Opcode, EscrowClaimOp);
+instruction!(Opcode, EscrowRefundOp);
+instruction!(Opcode, StreamCreateOp);
+instruction!(Opcode, StreamWithdrawOp);
+instruction!(Opcode, StreamCancelOp);
+instruction!(Opcode, ApproveOp);
+instruction!(Opcode, TransferFromOp);
+instruction!(Opcode, CloseNonceOp);
+instruction!(Opcode, CloseTimelockOp);
+instruction!(Opcode, CloseRelayOp);
+instruction!(Opcode, CloseEscrowOp);
+instruction!(Opcode, CloseStreamOp);
+instruction!(Opcode, CloseAllowanceOp);
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
@@ -247,3 +289,313 @@ pub struct ParsedAirdropOp {
     pub amount: u64,
     pub count: u8,
 }
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct PayoutOp {
+    // Dynamically sized data, not supported by Pod (or steel)
+    _data: PhantomData<PayoutOpData>,
+}
+
+impl PayoutOp {
+    pub fn try_from_slice(data: &[u8]) -> Result<PayoutOpData, std::io::Error> {
+        PayoutOpData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: PayoutOpData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = Opcode::PayoutOp as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct PayoutOpData {
+    pub signature: Signature,
+    pub amounts: Vec<u64>, // One amount per destination (in mem_indicies order)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct EscrowFundOp {
+    pub signature: [u8; 64],
+    pub amount: [u8; 8],       // Pack u64 as [u8; 8]
+    pub refund_after: [u8; 8], // Pack i64 as [u8; 8]
+    pub recipient: Pubkey,     // no packing needed
+    pub hashlock: Hash,        // no packing needed
+}
+
+impl EscrowFundOp {
+    /// Converts the byte arrays `amount` and `refund_after` to `u64` and `i64`.
+    pub fn to_struct(&self) -> Result<ParsedEscrowFundOp, std::io::Error> {
+        Ok(ParsedEscrowFundOp {
+            signature: self.signature,
+            amount: u64::from_le_bytes(self.amount),
+            refund_after: i64::from_le_bytes(self.refund_after),
+            recipient: self.recipient,
+            hashlock: self.hashlock,
+        })
+    }
+
+    /// Creates `EscrowFundOp` from the parsed struct by converting the integers back to byte arrays.
+    pub fn from_struct(parsed: ParsedEscrowFundOp) -> Self {
+        EscrowFundOp {
+            signature: parsed.signature,
+            amount: parsed.amount.to_le_bytes(),
+            refund_after: parsed.refund_after.to_le_bytes(),
+            recipient: parsed.recipient,
+            hashlock: parsed.hashlock,
+        }
+    }
+}
+
+pub struct ParsedEscrowFundOp {
+    pub signature: [u8; 64],
+    pub amount: u64,
+    pub refund_after: i64,
+    pub recipient: Pubkey,
+    pub hashlock: Hash,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct EscrowClaimOp {
+    pub preimage: [u8; 32],
+}
+
+impl EscrowClaimOp {
+    // Since EscrowClaimOp only contains byte arrays, no conversion methods are necessary.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct EscrowRefundOp {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct StreamCreateOp {
+    pub signature: [u8; 64],
+    pub amount: [u8; 8],       // Pack u64 as [u8; 8]
+    pub start_time: [u8; 8],   // Pack i64 as [u8; 8]
+    pub end_time: [u8; 8],     // Pack i64 as [u8; 8]
+    pub beneficiary: Pubkey,   // no packing needed
+}
+
+impl StreamCreateOp {
+    /// Converts the byte arrays `amount`, `start_time` and `end_time` to `u64` and `i64`.
+    pub fn to_struct(&self) -> Result<ParsedStreamCreateOp, std::io::Error> {
+        Ok(ParsedStreamCreateOp {
+            signature: self.signature,
+            amount: u64::from_le_bytes(self.amount),
+            start_time: i64::from_le_bytes(self.start_time),
+            end_time: i64::from_le_bytes(self.end_time),
+            beneficiary: self.beneficiary,
+        })
+    }
+
+    /// Creates `StreamCreateOp` from the parsed struct by converting the integers back to byte arrays.
+    pub fn from_struct(parsed: ParsedStreamCreateOp) -> Self {
+        StreamCreateOp {
+            signature: parsed.signature,
+            amount: parsed.amount.to_le_bytes(),
+            start_time: parsed.start_time.to_le_bytes(),
+            end_time: parsed.end_time.to_le_bytes(),
+            beneficiary: parsed.beneficiary,
+        }
+    }
+}
+
+pub struct ParsedStreamCreateOp {
+    pub signature: [u8; 64],
+    pub amount: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub beneficiary: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct StreamWithdrawOp {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct StreamCancelOp {
+    pub signature: [u8; 64],
+}
+
+impl StreamCancelOp {
+    // Since StreamCancelOp only contains byte arrays, no conversion methods are necessary.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ApproveOp {
+    pub signature: [u8; 64],
+    pub amount: [u8; 8],       // Pack u64 as [u8; 8]
+    pub expires_at: [u8; 8],   // Pack i64 as [u8; 8]
+    pub delegate: Pubkey,      // no packing needed
+}
+
+impl ApproveOp {
+    /// Converts the byte arrays `amount` and `expires_at` to `u64` and `i64`.
+    pub fn to_struct(&self) -> Result<ParsedApproveOp, std::io::Error> {
+        Ok(ParsedApproveOp {
+            signature: self.signature,
+            amount: u64::from_le_bytes(self.amount),
+            expires_at: i64::from_le_bytes(self.expires_at),
+            delegate: self.delegate,
+        })
+    }
+
+    /// Creates `ApproveOp` from the parsed struct by converting the integers back to byte arrays.
+    pub fn from_struct(parsed: ParsedApproveOp) -> Self {
+        ApproveOp {
+            signature: parsed.signature,
+            amount: parsed.amount.to_le_bytes(),
+            expires_at: parsed.expires_at.to_le_bytes(),
+            delegate: parsed.delegate,
+        }
+    }
+}
+
+pub struct ParsedApproveOp {
+    pub signature: [u8; 64],
+    pub amount: u64,
+    pub expires_at: i64,
+    pub delegate: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct TransferFromOp {
+    pub signature: [u8; 64],
+    pub amount: [u8; 8], // Pack u64 as [u8; 8]
+}
+
+impl TransferFromOp {
+    /// Converts the byte array `amount` to a `u64`.
+    pub fn to_struct(&self) -> Result<ParsedTransferFromOp, std::io::Error> {
+        Ok(ParsedTransferFromOp {
+            signature: self.signature,
+            amount: u64::from_le_bytes(self.amount),
+        })
+    }
+
+    /// Creates `TransferFromOp` from the parsed struct by converting the `u64` back to a byte array.
+    pub fn from_struct(parsed: ParsedTransferFromOp) -> Self {
+        TransferFromOp {
+            signature: parsed.signature,
+            amount: parsed.amount.to_le_bytes(),
+        }
+    }
+}
+
+pub struct ParsedTransferFromOp {
+    pub signature: [u8; 64],
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseNonceOp {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseTimelockOp {
+    pub signature: [u8; 64],
+}
+
+impl CloseTimelockOp {
+    // Since CloseTimelockOp only contains byte arrays, no conversion methods are necessary.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseRelayOp {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseEscrowOp {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseStreamOp {
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CloseAllowanceOp {
+    pub signature: [u8; 64],
+}
+
+impl CloseAllowanceOp {
+    // Since CloseAllowanceOp only contains byte arrays, no conversion methods are necessary.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MultisigTransferOp {
+    // Dynamically sized data, not supported by Pod (or steel)
+    _data: PhantomData<MultisigTransferOpData>,
+}
+
+impl MultisigTransferOp {
+    pub fn try_from_slice(data: &[u8]) -> Result<MultisigTransferOpData, std::io::Error> {
+        MultisigTransferOpData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: MultisigTransferOpData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = Opcode::MultisigTransferOp as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct MultisigTransferOpData {
+    pub multisig: MultisigOwner,
+    pub signatures: Vec<MultisigSignature>,
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MultisigWithdrawOp {
+    // Dynamically sized data, not supported by Pod (or steel)
+    _data: PhantomData<MultisigWithdrawOpData>,
+}
+
+impl MultisigWithdrawOp {
+    pub fn try_from_slice(data: &[u8]) -> Result<MultisigWithdrawOpData, std::io::Error> {
+        MultisigWithdrawOpData::try_from_slice(data)
+    }
+
+    pub fn try_to_bytes(args: MultisigWithdrawOpData) -> Result<Vec<u8>, std::io::Error> {
+        let discriminator = Opcode::MultisigWithdrawOp as u8;
+        let data = args.try_to_vec()?;
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(discriminator);
+        result.extend_from_slice(&data);
+        Ok(result)
+    }
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct MultisigWithdrawOpData {
+    pub multisig: MultisigOwner,
+    pub signatures: Vec<MultisigSignature>,
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+
+use steel::*;
+use crate::{
+    consts::*,
+    event::*,
+    instruction::WithdrawIxData,
+    types::{Hash, MerkleTree, Signature},
+    utils,
+};
+
+/// An account held in a storage tree, rebuilt from its `CompressEvent`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ColdAccount {
+    pub vm: Pubkey,
+    pub vm_storage: Pubkey,
+    pub tree_id: u16,
+    pub leaf_index: u64,
+    pub packed_va: Vec<u8>,
+    pub signature: Signature,
+    pub is_decompressed: bool,
+}
+
+impl ColdAccount {
+    /// The value stored in the tree for this account (before `as_leaf`).
+    pub fn get_leaf_value(&self) -> Hash {
+        let va_hash = utils::hash(&self.packed_va);
+        utils::get_storage_leaf_value(&self.signature, &va_hash, self.leaf_index)
+    }
+
+    pub fn to_withdraw_data(&self, proof: Vec<Hash>) -> WithdrawIxData {
+        WithdrawIxData::FromStorage {
+            packed_va: self.packed_va.clone(),
+            tree_id: self.tree_id,
+            leaf_index: self.leaf_index,
+            proof,
+            signature: self.signature,
+        }
+    }
+}
+
+/// The contents of every storage tree, rebuilt from the events logged by
+/// compress and decompress. This is enough to decompress or withdraw any
+/// account without the help of the operator.
+///
+/// Events are read from the log messages of a transaction, which must be
+/// applied in the order they were executed.
+#[derive(Clone, Default, Debug)]
+pub struct ColdStorage {
+    accounts: BTreeMap<(Pubkey, u16, u64), ColdAccount>,
+}
+
+impl ColdStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the events in the log messages of one transaction. Only the
+    /// `Program data:` lines logged by this program are used, anything logged
+    /// by another program, including one invoked by this program, is skipped.
+    /// The logs of a failed transaction are skipped, as none of its changes
+    /// were kept.
+    ///
+    /// Fails if the logs were truncated, as events may be missing, or if an
+    /// event would replace an account that is already known. Events before
+    /// the one that failed have been applied. A storage account that is
+    /// closed and created again starts over at the same leaves, so it needs a
+    /// new `ColdStorage`.
+    pub fn apply_logs<S: AsRef<str>>(&mut self, logs: &[S]) -> Result<(), std::io::Error> {
+        let invalid = |message| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+        let program_id = crate::ID.to_string();
+        let mut invoked: Vec<&str> = Vec::new();
+        let mut events = Vec::new();
+
+        for log in logs.iter().map(|log| log.as_ref()) {
+            if log == "Log truncated" {
+                return Err(invalid("the logs are truncated"));
+            }
+
+            let Some(rest) = log.strip_prefix("Program ") else {
+                continue;
+            };
+
+            if let Some(data) = rest.strip_prefix("data: ") {
+                if invoked.last() == Some(&program_id.as_str()) {
+                    for field in data.split(' ') {
+                        let event = base64::decode(field)
+                            .map_err(|_| invalid("program data is not base64"))?;
+                        events.push(event);
+                    }
+                }
+                continue;
+            }
+
+            let mut words = rest.split(' ');
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => invoked.push(program),
+                (Some(_), Some("success")) => {
+                    invoked.pop();
+                }
+                (Some(_), Some("failed:")) => return Ok(()),
+                _ => {}
+            }
+        }
+
+        for event in events.iter() {
+            self.apply_event(event)?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply one logged event. Data that isn't a known event is ignored. An
+    /// event that was already applied is ignored too, but one that differs
+    /// from the known account is an error.
+    fn apply_event(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+        match ParsedEvent::try_from_bytes(data) {
+            Ok(ParsedEvent::Compress(event)) => {
+                let key = (event.vm_storage, event.tree_id, event.leaf_index);
+                let account = ColdAccount {
+                    vm: event.vm,
+                    vm_storage: event.vm_storage,
+                    tree_id: event.tree_id,
+                    leaf_index: event.leaf_index,
+                    packed_va: event.packed_va,
+                    signature: event.signature,
+                    is_decompressed: false,
+                };
+
+                match self.accounts.get(&key) {
+                    Some(existing) if existing.packed_va == account.packed_va
+                        && existing.signature == account.signature
+                        && existing.vm == account.vm => {}
+                    Some(_) => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::AlreadyExists,
+                            "a different account is already at this leaf",
+                        ));
+                    }
+                    None => {
+                        self.accounts.insert(key, account);
+                    }
+                }
+            }
+            Ok(ParsedEvent::Decompress(event)) => {
+                // Only the account whose value was removed is marked, so an
+                // event that names the wrong index can't hide another account.
+                let key = (event.vm_storage, event.tree_id, event.leaf_index);
+                if let Some(account) = self.accounts.get_mut(&key) {
+                    if account.get_leaf_value() == event.leaf {
+                        account.is_decompressed = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, vm_storage: Pubkey, tree_id: u16, leaf_index: u64) -> Option<&ColdAccount> {
+        self.accounts.get(&(vm_storage, tree_id, leaf_index))
+    }
+
+    /// The accounts still held by a storage account, in every tree.
+    pub fn get_accounts(&self, vm_storage: Pubkey) -> Vec<&ColdAccount> {
+        self.accounts.range((vm_storage, 0, 0)..=(vm_storage, u16::MAX, u64::MAX))
+            .map(|(_, account)| account)
+            .filter(|account| !account.is_decompressed)
+            .collect()
+    }
+
+    /// The leaves of one storage tree, in order, with decompressed accounts
+    /// replaced by the empty leaf. Returns None if an event is missing.
+    pub fn get_leaves(&self, vm_storage: Pubkey, tree_id: u16, empty_leaf: Hash) -> Option<Vec<Hash>> {
+        let mut leaves = Vec::new();
+
+        for (i, (_, account)) in self.accounts
+            .range((vm_storage, tree_id, 0)..=(vm_storage, tree_id, u64::MAX))
+            .enumerate()
+        {
+            if account.leaf_index != i as u64 {
+                return None;
+            }
+
+            leaves.push(if account.is_decompressed {
+                empty_leaf
+            } else {
+                MerkleTree::<{COMPRESSED_STATE_DEPTH}>::as_leaf(account.get_leaf_value())
+            });
+        }
+
+        Some(leaves)
+    }
+
+    /// A proof for an account against the current root of its tree. `tree` is
+    /// the `compressed_state` of the storage account, or any tree made with
+    /// the same seeds.
+    pub fn get_merkle_proof(
+        &self,
+        tree: &MerkleTree<{COMPRESSED_STATE_DEPTH}>,
+        vm_storage: Pubkey,
+        tree_id: u16,
+        leaf_index: u64,
+    ) -> Option<Vec<Hash>> {
+        let leaves = self.get_leaves(vm_storage, tree_id, tree.get_empty_leaf())?;
+
+        if leaf_index >= leaves.len() as u64 {
+            return None;
+        }
+
+        Some(tree.get_merkle_proof(&leaves, leaf_index as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compress_event(vm_storage: Pubkey, tree_id: u16, leaf_index: u64) -> Vec<u8> {
+        CompressEvent {
+            vm: Pubkey::default(),
+            vm_storage,
+            tree_id,
+            leaf_index,
+            packed_va: vec![leaf_index as u8; 8],
+            signature: Signature::from([tree_id as u8; 64]),
+        }.try_to_bytes().unwrap()
+    }
+
+    fn program_logs(program: Pubkey, events: &[Vec<u8>]) -> Vec<String> {
+        let mut logs = vec![format!("Program {} invoke [1]", program)];
+        for event in events {
+            logs.push(format!("Program data: {}", base64::encode(event)));
+        }
+        logs.push(format!("Program {} consumed 1000 of 200000 compute units", program));
+        logs.push(format!("Program {} success", program));
+        logs
+    }
+
+    #[test]
+    fn test_rebuild_tree() {
+        let storage = Pubkey::new_unique();
+        let seeds: &[&[u8]] = &[b"test"];
+        let mut tree = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::new(seeds);
+        let mut cold = ColdStorage::new();
+
+        for i in 0..3 {
+            let logs = program_logs(crate::ID, &[compress_event(storage, 0, i)]);
+            assert!(cold.apply_logs(&logs).is_ok());
+
+            let account = cold.get(storage, 0, i).unwrap();
+            assert!(tree.try_insert(account.get_leaf_value()).is_ok());
+        }
+
+        // Unrelated log data is skipped
+        let logs = program_logs(crate::ID, &[vec![], b"hello".to_vec(), compress_event(Pubkey::new_unique(), 0, 0)]);
+        assert!(cold.apply_logs(&logs).is_ok());
+
+        let account = cold.get(storage, 0, 1).unwrap().clone();
+        let proof = cold.get_merkle_proof(&tree, storage, 0, 1).unwrap();
+        assert!(tree.contains(&proof, account.get_leaf_value()));
+        assert!(tree.try_remove(&proof, account.get_leaf_value()).is_ok());
+
+        let logs = program_logs(crate::ID, &[DecompressEvent {
+            vm: Pubkey::default(),
+            vm_storage: storage,
+            tree_id: 0,
+            leaf_index: 1,
+            leaf: account.get_leaf_value(),
+        }.try_to_bytes().unwrap()]);
+        assert!(cold.apply_logs(&logs).is_ok());
+
+        assert_eq!(cold.get_accounts(storage).len(), 2);
+
+        // Proofs for the remaining accounts account for the removed one
+        let account = cold.get(storage, 0, 2).unwrap();
+        let proof = cold.get_merkle_proof(&tree, storage, 0, 2).unwrap();
+        assert!(tree.contains(&proof, account.get_leaf_value()));
+
+        // A missing event means the tree can't be rebuilt
+        let logs = program_logs(crate::ID, &[compress_event(storage, 1, 1)]);
+        assert!(cold.apply_logs(&logs).is_ok());
+        assert!(cold.get_leaves(storage, 1, tree.get_empty_leaf()).is_none());
+    }
+
+    #[test]
+    fn test_only_program_logs() {
+        let storage = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let mut cold = ColdStorage::new();
+
+        // Logged by another program
+        let logs = program_logs(other, &[compress_event(storage, 0, 0)]);
+        assert!(cold.apply_logs(&logs).is_ok());
+        assert!(cold.get(storage, 0, 0).is_none());
+
+        // Logged by a program this one invokes, then by this program
+        let mut logs = vec![format!("Program {} invoke [1]", crate::ID)];
+        logs.extend(program_logs(other, &[compress_event(storage, 0, 0)]));
+        logs.push(format!("Program data: {}", base64::encode(compress_event(storage, 0, 1))));
+        logs.push(format!("Program {} success", crate::ID));
+        assert!(cold.apply_logs(&logs).is_ok());
+        assert!(cold.get(storage, 0, 0).is_none());
+        assert!(cold.get(storage, 0, 1).is_some());
+
+        // Nothing from a failed transaction is kept
+        let mut logs = program_logs(crate::ID, &[compress_event(storage, 0, 2)]);
+        logs.pop();
+        logs.push(format!("Program {} failed: custom program error: 0x1", crate::ID));
+        assert!(cold.apply_logs(&logs).is_ok());
+        assert!(cold.get(storage, 0, 2).is_none());
+
+        // Events may be missing from truncated logs
+        let mut logs = program_logs(crate::ID, &[compress_event(storage, 0, 2)]);
+        logs.insert(2, "Log truncated".to_string());
+        assert!(cold.apply_logs(&logs).is_err());
+    }
+
+    #[test]
+    fn test_decompress_wrong_index() {
+        let storage = Pubkey::new_unique();
+        let mut cold = ColdStorage::new();
+
+        let logs = program_logs(crate::ID, &[compress_event(storage, 0, 0), compress_event(storage, 0, 1)]);
+        assert!(cold.apply_logs(&logs).is_ok());
+
+        // The removed value is that of the account at index 1
+        let leaf = cold.get(storage, 0, 1).unwrap().get_leaf_value();
+        let event = |leaf_index| DecompressEvent {
+            vm: Pubkey::default(),
+            vm_storage: storage,
+            tree_id: 0,
+            leaf_index,
+            leaf,
+        }.try_to_bytes().unwrap();
+
+        assert!(cold.apply_logs(&program_logs(crate::ID, &[event(0)])).is_ok());
+        assert!(!cold.get(storage, 0, 0).unwrap().is_decompressed);
+        assert_eq!(cold.get_accounts(storage).len(), 2);
+
+        assert!(cold.apply_logs(&program_logs(crate::ID, &[event(1)])).is_ok());
+        assert!(cold.get(storage, 0, 1).unwrap().is_decompressed);
+        assert_eq!(cold.get_accounts(storage).len(), 1);
+    }
+
+    #[test]
+    fn test_no_replace() {
+        let storage = Pubkey::new_unique();
+        let mut cold = ColdStorage::new();
+
+        let logs = program_logs(crate::ID, &[compress_event(storage, 0, 0)]);
+        assert!(cold.apply_logs(&logs).is_ok());
+
+        // The same event again changes nothing
+        assert!(cold.apply_logs(&logs).is_ok());
+
+        // A different account at the same leaf is rejected
+        let event = CompressEvent {
+            vm: Pubkey::default(),
+            vm_storage: storage,
+            tree_id: 0,
+            leaf_index: 0,
+            packed_va: vec![42; 8],
+            signature: Signature::from([0; 64]),
+        };
+        let logs = program_logs(crate::ID, &[event.try_to_bytes().unwrap()]);
+        assert!(cold.apply_logs(&logs).is_err());
+
+        assert_eq!(cold.get(storage, 0, 0).unwrap().packed_va, vec![0; 8]);
+    }
+}
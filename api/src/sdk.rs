@@ -28,6 +28,34 @@ pub fn vm_init(vm_authority: Pubkey, mint: Pubkey, lock_duration: u8) -> Instruc
     }
 }
 
+pub fn vm_close(vm_authority: Pubkey, vm: Pubkey, destination: Pubkey) -> Instruction {
+    let (omnibus, _) = find_vm_omnibus_pda(&vm);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(omnibus, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: CloseVmIx {}.to_bytes(),
+    }
+}
+
+pub fn vm_migrate(vm_authority: Pubkey, vm: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: MigrateVmIx {}.to_bytes(),
+    }
+}
+
 pub fn vm_memory_init(
     vm_authority: Pubkey,
     vm: Pubkey,
@@ -80,6 +108,64 @@ pub fn vm_memory_resize(
     }
 }
 
+pub fn vm_memory_compact(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    vm_memory: Pubkey,
+    num_accounts: u32,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(vm_memory, false),
+        ],
+        data: CompactMemoryIx::from_struct(
+            ParsedCompactMemoryIx {
+            num_accounts,
+        }).to_bytes(),
+    }
+}
+
+pub fn vm_memory_migrate(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    vm_memory: Pubkey,
+    num_accounts: u32,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(vm_memory, false),
+        ],
+        data: MigrateMemoryIx::from_struct(
+            ParsedMigrateMemoryIx {
+            num_accounts,
+        }).to_bytes(),
+    }
+}
+
+pub fn vm_memory_close(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    vm_memory: Pubkey,
+    destination: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(vm_memory, false),
+            AccountMeta::new(destination, false),
+        ],
+        data: CloseMemoryIx {}.to_bytes(),
+    }
+}
+
 pub fn vm_storage_init(vm_authority: Pubkey, vm: Pubkey, name: &str) -> Instruction {
     let name = create_name(name);
     let (vm_storage, vm_storage_bump) = find_vm_storage_pda(&vm, &name);
@@ -101,6 +187,41 @@ pub fn vm_storage_init(vm_authority: Pubkey, vm: Pubkey, name: &str) -> Instruct
     }
 }
 
+pub fn vm_storage_close(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    vm_storage: Pubkey,
+    destination: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(vm_storage, false),
+            AccountMeta::new(destination, false),
+        ],
+        data: CloseStorageIx {}.to_bytes(),
+    }
+}
+
+pub fn vm_storage_rollover(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    vm_storage: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(vm_storage, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: RolloverStorageIx {}.to_bytes(),
+    }
+}
+
 pub fn system_nonce_init(
     vm_authority: Pubkey,
     vm: Pubkey,
@@ -175,6 +296,32 @@ pub fn system_account_compress(
     }
 }
 
+pub fn system_account_compress_batch(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    vm_memory: Pubkey,
+    vm_storage: Pubkey,
+    account_indices: Vec<u16>,
+    signature: Signature,
+) -> Instruction {
+    let args = CompressBatchIxData {
+        account_indices,
+        signature,
+    };
+    let data = CompressBatchIx::try_to_bytes(args).unwrap();
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(vm_memory, false),
+            AccountMeta::new(vm_storage, false),
+        ],
+        data,
+    }
+}
+
 pub fn system_account_decompress(
     vm_authority: Pubkey,
     vm: Pubkey,
@@ -184,12 +331,16 @@ pub fn system_account_decompress(
     withdraw_receipt: Option<Pubkey>,
     account_index: u16,
     packed_va: Vec<u8>,
+    tree_id: u16,
+    leaf_index: u64,
     proof: Vec<Hash>,
     signature: Signature,
 ) -> Instruction {
     let args = DecompressIxData {
         account_index,
         packed_va,
+        tree_id,
+        leaf_index,
         proof,
         signature,
     };
@@ -212,6 +363,70 @@ pub fn system_account_decompress(
     }
 }
 
+/// Each entry in `accounts` comes with the (unlock_pda, withdraw_receipt) pair
+/// for that virtual account, only required for timelock accounts.
+pub fn system_account_decompress_batch(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    vm_memory: Pubkey,
+    vm_storage: Pubkey,
+    tree_id: u16,
+    accounts: Vec<(DecompressBatchAccount, Option<Pubkey>, Option<Pubkey>)>,
+    proof: Vec<Hash>,
+) -> Instruction {
+    let mut metas = vec![
+        AccountMeta::new(vm_authority, true),
+        AccountMeta::new(vm, false),
+        AccountMeta::new(vm_memory, false),
+        AccountMeta::new(vm_storage, false),
+    ];
+
+    let mut entries = Vec::with_capacity(accounts.len());
+    for (entry, unlock_pda, withdraw_receipt) in accounts {
+        metas.push(optional_meta(unlock_pda, false));
+        metas.push(optional_meta(withdraw_receipt, false));
+        entries.push(entry);
+    }
+
+    let args = DecompressBatchIxData {
+        tree_id,
+        accounts: entries,
+        proof,
+    };
+
+    let data = DecompressBatchIx::try_to_bytes(args).unwrap();
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: metas,
+        data,
+    }
+}
+
+pub fn system_account_relocate(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    src_memory: Pubkey,
+    src_index: u16,
+    dst_memory: Pubkey,
+    dst_index: u16,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(src_memory, false),
+            AccountMeta::new(dst_memory, false),
+        ],
+        data: RelocateIx::from_struct(
+            ParsedRelocateIx {
+            src_index,
+            dst_index,
+        }).to_bytes(),
+    }
+}
+
 pub fn vm_exec(
     vm_authority: Pubkey,
     vm: Pubkey,
@@ -258,6 +473,182 @@ pub fn vm_exec(
     }
 }
 
+pub fn vm_exec_batch(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    mem_a: Option<Pubkey>,
+    mem_b: Option<Pubkey>,
+    mem_c: Option<Pubkey>,
+    mem_d: Option<Pubkey>,
+    vm_omnibus: Option<Pubkey>,
+    relay: Option<Pubkey>,
+    relay_vault: Option<Pubkey>,
+    external_address: Option<Pubkey>,
+    token_program: Option<Pubkey>,
+    ops: Vec<ExecIxData>,
+) -> Instruction {
+    let args = ExecBatchIxData {
+        ops,
+    };
+    let data = ExecBatchIx::try_to_bytes(args).unwrap();
+
+    let accounts = vec![
+        AccountMeta::new(vm_authority, true),
+        AccountMeta::new(vm, false),
+        optional_meta(mem_a, false),
+        optional_meta(mem_b, false),
+        optional_meta(mem_c, false),
+        optional_meta(mem_d, false),
+        optional_meta(vm_omnibus, false),
+        optional_meta(relay, false),
+        optional_meta(relay_vault, false),
+        optional_meta(external_address, false),
+        optional_readonly_meta(token_program, false),
+    ];
+
+    Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    }
+}
+
+pub fn vm_set_sig_verify_mode(vm_authority: Pubkey, vm: Pubkey, mode: SigVerifyMode) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+        ],
+        data: SetSigVerifyModeIx {
+            mode: mode as u8,
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn vm_set_require_versioned(vm_authority: Pubkey, vm: Pubkey, require_versioned: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+        ],
+        data: SetRequireVersionedIx {
+            require_versioned: require_versioned as u8,
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn vm_set_fee_config(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    fee_collector: Pubkey,
+    fee_flat: u64,
+    fee_bps: u16,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+        ],
+        data: SetFeeConfigIx::from_struct(
+            ParsedSetFeeConfigIx {
+                fee_collector,
+                fee_flat,
+                fee_bps,
+            }
+        ).to_bytes(),
+    }
+}
+
+pub fn vm_propose_authority(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    new_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+        ],
+        data: ProposeAuthorityIx {
+            new_authority,
+        }
+        .to_bytes(),
+    }
+}
+
+pub fn vm_accept_authority(
+    new_authority: Pubkey,
+    vm: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(new_authority, true),
+            AccountMeta::new(vm, false),
+        ],
+        data: AcceptAuthorityIx {}.to_bytes(),
+    }
+}
+
+/// Pause (or resume) the VM, and disable individual opcodes. Opcodes are
+/// given by value, all other opcodes are enabled.
+pub fn vm_set_pause(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    paused: bool,
+    disabled_opcodes: &[Opcode],
+) -> Instruction {
+    let mut mask = [0u8; 32];
+    for opcode in disabled_opcodes {
+        let opcode = *opcode as u8;
+        mask[(opcode / 8) as usize] |= 1 << (opcode % 8);
+    }
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+        ],
+        data: SetPauseIx {
+            paused: paused as u8,
+            disabled_opcodes: mask,
+        }
+        .to_bytes(),
+    }
+}
+
+/// An Ed25519 program instruction that verifies `signature` over `message`.
+/// Include it before a vm_exec or compress instruction when the VM uses
+/// SigVerifyMode::Precompile.
+pub fn ed25519_verify(pubkey: &Pubkey, signature: &Signature, message: &[u8]) -> Instruction {
+    Instruction {
+        program_id: solana_program::ed25519_program::ID,
+        accounts: vec![],
+        data: create_ed25519_instruction_data(
+            pubkey.as_ref(),
+            signature.as_ref(),
+            message,
+        ),
+    }
+}
+
+/// Appends the instructions sysvar to a vm_exec, vm_exec_batch or compress
+/// instruction, required when the VM uses SigVerifyMode::Precompile.
+pub fn with_instructions_sysvar(mut ix: Instruction) -> Instruction {
+    ix.accounts.push(AccountMeta::new_readonly(
+        solana_program::sysvar::instructions::ID,
+        false,
+    ));
+    ix
+}
+
 pub fn relay_init(vm_authority: Pubkey, vm: Pubkey, mint: Pubkey, name: &str) -> Instruction {
     let name = create_name(name);
     let (relay, relay_bump) = find_vm_relay_pda(&vm, &name);
@@ -296,6 +687,28 @@ pub fn relay_save_root(vm_authority: Pubkey, vm: Pubkey, relay: Pubkey) -> Instr
     }
 }
 
+pub fn relay_close(
+    vm_authority: Pubkey,
+    vm: Pubkey,
+    relay: Pubkey,
+    destination: Pubkey,
+) -> Instruction {
+    let (relay_vault, _) = find_vm_relay_vault_pda(&relay);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(vm_authority, true),
+            AccountMeta::new(vm, false),
+            AccountMeta::new(relay, false),
+            AccountMeta::new(relay_vault, false),
+            AccountMeta::new(destination, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: CloseRelayIx {}.to_bytes(),
+    }
+}
+
 pub fn timelock_deposit_from_pda(
     vm_authority: Pubkey,
     vm: Pubkey,
@@ -408,6 +821,79 @@ pub fn timelock_withdraw(
     }
 }
 
+pub fn timelock_multisig_unlock_init(
+    account_owner: Pubkey,
+    payer: Pubkey,
+    vm: Pubkey,
+    unlock_pda: Pubkey,
+    multisig: MultisigOwner,
+    signatures: Vec<MultisigSignature>,
+) -> Instruction {
+    let mut ix = timelock_unlock_init(account_owner, payer, vm, unlock_pda);
+    ix.accounts[0].is_signer = false;
+    ix.data = MultisigInitUnlockIx::try_to_bytes(MultisigUnlockIxData {
+        multisig,
+        signatures,
+    }).unwrap();
+    ix
+}
+
+pub fn timelock_multisig_unlock_finalize(
+    account_owner: Pubkey,
+    payer: Pubkey,
+    vm: Pubkey,
+    unlock_pda: Pubkey,
+    multisig: MultisigOwner,
+    signatures: Vec<MultisigSignature>,
+) -> Instruction {
+    let mut ix = timelock_unlock_finalize(account_owner, payer, vm, unlock_pda);
+    ix.accounts[0].is_signer = false;
+    ix.data = MultisigUnlockIx::try_to_bytes(MultisigUnlockIxData {
+        multisig,
+        signatures,
+    }).unwrap();
+    ix
+}
+
+pub fn timelock_multisig_withdraw(
+    depositor: Pubkey,
+    payer: Pubkey,
+    vm: Pubkey,
+    vm_omnibus: Option<Pubkey>,
+    vm_memory: Option<Pubkey>,
+    vm_storage: Option<Pubkey>,
+    deposit_pda: Option<Pubkey>,
+    deposit_ata: Option<Pubkey>,
+    unlock_pda: Pubkey,
+    withdraw_receipt: Option<Pubkey>,
+    external_address: Pubkey,
+    multisig: MultisigOwner,
+    signatures: Vec<MultisigSignature>,
+    data: WithdrawIxData,
+) -> Instruction {
+    let mut ix = timelock_withdraw(
+        depositor,
+        payer,
+        vm,
+        vm_omnibus,
+        vm_memory,
+        vm_storage,
+        deposit_pda,
+        deposit_ata,
+        unlock_pda,
+        withdraw_receipt,
+        external_address,
+        data.clone(),
+    );
+    ix.accounts[0].is_signer = false;
+    ix.data = MultisigWithdrawIx::try_to_bytes(MultisigWithdrawIxData {
+        multisig,
+        signatures,
+        withdraw: data,
+    }).unwrap();
+    ix
+}
+
 fn withdraw_from_deposit(
     depositor: Pubkey,
     payer: Pubkey,
@@ -0,0 +1,1095 @@
+use std::{collections::BTreeMap, time::{SystemTime, UNIX_EPOCH}};
+
+use solana_program::ed25519_program;
+
+use steel::*;
+use crate::{
+    consts::*,
+    cvm::*,
+    error::CodeVmError,
+    helpers::{check_condition, compress_leaf, decompress_archived_leaves, decompress_leaf},
+    instruction::*,
+    opcode::*,
+    types::{Hash, MerkleTree, Signature, SliceAllocator},
+    utils,
+};
+
+/// A memory account as the simulator sees it: the packed virtual account held
+/// at each allocated index.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SimulatedMemory {
+    pub capacity: usize,
+    pub account_size: usize,
+    accounts: BTreeMap<u16, Vec<u8>>,
+    /// The layout a legacy memory account was created for, `None` for
+    /// current memory accounts.
+    legacy_layout: Option<LegacyMemoryLayout>,
+}
+
+impl SimulatedMemory {
+    pub fn new(capacity: usize, account_size: usize) -> Self {
+        Self {
+            capacity,
+            account_size,
+            accounts: BTreeMap::new(),
+            legacy_layout: None,
+        }
+    }
+
+    /// Read a memory account from its on-chain data.
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        let memory = read_account::<MemoryAccount>(data)?;
+        let (n, m) = (memory.get_capacity(), memory.get_account_size());
+        let mem = SliceAllocator::try_from_slice(&data[MemoryAccount::get_size()..], n, m)?;
+
+        let mut result = Self::new(n, m);
+        if memory.get_version() != MemoryVersion::Current {
+            result.legacy_layout = Some(memory.get_legacy_layout());
+        }
+        for index in 0..n {
+            if let Some(item) = mem.read_item(index as u16) {
+                result.accounts.insert(index as u16, item);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// The virtual account at `index`, if there is one.
+    pub fn get(&self, index: u16) -> Option<VirtualAccount> {
+        VirtualAccount::unpack(self.accounts.get(&index)?).ok()
+    }
+
+    /// The bytes stored at `index`. Written accounts are exactly
+    /// `VirtualAccount::pack`, accounts read from chain keep their padding.
+    pub fn get_packed(&self, index: u16) -> Option<&[u8]> {
+        self.accounts.get(&index).map(|item| item.as_slice())
+    }
+
+    pub fn num_accounts(&self) -> usize {
+        self.accounts.len()
+    }
+
+    fn check_index(&self, index: u16) -> ProgramResult {
+        // On-chain, an index past the capacity fails in the SliceAllocator.
+        if index as usize >= self.capacity {
+            return Err(ProgramError::InvalidArgument);
+        }
+        Ok(())
+    }
+
+    fn try_read(&self, index: u16) -> Result<VirtualAccount, ProgramError> {
+        self.check_index(index)?;
+
+        let item = self.accounts.get(&index);
+        check_condition(
+            item.is_some(),
+            CodeVmError::AccountNotAllocated,
+            "the virtual account is not allocated",
+        )?;
+
+        VirtualAccount::unpack(item.unwrap())
+    }
+
+    fn check_is_empty(&self, index: u16) -> ProgramResult {
+        self.check_index(index)?;
+
+        check_condition(
+            !self.accounts.contains_key(&index),
+            CodeVmError::AccountAlreadyAllocated,
+            "the virtual account is already allocated",
+        )
+    }
+
+    fn try_write(&mut self, index: u16, account: &VirtualAccount) -> ProgramResult {
+        // Same check as helpers::try_write, before the index is looked at.
+        let allowed = match self.legacy_layout {
+            Some(layout) => layout.allows(account),
+            None => true,
+        };
+        check_condition(
+            allowed,
+            CodeVmError::InvalidMemoryLayout,
+            "the virtual account does not match the legacy memory layout",
+        )?;
+
+        self.check_index(index)?;
+
+        let data = account.pack();
+        if data.len() > self.account_size {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        self.accounts.insert(index, data);
+        Ok(())
+    }
+
+    fn try_delete(&mut self, index: u16) -> ProgramResult {
+        self.check_index(index)?;
+        self.accounts.remove(&index);
+        Ok(())
+    }
+}
+
+/// A storage account as the simulator sees it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SimulatedStorage {
+    pub tree_id: u16,
+    pub compressed_state: MerkleTree<{COMPRESSED_STATE_DEPTH}>,
+    pub changelog: Option<Box<StorageChangeLog>>,
+    pub archived_roots: Vec<Hash>,
+}
+
+impl SimulatedStorage {
+    /// Read a storage account from its on-chain data.
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        let storage = read_account::<StorageAccount>(data)?;
+
+        let changelog = if data.len() >= StorageAccount::get_size_with_changelog() {
+            let offset = StorageAccount::get_size();
+            let changelog = bytemuck::try_pod_read_unaligned::<StorageChangeLog>(
+                &data[offset..offset + StorageChangeLog::get_size()]
+            ).map_err(|_| ProgramError::InvalidAccountData)?;
+            Some(Box::new(changelog))
+        } else {
+            None
+        };
+
+        let num_archived = storage.tree_id as usize;
+        if num_archived > 0 && data.len() < StorageAccount::get_size_with_archived_trees(num_archived) {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let offset = StorageAccount::get_size_with_changelog();
+        let archived_roots = (0..num_archived)
+            .map(|i| Hash::new(&data[offset + i * Hash::LEN..offset + (i + 1) * Hash::LEN]))
+            .collect();
+
+        Ok(Self {
+            tree_id: storage.tree_id,
+            compressed_state: storage.compressed_state,
+            changelog,
+            archived_roots,
+        })
+    }
+
+    fn try_compress(&mut self, signature: &Signature, va_hash: &Hash) -> Result<u64, ProgramError> {
+        compress_leaf(&mut self.compressed_state, self.changelog.as_deref_mut(), signature, va_hash)
+    }
+
+    fn try_decompress(
+        &mut self,
+        tree_id: u16,
+        signature: &Signature,
+        va_hash: &Hash,
+        leaf_index: u64,
+        proof: &[Hash],
+    ) -> Result<Hash, ProgramError> {
+        check_condition(
+            tree_id <= self.tree_id,
+            CodeVmError::UnknownTreeId,
+            "unknown storage tree id",
+        )?;
+
+        if tree_id < self.tree_id {
+            let empty_leaf = self.compressed_state.get_empty_leaf();
+            let root = &mut self.archived_roots[tree_id as usize];
+            let values = decompress_archived_leaves(root, empty_leaf, &[leaf_index], &[*signature], &[*va_hash], proof)?;
+            return Ok(values[0]);
+        }
+
+        decompress_leaf(
+            &mut self.compressed_state,
+            self.changelog.as_deref_mut(),
+            signature,
+            va_hash,
+            leaf_index,
+            proof,
+        )
+    }
+}
+
+/// The accounts of an exec or exec_batch instruction, with the optional ones
+/// that were left out set to None.
+struct ExecAccounts {
+    banks: [Option<Pubkey>; 4],
+    omnibus: Option<Pubkey>,
+    relay: Option<Pubkey>,
+    relay_vault: Option<Pubkey>,
+    external_address: Option<Pubkey>,
+    token_program: Option<Pubkey>,
+    has_instructions_sysvar: bool,
+}
+
+/// The memory banks of an exec instruction, for the opcodes in `cvm::exec`.
+struct SimulatedExec<'a> {
+    vm_address: Pubkey,
+    unix_timestamp: i64,
+    banks: [Option<Pubkey>; 4],
+    memories: &'a mut BTreeMap<Pubkey, SimulatedMemory>,
+
+    /// The instructions before this one in the transaction, if the
+    /// instructions sysvar was passed.
+    instructions: Option<&'a [Instruction]>,
+}
+
+impl SimulatedExec<'_> {
+    fn memory(&self, bank: u8) -> Result<&SimulatedMemory, ProgramError> {
+        let address = get_bank(&self.banks, bank)?;
+        self.memories.get(&address).ok_or(CodeVmError::MissingAccount.into())
+    }
+
+    fn memory_mut(&mut self, bank: u8) -> Result<&mut SimulatedMemory, ProgramError> {
+        let address = get_bank(&self.banks, bank)?;
+        self.memories.get_mut(&address).ok_or(CodeVmError::MissingAccount.into())
+    }
+}
+
+impl ExecState for SimulatedExec<'_> {
+    fn get_vm_address(&self) -> &Pubkey {
+        &self.vm_address
+    }
+
+    fn try_read(&self, bank: u8, index: u16) -> Result<VirtualAccount, ProgramError> {
+        self.memory(bank)?.try_read(index)
+    }
+
+    fn try_write(&mut self, bank: u8, index: u16, va: &VirtualAccount) -> ProgramResult {
+        self.memory_mut(bank)?.try_write(index, va)
+    }
+
+    fn try_delete(&mut self, bank: u8, index: u16) -> ProgramResult {
+        self.memory_mut(bank)?.try_delete(index)
+    }
+
+    fn check_is_empty(&self, bank: u8, index: u16) -> ProgramResult {
+        self.memory(bank)?.check_is_empty(index)
+    }
+
+    fn sig_verify(
+        &self,
+        vm: &CodeVmAccount,
+        pubkey: &[u8],
+        sig: &[u8],
+        message: &[u8],
+    ) -> ProgramResult {
+        simulate_sig_verify(vm, self.instructions, pubkey, sig, message)
+    }
+
+    fn get_unix_timestamp(&self) -> Result<i64, ProgramError> {
+        Ok(self.unix_timestamp)
+    }
+}
+
+/// A host-side model of one VM, its memory banks, storage trees and relays.
+/// Instructions built with the sdk can be applied to it to predict their
+/// result before they are sent: the virtual accounts, trees and poh it ends
+/// up with are the ones the program would produce.
+///
+/// Supported are exec and exec_batch with every opcode, compress and
+/// decompress. Everything else returns InvalidInstructionData. Opcodes run the
+/// same state transitions as the program (see `cvm::exec`), so errors are the
+/// ones the program returns, and a failed instruction leaves the simulator
+/// unchanged.
+///
+/// Token accounts are not modelled, so a relay vault or omnibus without enough
+/// tokens is not caught, and a withdraw receipt is assumed to not exist yet.
+/// In precompile sig verify mode, signatures must be verified by an Ed25519
+/// instruction earlier in the same `process_transaction` call.
+#[derive(Clone, Debug)]
+pub struct VmSimulator {
+    pub vm_address: Pubkey,
+    pub vm: CodeVmAccount,
+
+    /// Used to check the `valid_until` of signed intents, in place of the
+    /// clock sysvar. Defaults to the time the simulator was created.
+    pub unix_timestamp: i64,
+
+    memories: BTreeMap<Pubkey, SimulatedMemory>,
+    storages: BTreeMap<Pubkey, SimulatedStorage>,
+    relays: BTreeMap<Pubkey, RelayAccount>,
+}
+
+impl VmSimulator {
+    pub fn new(vm_address: Pubkey, vm: CodeVmAccount) -> Self {
+        Self {
+            vm_address,
+            vm,
+            unix_timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs() as i64)
+                .unwrap_or_default(),
+            memories: BTreeMap::new(),
+            storages: BTreeMap::new(),
+            relays: BTreeMap::new(),
+        }
+    }
+
+    /// Start from the on-chain data of the VM account.
+    pub fn from_account_data(vm_address: Pubkey, data: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self::new(vm_address, read_account::<CodeVmAccount>(data)?))
+    }
+
+    /// Add a memory account from its on-chain data. Every memory, storage and
+    /// relay account used by an instruction must be added first.
+    pub fn add_memory(&mut self, address: Pubkey, data: &[u8]) -> ProgramResult {
+        let memory = read_account::<MemoryAccount>(data)?;
+        self.check_vm(memory.vm)?;
+        self.memories.insert(address, SimulatedMemory::from_account_data(data)?);
+        Ok(())
+    }
+
+    pub fn add_storage(&mut self, address: Pubkey, data: &[u8]) -> ProgramResult {
+        let storage = read_account::<StorageAccount>(data)?;
+        self.check_vm(storage.vm)?;
+        self.storages.insert(address, SimulatedStorage::from_account_data(data)?);
+        Ok(())
+    }
+
+    pub fn add_relay(&mut self, address: Pubkey, data: &[u8]) -> ProgramResult {
+        let relay = read_account::<RelayAccount>(data)?;
+        self.check_vm(relay.vm)?;
+        self.relays.insert(address, relay);
+        Ok(())
+    }
+
+    pub fn get_memory(&self, address: &Pubkey) -> Option<&SimulatedMemory> {
+        self.memories.get(address)
+    }
+
+    pub fn get_storage(&self, address: &Pubkey) -> Option<&SimulatedStorage> {
+        self.storages.get(address)
+    }
+
+    pub fn get_relay(&self, address: &Pubkey) -> Option<&RelayAccount> {
+        self.relays.get(address)
+    }
+
+    pub fn get_virtual_account(&self, memory: &Pubkey, index: u16) -> Option<VirtualAccount> {
+        self.memories.get(memory)?.get(index)
+    }
+
+    /// Apply a VM instruction, as a transaction of its own. On error, nothing
+    /// is changed.
+    pub fn process_instruction(&mut self, ix: &Instruction) -> ProgramResult {
+        self.process_transaction(std::slice::from_ref(ix))
+    }
+
+    /// Apply several instructions, as one transaction. Ed25519 precompile
+    /// instructions are skipped, opcodes check their signatures when they
+    /// need them. On error, nothing is changed.
+    pub fn process_transaction(&mut self, ixs: &[Instruction]) -> ProgramResult {
+        let mut next = self.clone();
+        for (i, ix) in ixs.iter().enumerate() {
+            if ix.program_id == ed25519_program::ID {
+                continue;
+            }
+            next.try_process_instruction(ix, &ixs[..i])?;
+        }
+        *self = next;
+        Ok(())
+    }
+
+    fn try_process_instruction(&mut self, ix: &Instruction, previous: &[Instruction]) -> ProgramResult {
+        if ix.program_id != crate::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let (discriminator, data) = ix.data.split_first()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+
+        let instruction = CodeInstruction::try_from(*discriminator)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+        let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+
+        match instruction {
+            CodeInstruction::ExecIx => {
+                let args = ExecIx::try_from_slice(data)?;
+                let accounts = self.load_exec_accounts(&ix.accounts)?;
+                self.exec_opcode(&accounts, previous, &args)?;
+            }
+            CodeInstruction::ExecBatchIx => {
+                let args = ExecBatchIx::try_from_slice(data)?;
+                let accounts = self.load_exec_accounts(&ix.accounts)?;
+
+                check_condition(
+                    !args.ops.is_empty(),
+                    CodeVmError::InvalidInstructionData,
+                    "at least one opcode must be provided",
+                )?;
+
+                for op in args.ops.iter() {
+                    self.exec_opcode(&accounts, previous, op)?;
+
+                    let op_data = ExecIx::try_to_bytes(op.clone())?;
+                    self.advance_poh(CodeInstruction::ExecIx, &keys, &op_data[1..]);
+                }
+
+                return Ok(());
+            }
+            CodeInstruction::CompressIx => self.compress(&ix.accounts, previous, data)?,
+            CodeInstruction::DecompressIx => self.decompress(&ix.accounts, data)?,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        }
+
+        self.advance_poh(instruction, &keys, data);
+
+        Ok(())
+    }
+
+    fn advance_poh(&mut self, ix: CodeInstruction, keys: &[Pubkey], data: &[u8]) {
+        self.vm.poh = self.vm.get_next_poh(ix, keys, data);
+        self.vm.advance_slot();
+    }
+
+    fn check_vm(&self, vm: Pubkey) -> ProgramResult {
+        check_condition(
+            vm.eq(&self.vm_address),
+            CodeVmError::AccountMismatch,
+            "vm does not match the VM account",
+        )
+    }
+
+    fn check_vm_authority(&self, accounts: &[AccountMeta]) -> ProgramResult {
+        let [vm_authority, vm, ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_condition(
+            vm_authority.is_signer && self.vm.get_current_authority().eq(&vm_authority.pubkey),
+            CodeVmError::Unauthorized,
+            "vm_authority does not match the authority of the VM account",
+        )?;
+
+        self.check_vm(vm.pubkey)?;
+
+        check_condition(
+            !self.vm.is_paused(),
+            CodeVmError::Paused,
+            "the VM is paused",
+        )
+    }
+
+    fn load_exec_accounts(&self, accounts: &[AccountMeta]) -> Result<ExecAccounts, ProgramError> {
+        if accounts.len() != 11 && accounts.len() != 12 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        self.check_vm_authority(accounts)?;
+
+        let optional = |i: usize| match accounts[i].pubkey {
+            crate::ID => None,
+            key => Some(key),
+        };
+
+        let banks = [optional(2), optional(3), optional(4), optional(5)];
+        let provided: Vec<Pubkey> = banks.iter().flatten().copied().collect();
+
+        for (i, bank) in provided.iter().enumerate() {
+            self.memory(bank)?;
+
+            check_condition(
+                !provided[i + 1..].contains(bank),
+                CodeVmError::DuplicateAccount,
+                "provided memory banks must be unique",
+            )?;
+        }
+
+        Ok(ExecAccounts {
+            banks,
+            omnibus: optional(6),
+            relay: optional(7),
+            relay_vault: optional(8),
+            external_address: optional(9),
+            token_program: optional(10),
+            has_instructions_sysvar: check_instructions_sysvar(accounts.get(11))?,
+        })
+    }
+
+    fn memory(&self, address: &Pubkey) -> Result<&SimulatedMemory, ProgramError> {
+        self.memories.get(address).ok_or(CodeVmError::MissingAccount.into())
+    }
+
+    fn memory_mut(&mut self, address: &Pubkey) -> Result<&mut SimulatedMemory, ProgramError> {
+        self.memories.get_mut(address).ok_or(CodeVmError::MissingAccount.into())
+    }
+
+    fn storage_mut(&mut self, address: &Pubkey) -> Result<&mut SimulatedStorage, ProgramError> {
+        self.storages.get_mut(address).ok_or(CodeVmError::MissingAccount.into())
+    }
+
+    fn try_read(&self, memory: &Pubkey, index: u16) -> Result<VirtualAccount, ProgramError> {
+        self.memory(memory)?.try_read(index)
+    }
+
+    fn try_write(&mut self, memory: &Pubkey, index: u16, account: &VirtualAccount) -> ProgramResult {
+        self.memory_mut(memory)?.try_write(index, account)
+    }
+
+    fn try_delete(&mut self, memory: &Pubkey, index: u16) -> ProgramResult {
+        self.memory_mut(memory)?.try_delete(index)
+    }
+
+    fn exec_opcode(
+        &mut self,
+        accounts: &ExecAccounts,
+        previous: &[Instruction],
+        args: &ExecIxData,
+    ) -> ProgramResult {
+        let ix = Opcode::try_from(args.opcode)
+            .map_err(|_| CodeVmError::InvalidOpcode)?;
+
+        check_condition(
+            self.vm.is_opcode_enabled(args.opcode),
+            CodeVmError::OpcodeDisabled,
+            "the opcode is disabled",
+        )?;
+
+        // The accounts the program checks before it runs the opcode, see the
+        // files in program/src/opcode.
+        match ix {
+            Opcode::ExternalTransferOp | Opcode::ExternalWithdrawOp => {
+                require_account(accounts.omnibus, "the omnibus account must be provided")?;
+                require_account(accounts.external_address, "the external address account must be provided")?;
+                require_account(accounts.token_program, "the token program account must be provided")?;
+            }
+            Opcode::ConditionalTransferOp => {
+                self.check_omnibus(accounts)?;
+                require_account(accounts.external_address, "the external address account must be provided")?;
+                require_account(accounts.token_program, "the token program account must be provided")?;
+            }
+            Opcode::RelayOp => {
+                self.check_omnibus(accounts)?;
+                self.check_relay(accounts)?;
+            }
+            Opcode::ExternalRelayOp => {
+                require_account(accounts.external_address, "the external address account must be provided")?;
+                self.check_relay(accounts)?;
+            }
+            _ => {}
+        }
+
+        let vm = self.vm;
+        let external_address = accounts.external_address.unwrap_or_default();
+        let relay_address = accounts.relay.unwrap_or_default();
+
+        let mut state = SimulatedExec {
+            vm_address: self.vm_address,
+            unix_timestamp: self.unix_timestamp,
+            banks: accounts.banks,
+            memories: &mut self.memories,
+            instructions: accounts.has_instructions_sysvar.then_some(previous),
+        };
+        let state = &mut state;
+
+        match ix {
+            Opcode::TransferOp             => exec::transfer(state, &vm, args),
+            Opcode::WithdrawOp             => exec::withdraw(state, &vm, args),
+            Opcode::RelayOp                => {
+                let relay = self.relays.get_mut(&relay_address).ok_or(CodeVmError::MissingAccount)?;
+                exec::relay(state, &vm, args, &relay_address, relay).map(|_| ())
+            }
+
+            Opcode::MultisigTransferOp     => exec::multisig_transfer(state, &vm, args),
+            Opcode::MultisigWithdrawOp     => exec::multisig_withdraw(state, &vm, args),
+
+            Opcode::ExternalTransferOp     => exec::external_transfer(state, &vm, args, &external_address).map(|_| ()),
+            Opcode::ExternalWithdrawOp     => exec::external_withdraw(state, &vm, args, &external_address).map(|_| ()),
+            Opcode::ExternalRelayOp        => {
+                let relay = self.relays.get_mut(&relay_address).ok_or(CodeVmError::MissingAccount)?;
+                exec::external_relay(state, args, &relay_address, relay, &external_address).map(|_| ())
+            }
+
+            Opcode::ConditionalTransferOp  => exec::conditional_transfer(state, &vm, args, &external_address).map(|_| ()),
+
+            Opcode::AirdropOp              => exec::airdrop(state, &vm, args),
+            Opcode::PayoutOp               => exec::payout(state, &vm, args),
+
+            Opcode::EscrowFundOp           => exec::escrow_fund(state, &vm, args),
+            Opcode::EscrowClaimOp          => exec::escrow_claim(state, args),
+            Opcode::EscrowRefundOp         => exec::escrow_refund(state, args),
+
+            Opcode::StreamCreateOp         => exec::stream_create(state, &vm, args),
+            Opcode::StreamWithdrawOp       => exec::stream_withdraw(state, args),
+            Opcode::StreamCancelOp         => exec::stream_cancel(state, &vm, args),
+
+            Opcode::ApproveOp              => exec::approve(state, &vm, args),
+            Opcode::TransferFromOp         => exec::transfer_from(state, &vm, args),
+
+            Opcode::CloseNonceOp           => exec::close_nonce(state, args),
+            Opcode::CloseTimelockOp        => exec::close_timelock(state, &vm, args),
+            Opcode::CloseRelayOp           => exec::close_relay(state, args),
+            Opcode::CloseEscrowOp          => exec::close_escrow(state, args),
+            Opcode::CloseStreamOp          => exec::close_stream(state, args),
+            Opcode::CloseAllowanceOp       => exec::close_allowance(state, &vm, args),
+
+            _ => Err(CodeVmError::InvalidOpcode.into()),
+        }
+    }
+
+    fn check_omnibus(&self, accounts: &ExecAccounts) -> ProgramResult {
+        let omnibus = require_account(accounts.omnibus, "the omnibus account must be provided")?;
+
+        check_condition(
+            omnibus.eq(&self.vm.omnibus.vault),
+            CodeVmError::AccountMismatch,
+            "omnibus does not match the VM omnibus",
+        )
+    }
+
+    /// Check the relay used by a relay opcode, the way the program does.
+    fn check_relay(&self, accounts: &ExecAccounts) -> ProgramResult {
+        let relay_address = require_account(accounts.relay, "the relay account must be provided")?;
+        let relay_vault = require_account(accounts.relay_vault, "the relay_vault account must be provided")?;
+        require_account(accounts.token_program, "the token program account must be provided")?;
+
+        let relay = self.relays.get(&relay_address).ok_or(CodeVmError::MissingAccount)?;
+
+        check_condition(
+            relay_vault.eq(&relay.treasury.vault),
+            CodeVmError::AccountMismatch,
+            "relay_vault does not match the relay treasury",
+        )
+    }
+
+    // See program/src/instruction/compress.rs
+    fn compress(&mut self, accounts: &[AccountMeta], previous: &[Instruction], data: &[u8]) -> ProgramResult {
+        let args = CompressIx::try_from_bytes(data)?.to_struct()?;
+
+        let (vm_authority, vm_memory, vm_storage, instructions) = match accounts {
+            [a0, _, a2, a3] => (a0, a2, a3, None),
+            [a0, _, a2, a3, a4] => (a0, a2, a3, Some(a4)),
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+        let instructions = check_instructions_sysvar(instructions)?.then_some(previous);
+
+        self.check_vm_authority(accounts)?;
+
+        let va = self.try_read(&vm_memory.pubkey, args.account_index)?;
+        let va_hash = va.get_hash();
+
+        simulate_sig_verify(
+            &self.vm,
+            instructions,
+            vm_authority.pubkey.as_ref(),
+            args.signature.as_ref(),
+            va_hash.as_ref(),
+        )?;
+
+        self.storage_mut(&vm_storage.pubkey)?.try_compress(&args.signature, &va_hash)?;
+        self.try_delete(&vm_memory.pubkey, args.account_index)
+    }
+
+    // See program/src/instruction/decompress.rs
+    fn decompress(&mut self, accounts: &[AccountMeta], data: &[u8]) -> ProgramResult {
+        let args = DecompressIx::try_from_slice(data)?;
+
+        let [_vm_authority, _vm, vm_memory, vm_storage, unlock_pda, withdraw_receipt] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        self.check_vm_authority(accounts)?;
+        self.memory(&vm_memory.pubkey)?.check_is_empty(args.account_index)?;
+
+        let va = VirtualAccount::unpack(&args.packed_va)?;
+
+        if let VirtualAccount::Timelock(vta) = &va {
+            check_condition(
+                unlock_pda.pubkey != crate::ID,
+                CodeVmError::MissingAccount,
+                "unlock_pda address is required for timelocked virtual accounts",
+            )?;
+
+            check_condition(
+                withdraw_receipt.pubkey != crate::ID,
+                CodeVmError::MissingAccount,
+                "withdraw_receipt address is required for timelocked virtual accounts",
+            )?;
+
+            let timelock_address = vta.get_timelock_address(
+                &self.vm.get_mint(),
+                &self.vm.get_authority(),
+                self.vm.get_lock_duration(),
+            );
+            let unlock_address = vta.get_unlock_address(&timelock_address, &self.vm_address);
+
+            check_condition(
+                unlock_pda.pubkey.eq(&unlock_address),
+                CodeVmError::AccountMismatch,
+                "unlock_pda does not match the expected unlock address",
+            )?;
+
+            let receipt_address = vta.get_withdraw_receipt_address(&unlock_address, &self.vm_address);
+
+            check_condition(
+                withdraw_receipt.pubkey.eq(&receipt_address),
+                CodeVmError::AccountMismatch,
+                "withdraw_receipt does not match the expected receipt address",
+            )?;
+        }
+
+        self.storage_mut(&vm_storage.pubkey)?
+            .try_decompress(args.tree_id, &args.signature, &va.get_hash(), args.leaf_index, &args.proof)?;
+
+        self.try_write(&vm_memory.pubkey, args.account_index, &va)
+    }
+}
+
+fn get_bank(banks: &[Option<Pubkey>; 4], bank: u8) -> Result<Pubkey, ProgramError> {
+    let bank = banks.get(bank as usize).copied().flatten();
+
+    check_condition(
+        bank.is_some(),
+        CodeVmError::MissingAccount,
+        "the memory account must be provided",
+    )?;
+
+    Ok(bank.unwrap())
+}
+
+fn require_account(account: Option<Pubkey>, message: &str) -> Result<Pubkey, ProgramError> {
+    check_condition(account.is_some(), CodeVmError::MissingAccount, message)?;
+    Ok(account.unwrap())
+}
+
+/// Whether the optional instructions sysvar account was passed.
+fn check_instructions_sysvar(account: Option<&AccountMeta>) -> Result<bool, ProgramError> {
+    match account {
+        Some(meta) if meta.pubkey != sysvar::instructions::ID => Err(ProgramError::UnsupportedSysvar),
+        Some(_) => Ok(true),
+        None => Ok(false),
+    }
+}
+
+/// `vm_sig_verify`, with the instructions of the transaction in place of the
+/// instructions sysvar.
+fn simulate_sig_verify(
+    vm: &CodeVmAccount,
+    instructions: Option<&[Instruction]>,
+    pubkey: &[u8],
+    sig: &[u8],
+    message: &[u8],
+) -> ProgramResult {
+    match vm.get_sig_verify_mode() {
+        SigVerifyMode::Program => utils::sig_verify(pubkey, sig, message)
+            .map_err(|_| CodeVmError::InvalidSignature.into()),
+        SigVerifyMode::Precompile => {
+            check_condition(
+                instructions.is_some(),
+                CodeVmError::MissingAccount,
+                "the instructions sysvar must be provided",
+            )?;
+
+            utils::sig_verify_precompile_instructions(instructions.unwrap(), pubkey, sig, message)
+        }
+    }
+}
+
+/// Read account state from its data, which may not be aligned.
+fn read_account<T: Pod + Discriminator>(data: &[u8]) -> Result<T, ProgramError> {
+    let size = std::mem::size_of::<T>();
+
+    if data.len() < 8 + size || data[0] != T::discriminator() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    bytemuck::try_pod_read_unaligned::<T>(&data[8..8 + size])
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pdas::*, sdk::*, types::Signature};
+    use solana_sdk::{signature::Keypair, signer::Signer};
+
+    const MEM_A: Pubkey = Pubkey::new_from_array([1; 32]);
+    const MEM_B: Pubkey = Pubkey::new_from_array([2; 32]);
+    const STORAGE: Pubkey = Pubkey::new_from_array([3; 32]);
+
+    fn create_timelock(vm: &CodeVmAccount, vm_address: &Pubkey, owner: &Pubkey, balance: u64) -> VirtualTimelockAccount {
+        let (timelock, bump) = find_virtual_timelock_address(
+            &vm.get_mint(),
+            &vm.get_authority(),
+            owner,
+            vm.get_lock_duration(),
+        );
+        let (_, token_bump) = find_virtual_timelock_vault_address(&timelock);
+        let (unlock, unlock_bump) = find_unlock_address(owner, &timelock, vm_address);
+        let instance = Hash::new_from_array(owner.to_bytes());
+        let (_, withdraw_bump) = find_withdraw_receipt_address(&unlock, &instance, vm_address);
+
+        VirtualTimelockAccount {
+            owner: *owner,
+            instance,
+            token_bump,
+            unlock_bump,
+            withdraw_bump,
+            balance,
+            bump,
+        }
+    }
+
+    fn sign(key: &Keypair, message: &[u8]) -> Signature {
+        Signature::new(key.sign_message(message).as_ref())
+    }
+
+    #[test]
+    fn test_simulate_exec_and_compression() {
+        let authority = Keypair::new();
+        let (src_key, dst_key) = (Keypair::new(), Keypair::new());
+
+        let mut vm = CodeVmAccount::zeroed();
+        vm.authority = authority.pubkey();
+        vm.mint = Pubkey::new_unique();
+        vm.lock_duration = 21;
+        let (vm_address, _) = find_vm_pda(&vm.mint, &vm.authority, vm.lock_duration);
+
+        let src = create_timelock(&vm, &vm_address, &src_key.pubkey(), 100);
+        let dst = create_timelock(&vm, &vm_address, &dst_key.pubkey(), 0);
+        let vdn = VirtualDurableNonce {
+            address: Pubkey::new_unique(),
+            value: Hash::new_from_array([7; 32]),
+        };
+
+        let mut sim = VmSimulator::new(vm_address, vm);
+        sim.memories.insert(MEM_A, SimulatedMemory::new(10, VirtualDurableNonce::LEN + 1));
+        sim.memories.insert(MEM_B, SimulatedMemory::new(10, VirtualTimelockAccount::LEN + 1));
+        sim.storages.insert(STORAGE, SimulatedStorage {
+            tree_id: 0,
+            compressed_state: MerkleTree::new(&[b"storage"]),
+            changelog: Some(Box::new(StorageChangeLog::zeroed())),
+            archived_roots: vec![],
+        });
+
+        let mem_a = sim.memory_mut(&MEM_A).unwrap();
+        mem_a.try_write(0, &VirtualAccount::Nonce(vdn)).unwrap();
+        let mem_b = sim.memory_mut(&MEM_B).unwrap();
+        mem_b.try_write(0, &VirtualAccount::Timelock(src)).unwrap();
+        mem_b.try_write(1, &VirtualAccount::Timelock(dst)).unwrap();
+
+        let exec = |data: Vec<u8>| vm_exec(
+            authority.pubkey(),
+            vm_address,
+            Some(MEM_A), Some(MEM_B), None, None,
+            None, None, None, None, None,
+            data[0],
+            vec![0, 0, 1],
+            vec![0, 1, 1],
+            data[1..].to_vec(),
+        );
+
+        // Transfer
+        let amount = 42;
+        let hash = create_transfer_message(&sim.vm, &src, &dst, &vdn, amount);
+        let signature = sign(&src_key, hash.as_ref());
+        let ix = exec(TransferOp::from_struct(ParsedTransferOp { amount, signature: signature.into() }).to_bytes());
+
+        let poh = sim.vm.get_current_poh();
+        let expected_poh = sim.vm.get_next_poh(
+            CodeInstruction::ExecIx,
+            ix.accounts.iter().map(|meta| &meta.pubkey),
+            &ix.data[1..],
+        );
+
+        assert!(sim.process_instruction(&ix).is_ok());
+        assert_eq!(sim.vm.poh, expected_poh);
+        assert_eq!(sim.vm.slot, 1);
+
+        let src = sim.get_virtual_account(&MEM_B, 0).unwrap().into_inner_timelock().unwrap();
+        let dst = sim.get_virtual_account(&MEM_B, 1).unwrap().into_inner_timelock().unwrap();
+        let vdn = sim.get_virtual_account(&MEM_A, 0).unwrap().into_inner_nonce().unwrap();
+        assert_eq!((src.balance, dst.balance), (58, 42));
+        assert_eq!(vdn.value, poh);
+
+        // Replaying the same transfer fails, as the nonce has moved on
+        let before = sim.clone();
+        assert_eq!(
+            sim.process_instruction(&ix),
+            Err(CodeVmError::InvalidSignature.into()),
+        );
+        assert_eq!(sim.vm.poh, before.vm.poh);
+        assert_eq!(sim.get_memory(&MEM_B), before.get_memory(&MEM_B));
+
+        // Withdraw everything that is left to the destination
+        let hash = create_withdraw_message(&sim.vm, &src, &dst, &vdn);
+        let signature = sign(&src_key, hash.as_ref());
+        assert!(sim.process_instruction(&exec(WithdrawOp { signature: signature.into() }.to_bytes())).is_ok());
+        assert_eq!(sim.get_virtual_account(&MEM_B, 0), None);
+
+        let dst = sim.get_virtual_account(&MEM_B, 1).unwrap();
+        assert_eq!(dst.get_balance(), 100);
+
+        // Compress the destination, then decompress it at another index
+        let va_hash = dst.get_hash();
+        let sig = sign(&authority, va_hash.as_ref());
+        let ix = system_account_compress(authority.pubkey(), vm_address, MEM_B, STORAGE, 1, sig);
+        assert!(sim.process_instruction(&ix).is_ok());
+        assert_eq!(sim.get_virtual_account(&MEM_B, 1), None);
+
+        let sig_hash = utils::get_storage_leaf_value(&sig, &va_hash, 0);
+        let mut tree = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::new(&[b"storage"]);
+        tree.try_insert(sig_hash).unwrap();
+        assert_eq!(sim.get_storage(&STORAGE).unwrap().compressed_state.get_root(), tree.get_root());
+
+        let vta = dst.into_inner_timelock().unwrap();
+        let timelock = vta.get_timelock_address(&vm.get_mint(), &vm.get_authority(), vm.get_lock_duration());
+        let unlock = vta.get_unlock_address(&timelock, &vm_address);
+        let receipt = vta.get_withdraw_receipt_address(&unlock, &vm_address);
+        let proof = tree.get_merkle_proof(&[sig_hash], 0);
+
+        // Without the unlock and receipt addresses a timelock can't be decompressed
+        let ix = system_account_decompress(
+            authority.pubkey(), vm_address, MEM_B, STORAGE,
+            None, None, 5, dst.pack(), 0, 0, proof.clone(), sig,
+        );
+        assert_eq!(
+            sim.process_instruction(&ix),
+            Err(CodeVmError::MissingAccount.into()),
+        );
+
+        let ix = system_account_decompress(
+            authority.pubkey(), vm_address, MEM_B, STORAGE,
+            Some(unlock), Some(receipt), 5, dst.pack(), 0, 0, proof.clone(), sig,
+        );
+        assert!(sim.process_instruction(&ix).is_ok());
+        assert_eq!(sim.get_virtual_account(&MEM_B, 5), Some(dst));
+        assert_eq!(sim.get_memory(&MEM_B).unwrap().get_packed(5), Some(dst.pack().as_slice()));
+
+        tree.try_remove(&proof, sig_hash).unwrap();
+        assert_eq!(sim.get_storage(&STORAGE).unwrap().compressed_state.get_root(), tree.get_root());
+    }
+
+    #[test]
+    fn test_simulate_precompile_escrow() {
+        let authority = Keypair::new();
+        let (src_key, dst_key) = (Keypair::new(), Keypair::new());
+
+        let mut vm = CodeVmAccount::zeroed();
+        vm.authority = authority.pubkey();
+        vm.mint = Pubkey::new_unique();
+        vm.lock_duration = 21;
+        vm.sig_verify_mode = SigVerifyMode::Precompile as u8;
+        let (vm_address, _) = find_vm_pda(&vm.mint, &vm.authority, vm.lock_duration);
+
+        let src = create_timelock(&vm, &vm_address, &src_key.pubkey(), 100);
+        let dst = create_timelock(&vm, &vm_address, &dst_key.pubkey(), 0);
+        let vdn = VirtualDurableNonce {
+            address: Pubkey::new_unique(),
+            value: Hash::new_from_array([7; 32]),
+        };
+
+        let mut sim = VmSimulator::new(vm_address, vm);
+        sim.unix_timestamp = 1_000;
+        sim.memories.insert(MEM_A, SimulatedMemory::new(10, VirtualEscrowAccount::LEN + 1));
+        sim.memories.insert(MEM_B, SimulatedMemory::new(10, VirtualTimelockAccount::LEN + 1));
+
+        let mem_a = sim.memory_mut(&MEM_A).unwrap();
+        mem_a.try_write(0, &VirtualAccount::Nonce(vdn)).unwrap();
+        let mem_b = sim.memory_mut(&MEM_B).unwrap();
+        mem_b.try_write(0, &VirtualAccount::Timelock(src)).unwrap();
+        mem_b.try_write(1, &VirtualAccount::Timelock(dst)).unwrap();
+
+        let exec = |data: Vec<u8>, mem_indicies: Vec<u16>, mem_banks: Vec<u8>| vm_exec(
+            authority.pubkey(),
+            vm_address,
+            Some(MEM_A), Some(MEM_B), None, None,
+            None, None, None, None, None,
+            data[0],
+            mem_indicies,
+            mem_banks,
+            data[1..].to_vec(),
+        );
+
+        let preimage = [5u8; 32];
+        let hashlock = utils::hash(&preimage);
+        let (amount, refund_after) = (60, 2_000);
+        let hash = create_escrow_fund_message(
+            &sim.vm, &src, &dst_key.pubkey(), &hashlock, refund_after, amount, &vdn,
+        );
+        let signature = sign(&src_key, hash.as_ref());
+        let fund = exec(
+            EscrowFundOp::from_struct(ParsedEscrowFundOp {
+                signature: signature.into(),
+                amount,
+                refund_after,
+                recipient: dst_key.pubkey(),
+                hashlock,
+            }).to_bytes(),
+            vec![0, 0, 1],
+            vec![0, 1, 0],
+        );
+        let verify = ed25519_verify(&src_key.pubkey(), &signature, hash.as_ref());
+
+        // The signature must come from an Ed25519 instruction, through the
+        // instructions sysvar
+        assert_eq!(
+            sim.process_transaction(&[verify.clone(), fund.clone()]),
+            Err(CodeVmError::MissingAccount.into()),
+        );
+        let fund = with_instructions_sysvar(fund);
+        assert_eq!(
+            sim.process_instruction(&fund),
+            Err(CodeVmError::InvalidSignature.into()),
+        );
+        assert!(sim.process_transaction(&[verify, fund]).is_ok());
+
+        let src = sim.get_virtual_account(&MEM_B, 0).unwrap().into_inner_timelock().unwrap();
+        let escrow = sim.get_virtual_account(&MEM_A, 1).unwrap().into_inner_escrow().unwrap();
+        assert_eq!((src.balance, escrow.balance), (40, 60));
+
+        // Claim it with the preimage, which needs no signature
+        let claim = exec(EscrowClaimOp { preimage }.to_bytes(), vec![1, 1], vec![0, 1]);
+        assert!(sim.process_instruction(&claim).is_ok());
+        assert_eq!(sim.get_virtual_account(&MEM_A, 1), None);
+        assert_eq!(sim.get_virtual_account(&MEM_B, 1).unwrap().get_balance(), 60);
+    }
+
+    #[test]
+    fn test_memory_model() {
+        let nonce = VirtualAccount::Nonce(VirtualDurableNonce {
+            address: Pubkey::new_unique(),
+            value: Hash::default(),
+        });
+
+        let mut memory = SimulatedMemory::new(2, VirtualDurableNonce::LEN + 1);
+        assert!(memory.try_read(0).is_err());
+        assert!(memory.try_write(0, &nonce).is_ok());
+        assert_eq!(memory.get(0), Some(nonce));
+        assert_eq!(memory.get_packed(0), Some(nonce.pack().as_slice()));
+        assert!(memory.check_is_empty(0).is_err());
+
+        // Out of range, or too large for the account size
+        assert!(memory.try_write(2, &nonce).is_err());
+        let mut small = SimulatedMemory::new(2, 8);
+        assert!(small.try_write(0, &nonce).is_err());
+
+        assert!(memory.try_delete(0).is_ok());
+        assert_eq!(memory.num_accounts(), 0);
+    }
+
+    #[test]
+    fn test_legacy_memory_layout() {
+        let nonce = VirtualAccount::Nonce(VirtualDurableNonce {
+            address: Pubkey::new_unique(),
+            value: Hash::default(),
+        });
+
+        let mut memory = SimulatedMemory::new(2, VirtualTimelockAccount::LEN + 1);
+        memory.legacy_layout = Some(LegacyMemoryLayout::Timelock);
+        assert_eq!(
+            memory.try_write(0, &nonce),
+            Err(CodeVmError::InvalidMemoryLayout.into()),
+        );
+
+        // The layout is checked before the index, as it is on-chain
+        assert_eq!(
+            memory.try_write(2, &nonce),
+            Err(CodeVmError::InvalidMemoryLayout.into()),
+        );
+
+        memory.legacy_layout = Some(LegacyMemoryLayout::Nonce);
+        assert!(memory.try_write(0, &nonce).is_ok());
+    }
+}
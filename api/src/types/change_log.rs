@@ -0,0 +1,206 @@
+use steel::*;
+use bytemuck::{Pod, Zeroable};
+use std::fmt::Debug;
+
+use super::hash::Hash;
+use crate::{error::CodeVmError, helpers::check_condition};
+
+/// A single change to a merkle tree: the leaf index that changed, the new
+/// nodes on its path (from the leaf up, without the root) and the new root.
+#[repr(C, align(8))]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ChangeLogEntry<const N: usize> {
+    pub root: Hash,
+    pub path: [Hash; N],
+    pub index: u64,
+}
+
+unsafe impl<const N: usize> Zeroable for ChangeLogEntry<N> {}
+unsafe impl<const N: usize> Pod for ChangeLogEntry<N> {}
+
+impl<const N: usize> ChangeLogEntry<N> {
+    /// Create an entry from a path as returned by `MerkleTree::compute_path`
+    /// (leaf first, root last).
+    pub fn new(index: u64, path: &[Hash]) -> Self {
+        let mut entry = Self::zeroed();
+        entry.root = path[N];
+        entry.path.copy_from_slice(&path[..N]);
+        entry.index = index;
+        entry
+    }
+
+    /// Update a proof for `leaf_index`, made before this change, so that it is
+    /// valid after it. Only the sibling on the level where the two paths meet
+    /// is affected.
+    pub fn try_update_proof(&self, leaf_index: u64, proof: &mut [Hash]) -> ProgramResult {
+        check_condition(
+            self.index != leaf_index,
+            CodeVmError::StaleMerkleProof,
+            "the leaf was changed after the proof was made",
+        )?;
+
+        let level = (63 - (leaf_index ^ self.index).leading_zeros()) as usize;
+        proof[level] = self.path[level];
+
+        Ok(())
+    }
+}
+
+/// The last B changes made to a merkle tree of depth N. This lets a proof made
+/// against any of the recent roots be fast-forwarded to the current root, so
+/// that concurrent updates don't invalidate proofs that are still in flight.
+#[repr(C, align(8))]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ChangeLog<const N: usize, const B: usize> {
+    entries: [ChangeLogEntry<N>; B],
+    count: u64,
+}
+
+unsafe impl<const N: usize, const B: usize> Zeroable for ChangeLog<N, B> {}
+unsafe impl<const N: usize, const B: usize> Pod for ChangeLog<N, B> {}
+
+impl<const N: usize, const B: usize> ChangeLog<N, B> {
+    pub const fn get_size() -> usize {
+        std::mem::size_of::<Self>()
+    }
+
+    pub const fn capacity(&self) -> usize {
+        B
+    }
+
+    /// The number of entries currently held, at most B.
+    pub fn len(&self) -> usize {
+        std::cmp::min(self.count, B as u64) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The i-th held entry, oldest first.
+    pub fn get(&self, i: usize) -> Option<&ChangeLogEntry<N>> {
+        if i >= self.len() {
+            return None;
+        }
+
+        let oldest = self.count - self.len() as u64;
+        Some(&self.entries[((oldest + i as u64) % B as u64) as usize])
+    }
+
+    pub fn last(&self) -> Option<&ChangeLogEntry<N>> {
+        self.len().checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    pub fn push(&mut self, entry: ChangeLogEntry<N>) {
+        self.entries[(self.count % B as u64) as usize] = entry;
+        self.count += 1;
+    }
+
+    /// Bring a proof for `leaf_index`, made against `root`, up to date with
+    /// every change since. The root must still be in the log.
+    pub fn try_fast_forward(&self, root: Hash, leaf_index: u64, proof: &mut [Hash]) -> ProgramResult {
+        check_condition(
+            proof.len() == N,
+            CodeVmError::InvalidMerkleProof,
+            "merkle proof length does not match tree depth",
+        )?;
+
+        // Search from the newest entry, a root that shows up more than once
+        // describes the same tree either way.
+        let start = (0..self.len())
+            .rev()
+            .find(|i| self.get(*i).unwrap().root == root);
+
+        check_condition(
+            start.is_some(),
+            CodeVmError::StaleMerkleProof,
+            "the proof root is not a recent root",
+        )?;
+
+        for i in start.unwrap() + 1..self.len() {
+            self.get(i).unwrap().try_update_proof(leaf_index, proof)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MerkleTree;
+    use crate::utils;
+
+    type TestTree = MerkleTree<3>;
+    type TestLog = ChangeLog<3, 4>;
+
+    #[test]
+    fn test_push_and_wrap() {
+        let mut log = TestLog::zeroed();
+        assert!(log.is_empty());
+        assert!(log.last().is_none());
+
+        for i in 0..6u64 {
+            let path = [utils::hash(&[i as u8]); 4];
+            log.push(ChangeLogEntry::new(i, &path));
+        }
+
+        assert_eq!(log.len(), 4);
+        assert_eq!(log.get(0).unwrap().index, 2);
+        assert_eq!(log.last().unwrap().index, 5);
+        assert!(log.get(4).is_none());
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        let seeds : &[&[u8]] = &[b"test"];
+
+        let mut tree = TestTree::new(seeds);
+        let mut log = TestLog::zeroed();
+
+        let vals: Vec<Hash> = (0..5u8).map(|i| utils::hash(&[i])).collect();
+        let mut leaves: Vec<Hash> = vals.iter().map(|val| TestTree::as_leaf(*val)).collect();
+
+        for (i, val) in vals[..3].iter().enumerate() {
+            let path = tree.try_insert_with_path(*val).unwrap();
+            log.push(ChangeLogEntry::new(i as u64, &path));
+        }
+
+        // Proofs made now, before any of the following changes
+        let root = tree.get_root();
+        let stale_0 = tree.get_merkle_proof(&leaves[..3], 0);
+        let stale_2 = tree.get_merkle_proof(&leaves[..3], 2);
+
+        // Insert two more leaves and remove one
+        for (i, val) in vals[3..].iter().enumerate() {
+            let path = tree.try_insert_with_path(*val).unwrap();
+            log.push(ChangeLogEntry::new(3 + i as u64, &path));
+        }
+
+        let proof = tree.get_merkle_proof(&leaves, 1);
+        let path = tree.try_replace_leaf_with_path(&proof, leaves[1], tree.get_empty_leaf()).unwrap();
+        log.push(ChangeLogEntry::new(1, &path));
+        leaves[1] = tree.get_empty_leaf();
+
+        // The stale proofs no longer match the tree
+        assert!(!tree.contains(&stale_0, vals[0]));
+        assert!(!tree.contains(&stale_2, vals[2]));
+
+        let mut proof = stale_0.clone();
+        assert!(log.try_fast_forward(root, 0, &mut proof).is_ok());
+        assert_eq!(proof, tree.get_merkle_proof(&leaves, 0));
+        assert!(tree.contains(&proof, vals[0]));
+
+        let mut proof = stale_2.clone();
+        assert!(log.try_fast_forward(root, 2, &mut proof).is_ok());
+        assert!(tree.contains(&proof, vals[2]));
+
+        // A changed leaf can't be fast-forwarded
+        let mut proof = stale_0.clone();
+        assert!(log.try_fast_forward(root, 1, &mut proof).is_err());
+
+        // Neither can a root that is no longer in the log
+        let mut proof = stale_0.clone();
+        assert!(log.try_fast_forward(utils::hash(b"old"), 0, &mut proof).is_err());
+    }
+}
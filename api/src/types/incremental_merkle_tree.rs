@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use steel::*;
+
+use super::{hash::Hash, merkle_tree::MerkleTree};
+use crate::{error::CodeVmError, helpers::check_condition};
+
+/// An off-chain copy of a `MerkleTree` that also keeps every node, so the
+/// proof of any leaf can be read in O(depth) instead of rebuilding the tree
+/// from all of its leaves.
+///
+/// Inserts, removals and replacements are applied to the inner `MerkleTree`
+/// with the same methods the program uses, so after the same operations it is
+/// equal to the on-chain tree, including its filled subtrees. Nodes that only
+/// cover empty leaves are not stored.
+#[derive(Clone, PartialEq, Debug)]
+pub struct IncrementalMerkleTree<const N: usize> {
+    tree: MerkleTree<N>,
+    nodes: Vec<HashMap<u64, Hash>>,
+}
+
+impl<const N: usize> IncrementalMerkleTree<N> {
+    pub fn new(seeds: &[&[u8]]) -> Self {
+        Self {
+            tree: MerkleTree::new(seeds),
+            nodes: vec![HashMap::new(); N],
+        }
+    }
+
+    /// Start from an on-chain tree that has no leaves yet. The nodes of a tree
+    /// with leaves can't be read from it, use `from_leaves` for that.
+    pub fn from_tree(tree: MerkleTree<N>) -> Result<Self, ProgramError> {
+        Self::from_leaves(tree, &[])
+    }
+
+    /// Start from an on-chain tree and all of its leaves, in order, with the
+    /// empty leaf in place of removed ones. The leaves are checked against the
+    /// root of the tree.
+    pub fn from_leaves(tree: MerkleTree<N>, leaves: &[Hash]) -> Result<Self, ProgramError> {
+        check_condition(
+            leaves.len() as u64 == tree.get_next_index(),
+            CodeVmError::InvalidMerkleProof,
+            "the number of leaves does not match the tree",
+        )?;
+
+        let mut nodes = vec![HashMap::new(); N];
+        let mut layer: Vec<(u64, Hash)> = leaves.iter()
+            .enumerate()
+            .map(|(i, leaf)| (i as u64, *leaf))
+            .collect();
+
+        for (level, level_nodes) in nodes.iter_mut().enumerate() {
+            let zero_value = tree.get_zero_value(level);
+            let mut parents: Vec<(u64, Hash)> = Vec::new();
+
+            for (index, node) in layer.iter() {
+                if *node != zero_value {
+                    level_nodes.insert(*index, *node);
+                }
+
+                if parents.last().map(|(parent, _)| *parent) != Some(index >> 1) {
+                    parents.push((index >> 1, zero_value));
+                }
+            }
+
+            for (parent, node) in parents.iter_mut() {
+                let get = |index: u64| level_nodes.get(&index).copied().unwrap_or(zero_value);
+                *node = MerkleTree::<N>::hash_left_right(get(*parent << 1), get((*parent << 1) | 1));
+            }
+
+            layer = parents;
+        }
+
+        // A new tree has a root of its own, with no leaves there is nothing
+        // to check.
+        check_condition(
+            leaves.is_empty() || layer[0].1 == tree.get_root(),
+            CodeVmError::InvalidMerkleProof,
+            "the leaves do not match the root of the tree",
+        )?;
+
+        Ok(Self { tree, nodes })
+    }
+
+    pub fn get_tree(&self) -> &MerkleTree<N> {
+        &self.tree
+    }
+
+    pub fn get_root(&self) -> Hash {
+        self.tree.get_root()
+    }
+
+    pub fn get_next_index(&self) -> u64 {
+        self.tree.get_next_index()
+    }
+
+    pub fn get_empty_leaf(&self) -> Hash {
+        self.tree.get_empty_leaf()
+    }
+
+    /// True if this tree is in the same state as `tree`, for example the
+    /// `compressed_state` of a `StorageAccount` or the `history` of a
+    /// `RelayAccount`.
+    pub fn matches(&self, tree: &MerkleTree<N>) -> bool {
+        self.tree == *tree
+    }
+
+    /// The leaf at `leaf_index`. Leaves that were never inserted, or that were
+    /// removed, are the empty leaf.
+    pub fn get_leaf(&self, leaf_index: u64) -> Hash {
+        self.get_node(0, leaf_index)
+    }
+
+    pub fn contains(&self, leaf_index: u64, val: Hash) -> bool {
+        leaf_index < self.get_next_index()
+            && self.get_leaf(leaf_index) == MerkleTree::<N>::as_leaf(val)
+    }
+
+    /// The proof of the leaf at `leaf_index` against the current root.
+    pub fn get_merkle_proof(&self, leaf_index: u64) -> Vec<Hash> {
+        (0..N)
+            .map(|level| self.get_node(level, (leaf_index >> level) ^ 1))
+            .collect()
+    }
+
+    /// Insert a value, as `MerkleTree::try_insert`. Returns its leaf index.
+    pub fn try_insert(&mut self, val: Hash) -> Result<u64, ProgramError> {
+        let path = self.tree.try_insert_with_path(val)?;
+        let leaf_index = self.tree.get_next_index() - 1;
+
+        self.set_path(leaf_index, &path);
+
+        Ok(leaf_index)
+    }
+
+    /// Replace a value with the empty leaf, as `MerkleTree::try_remove`.
+    pub fn try_remove(&mut self, leaf_index: u64, val: Hash) -> ProgramResult {
+        let empty_leaf = self.get_empty_leaf();
+        self.try_replace_leaf(leaf_index, MerkleTree::<N>::as_leaf(val), empty_leaf)
+    }
+
+    pub fn try_replace(&mut self, leaf_index: u64, original_val: Hash, new_val: Hash) -> ProgramResult {
+        self.try_replace_leaf(
+            leaf_index,
+            MerkleTree::<N>::as_leaf(original_val),
+            MerkleTree::<N>::as_leaf(new_val),
+        )
+    }
+
+    pub fn try_replace_leaf(&mut self, leaf_index: u64, original_leaf: Hash, new_leaf: Hash) -> ProgramResult {
+        check_condition(
+            leaf_index < self.get_next_index(),
+            CodeVmError::InvalidMerkleProof,
+            "leaf index is out of range",
+        )?;
+
+        let proof = self.get_merkle_proof(leaf_index);
+        let path = self.tree.try_replace_leaf_with_path(&proof, original_leaf, new_leaf)?;
+
+        self.set_path(leaf_index, &path);
+
+        Ok(())
+    }
+
+    fn get_node(&self, level: usize, index: u64) -> Hash {
+        self.nodes[level]
+            .get(&index)
+            .copied()
+            .unwrap_or_else(|| self.tree.get_zero_value(level))
+    }
+
+    /// Store the nodes of a path, from the leaf up to, but not including, the
+    /// root.
+    fn set_path(&mut self, leaf_index: u64, path: &[Hash]) {
+        for (level, node) in path[..N].iter().enumerate() {
+            let index = leaf_index >> level;
+
+            if *node == self.tree.get_zero_value(level) {
+                self.nodes[level].remove(&index);
+            } else {
+                self.nodes[level].insert(index, *node);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consts::COMPRESSED_STATE_DEPTH, utils};
+
+    type TestTree = IncrementalMerkleTree<3>;
+
+    #[test]
+    fn test_matches_merkle_tree() {
+        let seeds : &[&[u8]] = &[b"test"];
+
+        let mut tree = TestTree::new(seeds);
+        let mut expected = MerkleTree::<3>::new(seeds);
+        let mut leaves = Vec::new();
+
+        let vals: Vec<Hash> = (0..8u8).map(|i| utils::hash(&[i])).collect();
+
+        for (i, val) in vals.iter().enumerate() {
+            assert_eq!(tree.try_insert(*val), Ok(i as u64));
+            assert!(expected.try_insert(*val).is_ok());
+            leaves.push(MerkleTree::<3>::as_leaf(*val));
+
+            assert!(tree.matches(&expected));
+            for j in 0..leaves.len() {
+                assert_eq!(tree.get_merkle_proof(j as u64), expected.get_merkle_proof(&leaves, j));
+            }
+        }
+
+        // The tree is full
+        assert!(tree.try_insert(vals[0]).is_err());
+
+        // Remove and replace, as the program would
+        let proof = expected.get_merkle_proof(&leaves, 2);
+        assert!(expected.try_remove(&proof, vals[2]).is_ok());
+        assert!(tree.try_remove(2, vals[2]).is_ok());
+        leaves[2] = tree.get_empty_leaf();
+
+        let new_val = utils::hash(b"new");
+        let proof = expected.get_merkle_proof(&leaves, 5);
+        assert!(expected.try_replace(&proof, vals[5], new_val).is_ok());
+        assert!(tree.try_replace(5, vals[5], new_val).is_ok());
+        leaves[5] = MerkleTree::<3>::as_leaf(new_val);
+
+        assert!(tree.matches(&expected));
+        assert!(!tree.contains(2, vals[2]));
+        assert!(tree.contains(5, new_val));
+        for j in 0..leaves.len() {
+            assert_eq!(tree.get_merkle_proof(j as u64), expected.get_merkle_proof(&leaves, j));
+        }
+
+        // A value that is not at the index is not removed
+        let before = tree.clone();
+        assert!(tree.try_remove(2, vals[2]).is_err());
+        assert!(tree.try_remove(3, vals[4]).is_err());
+        assert_eq!(tree, before);
+
+        // Removing every leaf empties the tree, and only empty nodes are left
+        for (i, leaf) in leaves.iter().enumerate() {
+            if *leaf != tree.get_empty_leaf() {
+                assert!(tree.try_replace_leaf(i as u64, *leaf, tree.get_empty_leaf()).is_ok());
+            }
+        }
+        assert!(tree.get_tree().is_empty());
+        assert!(tree.nodes.iter().all(|level| level.is_empty()));
+    }
+
+    #[test]
+    fn test_from_leaves() {
+        let seeds : &[&[u8]] = &[b"test"];
+
+        let mut tree = TestTree::new(seeds);
+        let mut leaves = Vec::new();
+
+        assert_eq!(TestTree::from_tree(MerkleTree::<3>::new(seeds)), Ok(tree.clone()));
+
+        let vals: Vec<Hash> = (0..5u8).map(|i| utils::hash(&[i])).collect();
+        for val in vals.iter() {
+            assert!(tree.try_insert(*val).is_ok());
+            leaves.push(MerkleTree::<3>::as_leaf(*val));
+        }
+        assert!(tree.try_remove(1, vals[1]).is_ok());
+        assert!(tree.try_remove(4, vals[4]).is_ok());
+        leaves[1] = tree.get_empty_leaf();
+        leaves[4] = tree.get_empty_leaf();
+
+        // The nodes of a tree with leaves are unknown
+        assert!(TestTree::from_tree(*tree.get_tree()).is_err());
+
+        let rebuilt = TestTree::from_leaves(*tree.get_tree(), &leaves).unwrap();
+        assert_eq!(rebuilt, tree);
+        for i in 0..leaves.len() as u64 {
+            assert_eq!(rebuilt.get_merkle_proof(i), tree.get_merkle_proof(i));
+        }
+
+        // The leaves must be all of them, and produce the root
+        assert!(TestTree::from_leaves(*tree.get_tree(), &leaves[..4]).is_err());
+        leaves[2] = tree.get_empty_leaf();
+        assert!(TestTree::from_leaves(*tree.get_tree(), &leaves).is_err());
+    }
+
+    #[test]
+    fn test_storage_depth_proofs() {
+        let seeds : &[&[u8]] = &[b"storage"];
+
+        let mut tree = IncrementalMerkleTree::<{COMPRESSED_STATE_DEPTH}>::new(seeds);
+        let mut expected = MerkleTree::<{COMPRESSED_STATE_DEPTH}>::new(seeds);
+
+        let vals: Vec<Hash> = (0..100u8).map(|i| utils::hash(&[i])).collect();
+        for val in vals.iter() {
+            assert!(tree.try_insert(*val).is_ok());
+            assert!(expected.try_insert(*val).is_ok());
+        }
+
+        for i in [0, 37, 99] {
+            let proof = tree.get_merkle_proof(i);
+            assert!(expected.contains(&proof, vals[i as usize]));
+            assert!(expected.try_remove(&proof, vals[i as usize]).is_ok());
+            assert!(tree.try_remove(i, vals[i as usize]).is_ok());
+        }
+
+        assert!(tree.matches(&expected));
+        assert!(expected.contains_leaf(&tree.get_merkle_proof(37), tree.get_empty_leaf()));
+        assert!(expected.contains(&tree.get_merkle_proof(38), vals[38]));
+    }
+}
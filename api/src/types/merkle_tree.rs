@@ -3,7 +3,7 @@ use bytemuck::{Pod, Zeroable};
 use std::fmt::Debug;
 
 use super::hash::Hash;
-use crate::helpers::check_condition;
+use crate::{error::CodeVmError, helpers::check_condition};
 use crate::utils;
 
 #[repr(C, align(8))]
@@ -31,10 +31,40 @@ impl<const N: usize> MerkleTree<N> {
         self.root
     }
 
+    pub fn get_next_index(&self) -> u64 {
+        self.next_index
+    }
+
     pub fn get_empty_leaf(&self) -> Hash {
         self.zero_values[0]
     }
 
+    /// The node at `level` of a subtree that holds only empty leaves, level 0
+    /// being the empty leaf itself.
+    pub fn get_zero_value(&self, level: usize) -> Hash {
+        self.zero_values[level]
+    }
+
+    /// True if the tree holds no live leaves, either because nothing was ever
+    /// inserted or because every inserted leaf has since been removed.
+    pub fn is_empty(&self) -> bool {
+        if self.next_index == 0 {
+            return true;
+        }
+
+        self.root == self.get_empty_root()
+    }
+
+    /// The root of a tree in which every leaf is the empty leaf. Removed
+    /// leaves are replaced by the empty leaf, so a tree with only removed
+    /// leaves has this root.
+    pub fn get_empty_root(&self) -> Hash {
+        Self::hash_left_right(
+            self.zero_values[N - 1],
+            self.zero_values[N - 1],
+        )
+    }
+
     pub fn new(seeds: &[&[u8]]) -> Self {
         let zeros = Self::calc_zeros(seeds);
         Self {
@@ -66,17 +96,28 @@ impl<const N: usize> MerkleTree<N> {
     }
 
     pub fn try_insert(&mut self, val: Hash) -> ProgramResult {
+        self.try_insert_with_path(val)?;
+        Ok(())
+    }
+
+    /// Same as `try_insert`, but returns the path of the new leaf, from the
+    /// leaf up to the new root. The leaf index is `get_next_index() - 1`.
+    pub fn try_insert_with_path(&mut self, val: Hash) -> Result<Vec<Hash>, ProgramError> {
         check_condition(
             self.next_index < (1u64 << N),
+            CodeVmError::MerkleTreeFull,
             "merkle tree is full",
         )?;
 
         let mut current_index = self.next_index;
         let mut current_hash = MerkleTree::<N>::as_leaf(val);
+        let mut path = Vec::with_capacity(N + 1);
         let mut left;
         let mut right;
 
         for i in 0..N {
+            path.push(current_hash);
+
             if current_index % 2 == 0 {
                 left = current_hash;
                 right = self.zero_values[i];
@@ -90,10 +131,12 @@ impl<const N: usize> MerkleTree<N> {
             current_index /= 2;
         }
 
+        path.push(current_hash);
+
         self.root = current_hash;
         self.next_index += 1;
 
-        Ok(())
+        Ok(path)
     }
 
     pub fn try_remove(&mut self, proof: &[Hash], val: Hash) -> ProgramResult {
@@ -112,6 +155,18 @@ impl<const N: usize> MerkleTree<N> {
     }
 
     pub fn try_replace_leaf(&mut self, proof: &[Hash], original_leaf: Hash, new_leaf: Hash) -> ProgramResult {
+        self.try_replace_leaf_with_path(proof, original_leaf, new_leaf)?;
+        Ok(())
+    }
+
+    /// Same as `try_replace_leaf`, but returns the new path of the leaf, from
+    /// the leaf up to the new root.
+    pub fn try_replace_leaf_with_path(
+        &mut self,
+        proof: &[Hash],
+        original_leaf: Hash,
+        new_leaf: Hash,
+    ) -> Result<Vec<Hash>, ProgramError> {
         self.check_length(proof)?;
 
         let original_path = MerkleTree::<N>::compute_path(proof, original_leaf);
@@ -119,6 +174,7 @@ impl<const N: usize> MerkleTree<N> {
 
         check_condition(
             MerkleTree::<N>::is_valid_path(&original_path, self.root),
+            CodeVmError::InvalidMerkleProof,
             "invalid proof for original leaf",
         )?;
 
@@ -130,9 +186,132 @@ impl<const N: usize> MerkleTree<N> {
 
         self.root = *new_path.last().unwrap();
 
+        Ok(new_path)
+    }
+
+    /// Remove several values at once using a single multi-proof (see
+    /// `compute_multi_layers`). The leaf indices must be strictly increasing.
+    pub fn try_remove_multi(&mut self, proof: &[Hash], indices: &[u64], vals: &[Hash]) -> ProgramResult {
+        check_condition(
+            !indices.is_empty() && indices.len() == vals.len(),
+            CodeVmError::InvalidInstructionData,
+            "a value is required for every leaf index",
+        )?;
+
+        let original_leaves: Vec<Hash> = vals.iter().map(|val| Self::as_leaf(*val)).collect();
+        let new_leaves = vec![self.get_empty_leaf(); vals.len()];
+
+        let original_layers = Self::compute_multi_layers(proof, indices, &original_leaves)?;
+        let new_layers = Self::compute_multi_layers(proof, indices, &new_leaves)?;
+
+        check_condition(
+            original_layers[N][0].1 == self.root,
+            CodeVmError::InvalidMerkleProof,
+            "invalid multi-proof for original leaves",
+        )?;
+
+        for i in 0..N {
+            let nodes = original_layers[i].iter().zip(new_layers[i].iter());
+            for ((_, original), (_, new)) in nodes {
+                if *original == self.filled_subtrees[i] {
+                    self.filled_subtrees[i] = *new;
+                    break;
+                }
+            }
+        }
+
+        self.root = new_layers[N][0].1;
+
         Ok(())
     }
 
+    /// Rebuild every node touched by a set of leaves, from the leaves up to the
+    /// root. Returns N + 1 layers of (index, node) pairs sorted by index, the
+    /// last one holding only the root. Each layer also holds the siblings of
+    /// its nodes, so that the proof of any one leaf can be read from it.
+    ///
+    /// The multi-proof holds each missing sibling once, in the order they are
+    /// needed: layer by layer from the bottom, and left to right in a layer.
+    pub fn compute_multi_layers(
+        proof: &[Hash],
+        indices: &[u64],
+        leaves: &[Hash],
+    ) -> Result<Vec<Vec<(u64, Hash)>>, ProgramError> {
+        check_condition(
+            !indices.is_empty() && indices.len() == leaves.len(),
+            CodeVmError::InvalidInstructionData,
+            "a leaf is required for every leaf index",
+        )?;
+        check_condition(
+            indices.windows(2).all(|w| w[0] < w[1]),
+            CodeVmError::InvalidInstructionData,
+            "leaf indices must be strictly increasing",
+        )?;
+        check_condition(
+            indices[indices.len() - 1] < (1u64 << N),
+            CodeVmError::InvalidMerkleProof,
+            "leaf index is out of range",
+        )?;
+
+        let mut layers = Vec::with_capacity(N + 1);
+        let mut current: Vec<(u64, Hash)> = indices.iter().copied().zip(leaves.iter().copied()).collect();
+        let mut proof_iter = proof.iter();
+
+        for _ in 0..N {
+            let mut layer = Vec::with_capacity(2 * current.len());
+            let mut next = Vec::with_capacity(current.len());
+            let mut i = 0;
+
+            while i < current.len() {
+                let (index, node) = current[i];
+
+                let sibling = if index % 2 == 0 && i + 1 < current.len() && current[i + 1].0 == index + 1 {
+                    i += 1;
+                    current[i].1
+                } else {
+                    *proof_iter.next().ok_or(ProgramError::InvalidArgument)?
+                };
+
+                if index % 2 == 0 {
+                    layer.push((index, node));
+                    layer.push((index + 1, sibling));
+                } else {
+                    layer.push((index - 1, sibling));
+                    layer.push((index, node));
+                }
+
+                next.push((index / 2, Self::hash_left_right(node, sibling)));
+                i += 1;
+            }
+
+            layers.push(layer);
+            current = next;
+        }
+
+        check_condition(
+            proof_iter.next().is_none(),
+            CodeVmError::InvalidMerkleProof,
+            "multi-proof has unused nodes",
+        )?;
+
+        layers.push(current);
+
+        Ok(layers)
+    }
+
+    /// Read the regular proof of one leaf out of the layers returned by
+    /// `compute_multi_layers`. The leaf must be one of the leaves used to
+    /// build the layers.
+    pub fn get_proof_from_layers(layers: &[Vec<(u64, Hash)>], leaf_index: u64) -> Vec<Hash> {
+        let mut proof = Vec::with_capacity(N);
+        for (i, layer) in layers[..N].iter().enumerate() {
+            let sibling = (leaf_index >> i) ^ 1;
+            let at = layer.binary_search_by_key(&sibling, |(index, _)| *index).unwrap();
+            proof.push(layer[at].1);
+        }
+        proof
+    }
+
     pub fn contains(&self, proof: &[Hash], val: Hash) -> bool {
         if let Err(_) = self.check_length(proof) {
             return false;
@@ -211,7 +390,7 @@ impl<const N: usize> MerkleTree<N> {
     }
 
     #[cfg(not(feature = "solana"))]
-    pub fn get_merkle_proof(&self, values: &[Hash], index: usize) -> Vec<Hash> {
+    fn get_layers(&self, values: &[Hash]) -> Vec<Vec<Hash>> {
         let mut layers = Vec::with_capacity(N);
         let mut current_layer = values.to_vec();
         for i in 0..N {
@@ -223,6 +402,13 @@ impl<const N: usize> MerkleTree<N> {
             current_layer = Self::hash_pairs(current_layer);
         }
 
+        layers
+    }
+
+    #[cfg(not(feature = "solana"))]
+    pub fn get_merkle_proof(&self, values: &[Hash], index: usize) -> Vec<Hash> {
+        let layers = self.get_layers(values);
+
         // At this point we have all the layers of the merkle tree in an array
         // of arrays. The next step is to find the siblings of the provided
         // for_leaf all the way up the tree.
@@ -248,9 +434,43 @@ impl<const N: usize> MerkleTree<N> {
         proof
     }
 
+    /// Same as `get_merkle_proof`, but for several leaves at once. Siblings
+    /// shared between the paths, or that are themselves on a path, are left
+    /// out. The indices must be strictly increasing.
+    #[cfg(not(feature = "solana"))]
+    pub fn get_merkle_multi_proof(&self, values: &[Hash], indices: &[usize]) -> Vec<Hash> {
+        let layers = self.get_layers(values);
+
+        let mut proof = Vec::new();
+        let mut current: Vec<usize> = indices.to_vec();
+
+        for layer in layers.iter() {
+            let mut next = Vec::with_capacity(current.len());
+            let mut i = 0;
+
+            while i < current.len() {
+                let index = current[i];
+
+                if index % 2 == 0 && i + 1 < current.len() && current[i + 1] == index + 1 {
+                    i += 1;
+                } else {
+                    proof.push(layer[index ^ 1]);
+                }
+
+                next.push(index / 2);
+                i += 1;
+            }
+
+            current = next;
+        }
+
+        proof
+    }
+
     fn check_length(&self, proof: &[Hash]) -> Result<(), ProgramError> {
         check_condition(
             proof.len() == N,
+            CodeVmError::InvalidMerkleProof,
             "merkle proof length does not match tree depth",
         )
     }
@@ -375,6 +595,87 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_is_empty() {
+        let seeds : &[&[u8]] = &[b"test"];
+
+        let mut tree = TestTree::new(seeds);
+        assert!(tree.is_empty());
+
+        let val1 = utils::hash(b"val_1");
+        let val2 = utils::hash(b"val_2");
+        let leaves = [TestTree::as_leaf(val1), TestTree::as_leaf(val2)];
+
+        assert!(tree.try_insert(val1).is_ok());
+        assert!(tree.try_insert(val2).is_ok());
+        assert!(!tree.is_empty());
+
+        let val1_proof = tree.get_merkle_proof(&leaves, 0);
+        assert!(tree.try_remove(&val1_proof, val1).is_ok());
+        assert!(!tree.is_empty());
+
+        let val2_proof = tree.get_merkle_proof(&[tree.get_empty_leaf(), leaves[1]], 1);
+        assert!(tree.try_remove(&val2_proof, val2).is_ok());
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_multi_proof() {
+        let seeds : &[&[u8]] = &[b"test"];
+
+        let mut tree = TestTree::new(seeds);
+        let mut single = TestTree::new(seeds);
+
+        let vals: Vec<Hash> = (0..6u8).map(|i| utils::hash(&[i])).collect();
+        let mut leaves: Vec<Hash> = vals.iter().map(|val| TestTree::as_leaf(*val)).collect();
+
+        for val in vals.iter() {
+            assert!(tree.try_insert(*val).is_ok());
+            assert!(single.try_insert(*val).is_ok());
+        }
+
+        // Siblings on a path are not repeated in the proof
+        let indices = [1, 2, 3, 5];
+        let proof = tree.get_merkle_multi_proof(&leaves, &indices);
+        assert_eq!(proof.len(), 3);
+
+        let removed: Vec<Hash> = indices.iter().map(|i| vals[*i]).collect();
+        let indices_u64: Vec<u64> = indices.iter().map(|i| *i as u64).collect();
+
+        // Bad proofs
+        assert!(tree.clone().try_remove_multi(&proof[1..], &indices_u64, &removed).is_err());
+        assert!(tree.clone().try_remove_multi(&[&proof[..], &proof[..1]].concat(), &indices_u64, &removed).is_err());
+        assert!(tree.clone().try_remove_multi(&proof, &[1, 3, 2, 5], &removed).is_err());
+        assert!(tree.clone().try_remove_multi(&proof, &indices_u64, &vals[..4]).is_err());
+
+        // The regular proofs can be read back from the layers
+        let removed_leaves: Vec<Hash> = removed.iter().map(|val| TestTree::as_leaf(*val)).collect();
+        let layers = TestTree::compute_multi_layers(&proof, &indices_u64, &removed_leaves).unwrap();
+        for i in indices.iter() {
+            assert_eq!(TestTree::get_proof_from_layers(&layers, *i as u64), tree.get_merkle_proof(&leaves, *i));
+        }
+
+        assert!(tree.try_remove_multi(&proof, &indices_u64, &removed).is_ok());
+
+        // Same result as removing the leaves one at a time
+        for i in indices.iter() {
+            let proof = single.get_merkle_proof(&leaves, *i);
+            assert!(single.try_remove(&proof, vals[*i]).is_ok());
+            leaves[*i] = single.get_empty_leaf();
+        }
+
+        assert_eq!(tree, single);
+
+        // A one leaf multi-proof is a regular proof
+        assert_eq!(tree.get_merkle_multi_proof(&leaves, &[4]), tree.get_merkle_proof(&leaves, 4));
+
+        // The tree keeps working afterwards
+        let val = utils::hash(b"val_7");
+        assert!(tree.try_insert(val).is_ok());
+        assert!(single.try_insert(val).is_ok());
+        assert_eq!(tree, single);
+    }
+
     #[test]
     fn test_proof() {
         let seeds : &[&[u8]] = &[b"test"];
@@ -1,11 +1,19 @@
+pub mod change_log;
 pub mod circular_buffer;
 pub mod merkle_tree;
 pub mod signature;
 pub mod slice_allocator;
 pub mod hash;
 
+#[cfg(not(feature = "solana"))]
+pub mod incremental_merkle_tree;
+
+pub use change_log::*;
 pub use circular_buffer::*;
 pub use merkle_tree::*;
 pub use signature::*;
 pub use slice_allocator::*;
-pub use hash::*;
\ No newline at end of file
+pub use hash::*;
+
+#[cfg(not(feature = "solana"))]
+pub use incremental_merkle_tree::*;
\ No newline at end of file
@@ -87,6 +87,13 @@ impl<'a> SliceAllocator<'a> {
         self.read(item_index as usize, self.item_size)
             .map(|data| data.to_vec())
     }
+
+    pub fn num_items(&self) -> usize {
+        self.state
+            .iter()
+            .filter(|state| **state == ItemState::Used as u8)
+            .count()
+    }
 }
 
 pub struct SliceAllocatorMut<'a> {
@@ -197,6 +204,57 @@ impl<'a> SliceAllocatorMut<'a> {
 
         self.write(item_index as usize, data)
     }
+
+    pub fn try_move_item(&mut self, from_index: u16, to_index: u16) -> ProgramResult {
+        if from_index as usize >= self.capacity()
+            || to_index as usize >= self.capacity()
+            || self.is_empty(from_index)
+            || self.has_item(to_index)
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let from_start = from_index as usize * self.item_size;
+        let to_start = to_index as usize * self.item_size;
+        self.data.copy_within(from_start..from_start + self.item_size, to_start);
+
+        self.state[to_index as usize] = ItemState::Used as u8;
+        self.state[from_index as usize] = ItemState::Free as u8;
+        Ok(())
+    }
+
+    /// Shrink the allocator stored in `slice` from `capacity` to
+    /// `new_capacity` items, in place. All items at or above `new_capacity`
+    /// must be free. Returns the new size of the allocator.
+    pub fn try_shrink(
+        slice: &mut [u8],
+        capacity: usize,
+        new_capacity: usize,
+        max_item_size: usize,
+    ) -> Result<usize, ProgramError> {
+        if new_capacity > capacity
+            || slice.len() < SliceAllocator::get_size(capacity, max_item_size)
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let has_items = slice[new_capacity..capacity]
+            .iter()
+            .any(|state| *state != ItemState::Free as u8);
+
+        if has_items {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // The state section shrinks, so the data section moves down to
+        // start right after it.
+        let old_offset = SliceAllocator::get_state_size(capacity);
+        let new_offset = SliceAllocator::get_state_size(new_capacity);
+        let data_size = SliceAllocator::get_data_size(new_capacity, max_item_size);
+        slice.copy_within(old_offset..old_offset + data_size, new_offset);
+
+        Ok(SliceAllocator::get_size(new_capacity, max_item_size))
+    }
 }
 
 
@@ -291,6 +349,60 @@ mod tests {
         assert_eq!(read_data.unwrap(), data);
     }
 
+    #[test]
+    fn test_move_item() {
+        let capacity = 4;
+        let item_size = 8;
+        let total_size = SliceAllocator::get_size(capacity, item_size);
+        let mut buffer = vec![0u8; total_size];
+        let data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+
+        let mut allocator = create_allocator_mut(&mut buffer, capacity, item_size).unwrap();
+
+        assert!(allocator.try_alloc_item(3, data.len()).is_ok());
+        assert!(allocator.try_write_item(3, &data).is_ok());
+        assert!(allocator.try_alloc_item(1, data.len()).is_ok());
+
+        // Invalid move: destination is allocated
+        assert!(allocator.try_move_item(3, 1).is_err());
+
+        // Invalid move: source is not allocated
+        assert!(allocator.try_move_item(2, 0).is_err());
+
+        assert!(allocator.try_move_item(3, 0).is_ok());
+        assert!(allocator.is_empty(3));
+        assert_eq!(allocator.read_item(0).unwrap(), data);
+    }
+
+    #[test]
+    fn test_shrink() {
+        let capacity = 4;
+        let item_size = 8;
+        let total_size = SliceAllocator::get_size(capacity, item_size);
+        let mut buffer = vec![0u8; total_size];
+        let data_a = vec![1u8; item_size];
+        let data_b = vec![2u8; item_size];
+
+        let mut allocator = create_allocator_mut(&mut buffer, capacity, item_size).unwrap();
+        assert!(allocator.try_alloc_item(0, item_size).is_ok());
+        assert!(allocator.try_write_item(0, &data_a).is_ok());
+        assert!(allocator.try_alloc_item(2, item_size).is_ok());
+        assert!(allocator.try_write_item(2, &data_b).is_ok());
+
+        // Invalid shrink: item 2 is still allocated
+        assert!(SliceAllocatorMut::try_shrink(&mut buffer, capacity, 2, item_size).is_err());
+
+        let mut allocator = create_allocator_mut(&mut buffer, capacity, item_size).unwrap();
+        assert!(allocator.try_move_item(2, 1).is_ok());
+
+        let new_size = SliceAllocatorMut::try_shrink(&mut buffer, capacity, 2, item_size).unwrap();
+        assert_eq!(new_size, SliceAllocator::get_size(2, item_size));
+
+        let allocator = create_allocator(&buffer[..new_size], 2, item_size).unwrap();
+        assert_eq!(allocator.read_item(0).unwrap(), data_a);
+        assert_eq!(allocator.read_item(1).unwrap(), data_b);
+    }
+
     #[test]
     fn test_invalid_operations() {
         let capacity = 4;
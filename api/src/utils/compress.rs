@@ -0,0 +1,60 @@
+use crate::types::{Hash, Signature};
+use super::hashv;
+
+/// The value a compressed account is stored as in a storage tree. The tree
+/// hashes sorted pairs, so a proof doesn't commit to the position of a leaf.
+/// The value does, which lets the program check the leaf index it is given.
+pub fn get_storage_leaf_value(signature: &Signature, va_hash: &Hash, leaf_index: u64) -> Hash {
+    hashv(&[signature.as_ref(), va_hash.as_ref(), &leaf_index.to_le_bytes()])
+}
+
+/// The value of an account compressed before the leaf index was part of it.
+/// Storage trees without a changelog may still hold these.
+pub fn get_legacy_storage_leaf_value(signature: &Signature, va_hash: &Hash) -> Hash {
+    hashv(&[signature.as_ref(), va_hash.as_ref()])
+}
+
+/// The message signed by the VM authority to compress several virtual
+/// accounts at once. It commits to the account hashes in order, and is
+/// domain separated so that it can never be mistaken for the hash of a single
+/// account.
+pub fn get_compress_batch_message(va_hashes: &[Hash]) -> Hash {
+    let mut message: Vec<&[u8]> = Vec::with_capacity(1 + va_hashes.len());
+
+    message.push(b"compress_batch");
+    for va_hash in va_hashes.iter() {
+        message.push(va_hash.as_ref());
+    }
+
+    hashv(&message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_commits_to_hashes_in_order() {
+        let a = hashv(&[b"a"]);
+        let b = hashv(&[b"b"]);
+
+        let message = get_compress_batch_message(&[a, b]);
+
+        assert_eq!(message, get_compress_batch_message(&[a, b]));
+        assert_ne!(message, get_compress_batch_message(&[b, a]));
+        assert_ne!(message, get_compress_batch_message(&[a]));
+        assert_ne!(get_compress_batch_message(&[a]), a);
+    }
+
+    #[test]
+    fn test_leaf_value_commits_to_index() {
+        let signature = Signature::from([1; 64]);
+        let va_hash = hashv(&[b"a"]);
+
+        let value = get_storage_leaf_value(&signature, &va_hash, 0);
+
+        assert_eq!(value, get_storage_leaf_value(&signature, &va_hash, 0));
+        assert_ne!(value, get_storage_leaf_value(&signature, &va_hash, 1));
+        assert_ne!(value, get_legacy_storage_leaf_value(&signature, &va_hash));
+    }
+}
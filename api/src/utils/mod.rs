@@ -1,5 +1,11 @@
+mod compress;
 mod hash;
+mod multisig;
+mod precompile;
 mod signature;
 
+pub use compress::*;
 pub use hash::*;
+pub use multisig::*;
+pub use precompile::*;
 pub use signature::*;
\ No newline at end of file
@@ -0,0 +1,189 @@
+use steel::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::consts::MAX_MULTISIG_SIGNERS;
+use crate::{error::CodeVmError, helpers::check_condition};
+use crate::types::Signature;
+use super::{hashv, sig_verify};
+
+/// An m-of-n set of signer keys. A virtual timelock account is held by the
+/// multisig when its `owner` is the address returned by `get_address`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct MultisigOwner {
+    pub threshold: u8,
+    pub signers: Vec<Pubkey>,
+}
+
+/// A signature from the signer at `signer_index` in `MultisigOwner::signers`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, PartialEq, Debug)]
+pub struct MultisigSignature {
+    pub signer_index: u8,
+    pub signature: Signature,
+}
+
+impl MultisigOwner {
+    /// The owner address that commits to this threshold and signer set.
+    /// Signer order matters, the same set in a different order is a different
+    /// owner.
+    pub fn get_address(&self) -> Pubkey {
+        let mut message: Vec<&[u8]> = Vec::with_capacity(2 + self.signers.len());
+        let threshold = [self.threshold];
+
+        message.push(b"multisig");
+        message.push(&threshold);
+        for signer in self.signers.iter() {
+            message.push(signer.as_ref());
+        }
+
+        hashv(&message).into()
+    }
+}
+
+/// Verify that at least `threshold` distinct signers of `multisig` signed the
+/// message, and that `multisig` is the committed owner. A signer set that
+/// lists the same key twice is rejected, as that key could meet the threshold
+/// on its own.
+pub fn multisig_verify(
+    owner: &Pubkey,
+    multisig: &MultisigOwner,
+    signatures: &[MultisigSignature],
+    message: &[u8],
+) -> ProgramResult {
+    multisig_verify_with(owner, multisig, signatures, message, sig_verify)
+}
+
+/// Same as `multisig_verify`, but each individual signature is checked with
+/// `verify(pubkey, signature, message)`.
+pub fn multisig_verify_with<F>(
+    owner: &Pubkey,
+    multisig: &MultisigOwner,
+    signatures: &[MultisigSignature],
+    message: &[u8],
+    verify: F,
+) -> ProgramResult
+where
+    F: Fn(&[u8], &[u8], &[u8]) -> ProgramResult,
+{
+    check_condition(
+        multisig.signers.len() <= MAX_MULTISIG_SIGNERS,
+        CodeVmError::InvalidMultisig,
+        "too many multisig signers",
+    )?;
+
+    check_condition(
+        multisig.threshold > 0 && (multisig.threshold as usize) <= multisig.signers.len(),
+        CodeVmError::InvalidMultisig,
+        "invalid multisig threshold",
+    )?;
+
+    check_condition(
+        multisig.signers.iter()
+            .enumerate()
+            .all(|(i, signer)| !multisig.signers[..i].contains(signer)),
+        CodeVmError::InvalidMultisig,
+        "duplicate multisig signer key",
+    )?;
+
+    check_condition(
+        multisig.get_address().eq(owner),
+        CodeVmError::InvalidMultisig,
+        "the multisig does not match the account owner",
+    )?;
+
+    check_condition(
+        signatures.len() >= multisig.threshold as usize,
+        CodeVmError::NotEnoughSignatures,
+        "not enough multisig signatures",
+    )?;
+
+    // Do the cheap checks first, signature verification is expensive
+    let mut seen = [false; MAX_MULTISIG_SIGNERS];
+    for sig in signatures.iter() {
+        let index = sig.signer_index as usize;
+
+        check_condition(
+            index < multisig.signers.len(),
+            CodeVmError::InvalidMultisig,
+            "invalid multisig signer index",
+        )?;
+
+        check_condition(
+            !seen[index],
+            CodeVmError::InvalidMultisig,
+            "duplicate multisig signer",
+        )?;
+        seen[index] = true;
+    }
+
+    for sig in signatures.iter() {
+        verify(
+            multisig.signers[sig.signer_index as usize].as_ref(),
+            sig.signature.as_ref(),
+            message,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_commits_to_threshold_and_signers() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+
+        let owner = MultisigOwner { threshold: 1, signers: vec![a, b] };
+        let other_threshold = MultisigOwner { threshold: 2, signers: vec![a, b] };
+        let other_order = MultisigOwner { threshold: 1, signers: vec![b, a] };
+
+        assert_eq!(owner.get_address(), owner.clone().get_address());
+        assert_ne!(owner.get_address(), other_threshold.get_address());
+        assert_ne!(owner.get_address(), other_order.get_address());
+    }
+
+    #[test]
+    fn test_rejects_bad_multisig_before_verifying() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        let owner = MultisigOwner { threshold: 2, signers: vec![a, b] };
+        let address = owner.get_address();
+        let sig = |signer_index| MultisigSignature { signer_index, signature: Signature::default() };
+
+        // Wrong owner
+        assert!(multisig_verify(&a, &owner, &[sig(0), sig(1)], b"msg").is_err());
+
+        // Not enough signatures
+        assert!(multisig_verify(&address, &owner, &[sig(0)], b"msg").is_err());
+
+        // Duplicate signer
+        assert!(multisig_verify(&address, &owner, &[sig(0), sig(0)], b"msg").is_err());
+
+        // Out of range signer
+        assert!(multisig_verify(&address, &owner, &[sig(0), sig(2)], b"msg").is_err());
+
+        // Invalid threshold
+        let zero = MultisigOwner { threshold: 0, signers: vec![a, b] };
+        assert!(multisig_verify(&zero.get_address(), &zero, &[], b"msg").is_err());
+    }
+
+    #[test]
+    fn test_rejects_duplicate_signer_keys() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        let sig = |signer_index| MultisigSignature { signer_index, signature: Signature::default() };
+        let accept_all = |_: &[u8], _: &[u8], _: &[u8]| Ok(());
+
+        let owner = MultisigOwner { threshold: 2, signers: vec![a, b] };
+        assert!(multisig_verify_with(&owner.get_address(), &owner, &[sig(0), sig(1)], b"msg", accept_all).is_ok());
+
+        // One key listed twice would meet the threshold with a single signer
+        let dup = MultisigOwner { threshold: 2, signers: vec![a, b, a] };
+        assert_eq!(
+            multisig_verify_with(&dup.get_address(), &dup, &[sig(0), sig(2)], b"msg", accept_all),
+            Err(CodeVmError::InvalidMultisig.into()),
+        );
+    }
+}
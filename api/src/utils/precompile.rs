@@ -0,0 +1,171 @@
+use steel::*;
+use crate::error::CodeVmError;
+use super::sig_verify;
+use solana_program::{
+    ed25519_program,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Verify that an Ed25519 program instruction earlier in this transaction
+/// checked `sig` for the exact (`pubkey`, `message`) pair.
+///
+/// The runtime verifies precompile instructions before any program runs, so
+/// finding a matching entry is enough. Only entries that carry their own data
+/// (instruction index of u16::MAX) are considered.
+pub fn sig_verify_precompile(
+    instructions_info: &AccountInfo,
+    pubkey: &[u8],
+    sig: &[u8],
+    message: &[u8],
+) -> ProgramResult {
+    let current_index = load_current_index_checked(instructions_info)?;
+
+    for index in 0..current_index {
+        let ix = load_instruction_at_checked(index as usize, instructions_info)?;
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+
+        if has_signature(&ix.data, pubkey, sig, message) {
+            return Ok(());
+        }
+    }
+
+    Err(CodeVmError::InvalidSignature.into())
+}
+
+/// Same as `sig_verify_precompile`, for a transaction that has not been sent
+/// yet. `instructions` are the ones before the current instruction.
+///
+/// The runtime would reject a precompile instruction with a bad signature, so
+/// the signature of the matching entry is checked as well.
+pub fn sig_verify_precompile_instructions(
+    instructions: &[Instruction],
+    pubkey: &[u8],
+    sig: &[u8],
+    message: &[u8],
+) -> ProgramResult {
+    let found = instructions.iter()
+        .filter(|ix| ix.program_id == ed25519_program::ID)
+        .any(|ix| has_signature(&ix.data, pubkey, sig, message));
+
+    if !found {
+        return Err(CodeVmError::InvalidSignature.into());
+    }
+
+    sig_verify(pubkey, sig, message)
+        .map_err(|_| CodeVmError::InvalidSignature.into())
+}
+
+fn has_signature(data: &[u8], pubkey: &[u8], sig: &[u8], message: &[u8]) -> bool {
+    if data.len() < SIGNATURE_OFFSETS_START {
+        return false;
+    }
+
+    let num_signatures = data[0] as usize;
+    for i in 0..num_signatures {
+        let start = SIGNATURE_OFFSETS_START + i * SIGNATURE_OFFSETS_LEN;
+        let Some(offsets) = data.get(start..start + SIGNATURE_OFFSETS_LEN) else {
+            return false;
+        };
+
+        let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+
+        let signature_offset = read_u16(0) as usize;
+        let signature_ix = read_u16(2);
+        let pubkey_offset = read_u16(4) as usize;
+        let pubkey_ix = read_u16(6);
+        let message_offset = read_u16(8) as usize;
+        let message_size = read_u16(10) as usize;
+        let message_ix = read_u16(12);
+
+        if signature_ix != CURRENT_INSTRUCTION
+            || pubkey_ix != CURRENT_INSTRUCTION
+            || message_ix != CURRENT_INSTRUCTION {
+            continue;
+        }
+
+        let found_sig = data.get(signature_offset..signature_offset + sig.len());
+        let found_pubkey = data.get(pubkey_offset..pubkey_offset + pubkey.len());
+        let found_message = data.get(message_offset..message_offset + message_size);
+
+        if found_sig == Some(sig)
+            && found_pubkey == Some(pubkey)
+            && found_message == Some(message) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Instruction data for the Ed25519 program that verifies a single signature,
+/// laid out so that `sig_verify_precompile` can find it.
+pub fn create_ed25519_instruction_data(pubkey: &[u8], sig: &[u8], message: &[u8]) -> Vec<u8> {
+    let pubkey_offset = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN;
+    let signature_offset = pubkey_offset + pubkey.len();
+    let message_offset = signature_offset + sig.len();
+
+    let mut data = Vec::with_capacity(message_offset + message.len());
+    data.push(1); // num_signatures
+    data.push(0); // padding
+
+    for value in [
+        signature_offset as u16,
+        CURRENT_INSTRUCTION,
+        pubkey_offset as u16,
+        CURRENT_INSTRUCTION,
+        message_offset as u16,
+        message.len() as u16,
+        CURRENT_INSTRUCTION,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    data.extend_from_slice(pubkey);
+    data.extend_from_slice(sig);
+    data.extend_from_slice(message);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_own_signature() {
+        let pubkey = [1u8; 32];
+        let sig = [2u8; 64];
+        let message = [3u8; 32];
+        let data = create_ed25519_instruction_data(&pubkey, &sig, &message);
+
+        assert!(has_signature(&data, &pubkey, &sig, &message));
+        assert!(!has_signature(&data, &[9u8; 32], &sig, &message));
+        assert!(!has_signature(&data, &pubkey, &[9u8; 64], &message));
+        assert!(!has_signature(&data, &pubkey, &sig, &[9u8; 32]));
+    }
+
+    #[test]
+    fn test_ignores_data_from_other_instructions() {
+        let pubkey = [1u8; 32];
+        let sig = [2u8; 64];
+        let message = [3u8; 32];
+        let mut data = create_ed25519_instruction_data(&pubkey, &sig, &message);
+
+        // Point the message at another instruction in the transaction
+        data[SIGNATURE_OFFSETS_START + 12..SIGNATURE_OFFSETS_START + 14]
+            .copy_from_slice(&0u16.to_le_bytes());
+
+        assert!(!has_signature(&data, &pubkey, &sig, &message));
+    }
+
+    #[test]
+    fn test_truncated_data() {
+        assert!(!has_signature(&[], &[1u8; 32], &[2u8; 64], &[3u8; 32]));
+        assert!(!has_signature(&[1, 0, 0], &[1u8; 32], &[2u8; 64], &[3u8; 32]));
+    }
+}
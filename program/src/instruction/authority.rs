@@ -0,0 +1,93 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    These instructions rotate the authority of the VM using a two-step
+    handshake. The current authority proposes a new key, and the rotation only
+    takes effect once the new key accepts it. This avoids handing the VM to a
+    key that nobody controls.
+
+    The `authority` field of the VM is never changed, since the VM address and
+    all timelock addresses are derived from it. Instead, every instruction that
+    requires the VM authority checks against `get_current_authority()`.
+
+    Accounts expected by process_propose_authority:
+    
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The current authority of the VM.         |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+
+    Accounts expected by process_accept_authority:
+    
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | new_authority  | The proposed authority of the VM.        |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+
+    Instruction data (propose):
+
+    0. new_authority: Pubkey  - The proposed authority, or the default key to cancel.
+
+    Instruction data (accept):
+
+    (none)
+*/
+pub fn process_propose_authority(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = ProposeAuthorityIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    check_condition(
+        args.new_authority != vm.get_current_authority(),
+        CodeVmError::InvalidInstructionData,
+        "the proposed authority is already the current authority",
+    )?;
+
+    vm.pending_authority = args.new_authority;
+    vm.advance_poh(CodeInstruction::ProposeAuthorityIx, accounts, data);
+
+    Ok(())
+}
+
+pub fn process_accept_authority(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    AcceptAuthorityIx::try_from_bytes(data)?;
+
+    let [
+        new_authority_info,
+        vm_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(new_authority_info)?;
+    check_mut(vm_info)?;
+
+    let vm = load_vm(vm_info)?;
+
+    check_condition(
+        vm.get_pending_authority() == Some(*new_authority_info.key),
+        CodeVmError::Unauthorized,
+        "the signer is not the pending authority of the VM",
+    )?;
+
+    vm.current_authority = *new_authority_info.key;
+    vm.pending_authority = Pubkey::default();
+    vm.advance_poh(CodeInstruction::AcceptAuthorityIx, accounts, data);
+
+    Ok(())
+}
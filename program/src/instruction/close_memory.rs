@@ -0,0 +1,80 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction closes an empty memory account and returns its rent to a
+    recipient of the authority's choosing.
+
+    The memory account can only be closed once every virtual account in it has
+    been freed (compressed, closed or moved elsewhere).
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+    | 2 | mut | Memory  | PDA | vm_memory      | The memory account to close.             |
+    | 3 | mut | Any     |     | destination    | The account to send the rent to.         |
+
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. vm_memory: [ "code_vm", "vm_memory_account", <self.name>, <vm> ]
+
+
+    Instruction data:
+
+    <none>
+*/
+pub fn process_close_memory(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    CloseMemoryIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+        vm_memory_info,
+        destination_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(vm_memory_info)?;
+    check_mut(destination_info)?;
+    check_unique(
+        &[vm_info, vm_memory_info, destination_info],
+        "the destination must not be a VM account",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+    check_memory(vm_memory_info, vm_info)?;
+
+    let (capacity, account_size) = MemoryAccount::get_capacity_and_size(vm_memory_info);
+    let max_size = MemoryAccount::get_size_with_data(capacity, account_size);
+
+    // A memory account that was never fully resized can't hold any virtual
+    // accounts, so there is nothing to check.
+    if vm_memory_info.data_len() >= max_size {
+        let mem_data = MemoryAccount::get_data(vm_memory_info)?;
+        let mem = SliceAllocator::try_from_slice(&mem_data, capacity, account_size)?;
+
+        check_condition(
+            mem.num_items() == 0,
+            CodeVmError::AccountNotEmpty,
+            "the memory account still holds virtual accounts",
+        )?;
+    }
+
+    let counted = MemoryAccount::unpack(&vm_memory_info.data.borrow()).is_counted();
+    close_account(vm_memory_info, destination_info)?;
+
+    if counted {
+        vm.remove_child()?;
+    }
+    vm.advance_poh(CodeInstruction::CloseMemoryIx, accounts, data);
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction closes a relay account and its treasury, and returns the
+    rent of both to a recipient of the authority's choosing.
+
+    The relay can only be closed once its treasury (vm_relay_vault) has been
+    drained.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type         | PDA | Name           | Description                              |
+    |---|-----|--------------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer       |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm           | PDA | vm             | The VM instance state account.           |
+    | 2 | mut | Relay        | PDA | vm_relay       | The relay account to close.              |
+    | 3 | mut | TokenAccount | PDA | vm_relay_vault | The relay token account to close.        |
+    | 4 | mut | Any          |     | destination    | The account to send the rent to.         |
+    | 5 |     | Program      |     | token_program  | The SPL token program.                   |
+
+
+    Derived account seeds:
+
+    1. vm:           [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. relay:        [ "code_vm", "vm_relay_account", <self.name>, <vm> ]
+    3. relay_vault:  [ "code_vm", "vm_relay_vault", <relay> ]
+
+
+    Instruction data:
+
+    <none>
+*/
+pub fn process_close_relay(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    CloseRelayIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+        relay_info,
+        relay_vault_info,
+        destination_info,
+        token_program_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(relay_info)?;
+    check_mut(relay_vault_info)?;
+    check_mut(destination_info)?;
+    check_program(token_program_info, &spl_token::id())?;
+    check_unique(
+        &[vm_info, relay_info, relay_vault_info, destination_info],
+        "the destination must not be a VM account",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+    let relay = load_relay(relay_info, vm_info)?;
+
+    check_condition(
+        relay.treasury.vault.eq(relay_vault_info.key),
+        CodeVmError::AccountMismatch,
+        "relay_vault does not match the relay treasury",
+    )?;
+
+    check_condition(
+        relay_vault_info.to_token_account()?.amount == 0,
+        CodeVmError::NonZeroBalance,
+        "the relay treasury must be drained before it can be closed",
+    )?;
+
+    close_token_account(
+        relay_vault_info,
+        destination_info,
+        token_program_info,
+        &[
+            CODE_VM,
+            VM_RELAY_VAULT,
+            relay_info.key.as_ref(),
+            &[relay.treasury.vault_bump],
+        ],
+    )?;
+
+    let counted = relay.counted != 0;
+    close_account(relay_info, destination_info)?;
+
+    if counted {
+        vm.remove_child()?;
+    }
+    vm.advance_poh(CodeInstruction::CloseRelayIx, accounts, data);
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction closes an empty storage account and returns its rent to a
+    recipient of the authority's choosing.
+
+    The storage account can only be closed once its merkle trees hold no live
+    leaves, meaning every compressed account has been decompressed again. This
+    includes the trees archived by RolloverStorageIx. Otherwise, closing it
+    would destroy the only record of those accounts.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+    | 2 | mut | Storage | PDA | vm_storage     | The storage account to close.            |
+    | 3 | mut | Any     |     | destination    | The account to send the rent to.         |
+
+
+    Derived account seeds:
+
+    1. vm:         [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. vm_storage: [ "code_vm", "vm_storage_account", <self.name>, <vm> ]
+
+
+    Instruction data:
+
+    <none>
+*/
+pub fn process_close_storage(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    CloseStorageIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+        vm_storage_info,
+        destination_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(vm_storage_info)?;
+    check_mut(destination_info)?;
+    check_unique(
+        &[vm_info, vm_storage_info, destination_info],
+        "the destination must not be a VM account",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+    let storage = load_storage(vm_storage_info, vm_info)?;
+
+    check_condition(
+        storage.compressed_state.is_empty(),
+        CodeVmError::AccountNotEmpty,
+        "the storage account still holds compressed accounts",
+    )?;
+
+    let empty_root = storage.compressed_state.get_empty_root();
+    if storage.tree_id > 0 {
+        check_condition(
+            StorageAccount::get_archived_roots_mut(vm_storage_info)?
+                .iter()
+                .all(|root| root.eq(&empty_root)),
+            CodeVmError::AccountNotEmpty,
+            "an archived storage tree still holds compressed accounts",
+        )?;
+    }
+
+    let counted = storage.counted != 0;
+    close_account(vm_storage_info, destination_info)?;
+
+    if counted {
+        vm.remove_child()?;
+    }
+    vm.advance_poh(CodeInstruction::CloseStorageIx, accounts, data);
+
+    Ok(())
+}
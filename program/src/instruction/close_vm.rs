@@ -0,0 +1,97 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction decommissions a VM. It closes the VM account and its
+    omnibus token account, and returns the rent of both to a recipient of the
+    authority's choosing.
+
+    The omnibus must be empty, meaning every virtual timelock account has been
+    withdrawn from (or otherwise paid out). Memory, storage and relay accounts
+    must be closed first (see CloseMemoryIx, CloseStorageIx and CloseRelayIx),
+    as they can no longer be closed once the VM is gone. The VM counts them in
+    `num_children`, which only covers accounts created since the count was
+    added; older ones should still be closed before the VM. Each child records
+    whether it was counted, and closing an uncounted one leaves the count
+    alone, so it can't hide a counted child that is still open.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type         | PDA | Name           | Description                              |
+    |---|-----|--------------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer       |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm           | PDA | vm             | The VM instance state account to close.  |
+    | 2 | mut | TokenAccount | PDA | omnibus        | The VM token account to close.           |
+    | 3 | mut | Any          |     | destination    | The account to send the rent to.         |
+    | 4 |     | Program      |     | token_program  | The SPL token program.                   |
+
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. omnibus:   [ "code_vm", "vm_omnibus", <vm> ]
+
+
+    Instruction data:
+
+    <none>
+*/
+pub fn process_close_vm(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    CloseVmIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+        omnibus_info,
+        destination_info,
+        token_program_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(omnibus_info)?;
+    check_mut(destination_info)?;
+    check_program(token_program_info, &spl_token::id())?;
+    check_unique(
+        &[vm_info, omnibus_info, destination_info],
+        "the destination must not be a VM account",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    check_condition(
+        vm.omnibus.vault.eq(omnibus_info.key),
+        CodeVmError::AccountMismatch,
+        "omnibus does not match the VM omnibus",
+    )?;
+
+    check_condition(
+        vm.num_children == 0,
+        CodeVmError::AccountNotEmpty,
+        "memory, storage and relay accounts must be closed before the VM",
+    )?;
+
+    check_condition(
+        omnibus_info.to_token_account()?.amount == 0,
+        CodeVmError::NonZeroBalance,
+        "the omnibus must be empty before the VM can be closed",
+    )?;
+
+    close_token_account(
+        omnibus_info,
+        destination_info,
+        token_program_info,
+        &[
+            CODE_VM,
+            VM_OMNIBUS,
+            vm_info.key.as_ref(),
+            &[vm.get_omnibus_bump()],
+        ],
+    )?;
+
+    close_account(vm_info, destination_info)?;
+
+    Ok(())
+}
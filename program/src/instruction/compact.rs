@@ -0,0 +1,129 @@
+use code_vm_api::prelude::*;
+use solana_program::rent::Rent;
+use steel::*;
+
+/*
+    This instruction compacts a memory account and shrinks it down to a smaller
+    capacity, returning the freed rent to the VM authority.
+
+    Virtual accounts stored at or above the new capacity are moved into the
+    lowest free indices below it. Since this changes the account_index of those
+    virtual accounts, every move is logged as an AccountMoveEvent.
+
+    Off-chain indexers must apply these remappings before using the memory
+    account again.
+
+    The memory account must have been fully resized, and must hold no more
+    virtual accounts than the new capacity.
+
+    Accounts expected by this instruction:
+    
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+    | 2 | mut | Memory  | PDA | vm_memory      | The memory account to compact.           |
+
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. vm_memory: [ "code_vm", "vm_memory_account", <self.name>, <vm> ]
+
+
+    Instruction data:
+
+    0. num_accounts: u32    - The new capacity of the vm_memory account.
+*/
+pub fn process_compact(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+
+    let args = CompactMemoryIx::try_from_bytes(data)?.to_struct()?;
+    let [
+        vm_authority_info,
+        vm_info,
+        vm_memory_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(vm_memory_info)?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    check_not_paused(vm)?;
+
+    let memory = load_memory(vm_memory_info, vm_info)?;
+
+    let capacity = memory.get_capacity();
+    let account_size = memory.get_account_size();
+    let new_capacity = args.num_accounts as usize;
+
+    check_condition(
+        new_capacity > 0,
+        CodeVmError::InvalidInstructionData,
+        "num_accounts must be greater than zero",
+    )?;
+
+    check_condition(
+        new_capacity <= capacity,
+        CodeVmError::InvalidInstructionData,
+        "num_accounts must be less than or equal to the current capacity",
+    )?;
+
+    check_condition(
+        vm_memory_info.data_len() >= MemoryAccount::get_size_with_data(capacity, account_size),
+        CodeVmError::InvalidMemoryLayout,
+        "the memory account must be fully resized before it can be compacted",
+    )?;
+
+    {
+        let mut mem_data = MemoryAccount::get_data_mut(vm_memory_info)?;
+        let mut mem = SliceAllocatorMut::try_from_slice_mut(&mut mem_data, capacity, account_size)?;
+
+        // Move the accounts above the new capacity into the lowest free slots.
+        let mut free_index = 0;
+        for index in new_capacity..capacity {
+            if mem.is_empty(index as u16) {
+                continue;
+            }
+
+            while free_index < new_capacity && mem.has_item(free_index as u16) {
+                free_index += 1;
+            }
+
+            check_condition(
+                free_index < new_capacity,
+                CodeVmError::InvalidInstructionData,
+                "the memory account holds more virtual accounts than num_accounts",
+            )?;
+
+            mem.try_move_item(index as u16, free_index as u16)?;
+
+            AccountMoveEvent {
+                vm_memory: *vm_memory_info.key,
+                from_index: index as u16,
+                to_index: free_index as u16,
+            }.log();
+        }
+
+        SliceAllocatorMut::try_shrink(&mut mem_data, capacity, new_capacity, account_size)?;
+    }
+
+    memory.num_accounts = new_capacity as u32;
+
+    // Return the rent that is no longer needed to the authority.
+    let new_size = MemoryAccount::get_size_with_data(new_capacity, account_size);
+    vm_memory_info.realloc(new_size, false)?;
+
+    let rent_exempt_balance = Rent::get()?.minimum_balance(new_size);
+    let excess = vm_memory_info.lamports().saturating_sub(rent_exempt_balance);
+
+    **vm_memory_info.lamports.borrow_mut() -= excess;
+    **vm_authority_info.lamports.borrow_mut() += excess;
+
+    vm.advance_poh(CodeInstruction::CompactMemoryIx, accounts, data);
+
+    Ok(())
+}
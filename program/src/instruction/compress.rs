@@ -12,6 +12,11 @@ use steel::*;
     was witnessed by the VM authority as it currently exists in the VM's working
     memory before it is compressed.
 
+    Only a hash of the account is kept in storage, so a CompressEvent with the
+    packed account, the signature and the leaf index is logged. Anyone can use
+    these to rebuild the storage tree and decompress or withdraw the account,
+    without relying on the operator to keep them.
+
     Accounts expected by this instruction:
 
     | # | R/W | Type    | PDA | Name         | Description                              |
@@ -20,6 +25,8 @@ use steel::*;
     | 1 | mut | Vm      | PDA | vm           | The VM instance state account.           |
     | 2 | mut | Memory  | PDA | vm_memory    | The memory account to pull from.         |
     | 3 | mut | Storage | PDA | vm_storage   | The storage account to push to.          |
+    | 4 |     | Sysvar  |     | instructions | Optional, required when signatures are   |
+    |   |     |         |     |              | verified by the Ed25519 precompile.      |
 
 
     Derived account seeds:
@@ -35,9 +42,12 @@ use steel::*;
 */
 pub fn process_compress(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let args = CompressIx::try_from_bytes(data)?.to_struct()?;
-    let [vm_authority_info, vm_info, vm_memory_info, vm_storage_info] = accounts else {
-        return Err(ProgramError::NotEnoughAccountKeys);
-    };
+    let (vm_authority_info, vm_info, vm_memory_info, vm_storage_info, instructions_info) =
+        match accounts {
+            [a0, a1, a2, a3] => (a0, a1, a2, a3, None),
+            [a0, a1, a2, a3, a4] => (a0, a1, a2, a3, Some(a4)),
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
 
     check_signer(vm_authority_info)?;
     check_mut(vm_info)?;
@@ -46,23 +56,34 @@ pub fn process_compress(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramRes
 
     let vm = load_vm_checked(vm_info, vm_authority_info)?;
 
+    check_not_paused(vm)?;
+
     check_memory(vm_memory_info, vm_info)?;
-    check_storage(vm_storage_info, vm_info)?;
+    let tree_id = load_storage(vm_storage_info, vm_info)?.tree_id;
 
     let va = try_read(vm_memory_info, args.account_index)?;
     let va_hash = va.get_hash();
 
-    sig_verify(
+    vm_sig_verify(
+        vm,
+        instructions_info,
         vm_authority_info.key.as_ref(),
         args.signature.as_ref(),
         va_hash.as_ref(),
     )?;
 
-    let sig_hash = hashv(&[args.signature.as_ref(), va_hash.as_ref()]);
-
-    try_compress(vm_storage_info, sig_hash)?;
+    let leaf_index = try_compress(vm_storage_info, &args.signature, &va_hash)?;
     try_delete(vm_memory_info, args.account_index)?;
 
+    CompressEvent {
+        vm: *vm_info.key,
+        vm_storage: *vm_storage_info.key,
+        tree_id,
+        leaf_index,
+        packed_va: va.pack(),
+        signature: args.signature,
+    }.log();
+
     vm.advance_poh(CodeInstruction::CompressIx, accounts, data);
 
     Ok(())
@@ -0,0 +1,121 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction is used to compress several accounts from the same memory
+    account into the VM's cold storage in one go. It behaves like the compress
+    instruction, but the VM authority signs a single message covering all of
+    the accounts instead of one signature per account.
+
+    The signed message is `get_compress_batch_message` over the hashes of the
+    virtual accounts, in the order of `account_indices`. Each account is then
+    inserted into storage with the same leaf as the compress instruction would
+    have used, hash(signature, va_hash, leaf_index), so the accounts can be
+    decompressed one at a time with the batch signature.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type    | PDA | Name         | Description                              |
+    |---|-----|---------|-----|--------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm           | The VM instance state account.           |
+    | 2 | mut | Memory  | PDA | vm_memory    | The memory account to pull from.         |
+    | 3 | mut | Storage | PDA | vm_storage   | The storage account to push to.          |
+    | 4 |     | Sysvar  |     | instructions | Optional, required when signatures are   |
+    |   |     |         |     |              | verified by the Ed25519 precompile.      |
+
+
+    Derived account seeds:
+
+    1. vm:         [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. vm_memory:  [ "code_vm", "vm_memory_account", <self.name>, <vm> ]
+    3. vm_storage: [ "code_vm", "vm_storage_account", <self.name>, <vm> ]
+
+    Instruction data:
+
+    0. account_indices: [u16]  - The indices of the accounts in the VM's paged memory,
+                                 at most MAX_COMPRESS_BATCH_SIZE of them.
+    1. signature: [u8; 64]     - A signature of the batch message signed by the VM authority.
+*/
+pub fn process_compress_batch(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = CompressBatchIx::try_from_slice(data)?;
+    let (vm_authority_info, vm_info, vm_memory_info, vm_storage_info, instructions_info) =
+        match accounts {
+            [a0, a1, a2, a3] => (a0, a1, a2, a3, None),
+            [a0, a1, a2, a3, a4] => (a0, a1, a2, a3, Some(a4)),
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(vm_memory_info)?;
+    check_mut(vm_storage_info)?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    check_not_paused(vm)?;
+
+    check_memory(vm_memory_info, vm_info)?;
+    let tree_id = load_storage(vm_storage_info, vm_info)?.tree_id;
+
+    check_condition(
+        !args.account_indices.is_empty(),
+        CodeVmError::InvalidInstructionData,
+        "at least one account index must be provided",
+    )?;
+
+    // Every account is logged with a CompressEvent, and the events are the
+    // only record of the compressed state. Logs past the runtime's 10KB limit
+    // are truncated, so the batch is kept small enough for all of them to fit.
+    check_condition(
+        args.account_indices.len() <= MAX_COMPRESS_BATCH_SIZE,
+        CodeVmError::InvalidInstructionData,
+        "too many account indices",
+    )?;
+
+    // A repeated index would insert the same leaf twice, allowing the account
+    // to be decompressed twice.
+    for (i, index) in args.account_indices.iter().enumerate() {
+        check_condition(
+            !args.account_indices[i + 1..].contains(index),
+            CodeVmError::DuplicateAccount,
+            "account indices must be unique",
+        )?;
+    }
+
+    let mut vas = Vec::with_capacity(args.account_indices.len());
+    let mut va_hashes = Vec::with_capacity(args.account_indices.len());
+    for index in args.account_indices.iter() {
+        let va = try_read(vm_memory_info, *index)?;
+        va_hashes.push(va.get_hash());
+        vas.push(va);
+    }
+
+    let message = get_compress_batch_message(&va_hashes);
+
+    vm_sig_verify(
+        vm,
+        instructions_info,
+        vm_authority_info.key.as_ref(),
+        args.signature.as_ref(),
+        message.as_ref(),
+    )?;
+
+    for ((index, va), va_hash) in args.account_indices.iter().zip(vas.iter()).zip(va_hashes.iter()) {
+        let leaf_index = try_compress(vm_storage_info, &args.signature, va_hash)?;
+        try_delete(vm_memory_info, *index)?;
+
+        CompressEvent {
+            vm: *vm_info.key,
+            vm_storage: *vm_storage_info.key,
+            tree_id,
+            leaf_index,
+            packed_va: va.pack(),
+            signature: args.signature,
+        }.log();
+    }
+
+    vm.advance_poh(CodeInstruction::CompressBatchIx, accounts, data);
+
+    Ok(())
+}
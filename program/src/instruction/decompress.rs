@@ -30,7 +30,13 @@ use steel::*;
     Instruction data:
 
     0. account_index: u16   - The index of the account in the VM's paged memory.
-    1. signature: [u8; 64]  - A signature of the current account state signed by the VM authority.
+    1. packed_va: [u8]      - The packed virtual account state.
+    2. tree_id: u16         - The storage tree that holds the account, see RolloverStorageIx.
+    3. leaf_index: u64      - The index of the account in the storage tree.
+    4. proof: [Hash]        - A merkle proof against the current storage root. On
+                              storage accounts with a changelog, a proof against
+                              a recent root is also accepted.
+    5. signature: [u8; 64]  - The signature of the account state made by the VM authority when it was compressed.
 
     Notes:
 
@@ -66,29 +72,76 @@ pub fn process_decompress(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramR
 
     let vm = load_vm_checked(vm_info, vm_authority_info)?;
 
+    check_not_paused(vm)?;
+
     check_memory(vm_memory_info, vm_info)?;
     check_storage(vm_storage_info, vm_info)?;
     check_is_empty(vm_memory_info, args.account_index)?;
 
     let unchecked_va = VirtualAccount::unpack(&args.packed_va)?;
-    match unchecked_va {
+    check_decompress_state(
+        &unchecked_va,
+        vm,
+        vm_info,
+        unlock_pda_info,
+        withdraw_receipt_info,
+    )?;
+
+    let va = unchecked_va;
+    let va_hash = va.get_hash();
+
+    // The storage leaf commits to (signature, va_hash, leaf_index) and is only
+    // inserted by compress after checking the authority signature, so the
+    // proof is what authenticates the account and its leaf index. The
+    // signature isn't checked against the current authority, which may have
+    // been rotated since it was compressed.
+
+    let leaf = try_decompress(vm_storage_info, args.tree_id, &args.signature, &va_hash, args.leaf_index, &args.proof)?;
+
+    DecompressEvent {
+        vm: *vm_info.key,
+        vm_storage: *vm_storage_info.key,
+        tree_id: args.tree_id,
+        leaf_index: args.leaf_index,
+        leaf,
+    }.log();
+    try_write(vm_memory_info, args.account_index, &va)?;
+
+    vm.advance_poh(CodeInstruction::DecompressIx, accounts, data);
+
+    Ok(())
+}
+
+/// Checks that a virtual account can be brought back into working memory. A
+/// timelock account must come with its unlock_pda and withdraw_receipt, and
+/// must not have been withdrawn from non-custodially.
+pub(crate) fn check_decompress_state(
+    va: &VirtualAccount,
+    vm: &CodeVmAccount,
+    vm_info: &AccountInfo<'_>,
+    unlock_pda_info: Option<&AccountInfo<'_>>,
+    withdraw_receipt_info: Option<&AccountInfo<'_>>,
+) -> ProgramResult {
+    match va {
         VirtualAccount::Timelock(vta) => {
             check_condition(
                 unlock_pda_info.is_some(),
+                CodeVmError::MissingAccount,
                 "unlock_pda address is required for timelocked virtual accounts",
             )?;
 
             check_condition(
                 withdraw_receipt_info.is_some(),
+                CodeVmError::MissingAccount,
                 "withdraw_receipt address is required for timelocked virtual accounts",
             )?;
 
             check_timelock_state(
-                &vta,
+                vta,
                 vm,
                 vm_info,
-                unlock_pda_info.unwrap(),
-                withdraw_receipt_info.unwrap(),
+                unlock_pda_info.ok_or(CodeVmError::MissingAccount)?,
+                withdraw_receipt_info.ok_or(CodeVmError::MissingAccount)?,
             )?;
         }
         VirtualAccount::Nonce(_) => {
@@ -97,23 +150,17 @@ pub fn process_decompress(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramR
         VirtualAccount::Relay(_) => {
             // Relay accounts are not timelocked
         }
+        VirtualAccount::Escrow(_) => {
+            // Escrow accounts are not timelocked
+        }
+        VirtualAccount::Stream(_) => {
+            // Stream accounts are not timelocked
+        }
+        VirtualAccount::Allowance(_) => {
+            // Allowance accounts are not timelocked
+        }
     }
 
-    let va = unchecked_va;
-    let va_hash = va.get_hash();
-
-    sig_verify(
-        vm_authority_info.key.as_ref(),
-        args.signature.as_ref(),
-        va_hash.as_ref(),
-    )?;
-
-    let sig_hash = hashv(&[args.signature.as_ref(), va_hash.as_ref()]);
-    try_decompress(vm_storage_info, sig_hash, &args.proof)?;
-    try_write(vm_memory_info, args.account_index, &va)?;
-
-    vm.advance_poh(CodeInstruction::DecompressIx, accounts, data);
-
     Ok(())
 }
 
@@ -131,6 +178,7 @@ fn check_timelock_state(
 
     check_condition(
         unlock_pda_info.key.eq(&unlock_address),
+        CodeVmError::AccountMismatch,
         "unlock_pda does not match the expected unlock address",
     )?;
 
@@ -138,12 +186,14 @@ fn check_timelock_state(
 
     check_condition(
         withdraw_receipt_info.key.eq(&receipt_address),
+        CodeVmError::AccountMismatch,
         "withdraw_receipt does not match the expected receipt address",
     )?;
 
     // Check that the receipt account is empty (no data; len == 0)
     check_condition(
         withdraw_receipt_info.data_is_empty(),
+        CodeVmError::AccountNotEmpty,
         "withdraw_receipt is not empty",
     )?;
 
@@ -0,0 +1,122 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use super::check_decompress_state;
+
+/*
+    This instruction is used to decompress several virtual accounts from the
+    VM's cold storage (compressed_mem) at once. It behaves like the decompress
+    instruction, but all of the accounts are checked against the storage root
+    with a single merkle multi-proof, which only carries each sibling node
+    once.
+
+    The accounts must be sorted by their leaf index in the storage tree. The
+    multi-proof holds the siblings that can't be computed from the accounts
+    themselves, layer by layer from the leaves up, and left to right within a
+    layer (see `MerkleTree::get_merkle_multi_proof`).
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type            | Req | PDA | Name             | Description                              |
+    |---|-----|-----------------|-----|-----|------------------|------------------------------------------|
+    | 0 | mut | Signer          | Yes |     | vm_authority     | The authority of the VM.                 |
+    | 1 | mut | Vm              | Yes | PDA | vm               | The VM instance state account.           |
+    | 2 | mut | Memory          | Yes | PDA | vm_memory        | The memory account to write to.          |
+    | 3 | mut | Storage         | Yes | PDA | vm_storage       | The storage account to pull from.        |
+    |...|     | UnlockState     |     | PDA | unlock_pda       | State for unlocked timelock accounts.    |
+    |...|     | WithdrawReceipt |     | PDA | withdraw_receipt | State for withdrawn tokens.              |
+
+    The last two accounts are repeated once per virtual account, in the same
+    order as the instruction data. They are only required for timelock
+    accounts, see the decompress instruction for details.
+
+
+    Derived account seeds:
+
+    1. vm:         [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. vm_memory:  [ "code_vm", "vm_memory_account", <self.name>, <vm> ]
+    3. vm_storage: [ "code_vm", "vm_storage_account", <self.name>, <vm> ]
+    4. unlock_pda:  [ "code_vm", "vm_unlock_pda_account", <account_owner>, <timelock_address>, <vm> ]
+
+    Instruction data:
+
+    0. tree_id: u16                        - The storage tree that holds every account.
+    1. accounts: [DecompressBatchAccount]  - The accounts to decompress, sorted by leaf_index.
+         account_index: u16                - The index to write the account to in the VM's paged memory.
+         leaf_index: u64                   - The index of the account in the storage tree.
+         packed_va: [u8]                   - The packed virtual account state.
+         signature: [u8; 64]               - The signature made by the VM authority when it was compressed.
+    2. proof: [Hash]                       - A multi-proof for all of the accounts.
+*/
+pub fn process_decompress_batch(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = DecompressBatchIx::try_from_slice(data)?;
+    let [vm_authority_info, vm_info, vm_memory_info, vm_storage_info, state_infos @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_condition(
+        !args.accounts.is_empty(),
+        CodeVmError::InvalidInstructionData,
+        "at least one account must be provided",
+    )?;
+
+    if state_infos.len() != 2 * args.accounts.len() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(vm_memory_info)?;
+    check_mut(vm_storage_info)?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    check_not_paused(vm)?;
+
+    check_memory(vm_memory_info, vm_info)?;
+    check_storage(vm_storage_info, vm_info)?;
+
+    let mut vas = Vec::with_capacity(args.accounts.len());
+    let mut leaf_indices = Vec::with_capacity(args.accounts.len());
+    let mut signatures = Vec::with_capacity(args.accounts.len());
+    let mut va_hashes = Vec::with_capacity(args.accounts.len());
+
+    for (entry, infos) in args.accounts.iter().zip(state_infos.chunks(2)) {
+        let unchecked_va = VirtualAccount::unpack(&entry.packed_va)?;
+        check_decompress_state(
+            &unchecked_va,
+            vm,
+            vm_info,
+            get_optional(&infos[0]),
+            get_optional(&infos[1]),
+        )?;
+
+        // Same leaf as the decompress instruction, see there for why the
+        // signature itself is not checked.
+        va_hashes.push(unchecked_va.get_hash());
+        signatures.push(entry.signature);
+        leaf_indices.push(entry.leaf_index);
+        vas.push(unchecked_va);
+    }
+
+    let leaves = try_decompress_multi(vm_storage_info, args.tree_id, &leaf_indices, &signatures, &va_hashes, &args.proof)?;
+
+    for (leaf_index, leaf) in leaf_indices.iter().zip(leaves.iter()) {
+        DecompressEvent {
+            vm: *vm_info.key,
+            vm_storage: *vm_storage_info.key,
+            tree_id: args.tree_id,
+            leaf_index: *leaf_index,
+            leaf: *leaf,
+        }.log();
+    }
+
+    for (entry, va) in args.accounts.iter().zip(vas.iter()) {
+        check_is_empty(vm_memory_info, entry.account_index)?;
+        try_write(vm_memory_info, entry.account_index, va)?;
+    }
+
+    vm.advance_poh(CodeInstruction::DecompressBatchIx, accounts, data);
+
+    Ok(())
+}
@@ -64,18 +64,22 @@ pub fn process_deposit(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResu
 
     let vm = load_vm_checked(vm_info, vm_authority_info)?;
 
+    check_not_paused(vm)?;
+
     check_omnibus(omnibus_info, vm_info)?;
     check_memory(vm_memory_info, vm_info)?;
 
     let va = try_read(&vm_memory_info, args.account_index)?;
-    let mut vta = va.into_inner_timelock().unwrap();
+    let mut vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
 
     check_condition(
         vta.owner.eq(depositor_info.key),
+        CodeVmError::InvalidOwner,
         "The depositor does not own this account",
     )?;
 
-    transfer_signed(
+    transfer_signed_with_event(
         deposit_pda_info,
         deposit_ata_info,
         omnibus_info,
@@ -93,7 +97,7 @@ pub fn process_deposit(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResu
     vta.balance = vta
         .balance
         .checked_add(args.amount)
-        .ok_or(ProgramError::ArithmeticOverflow)?;
+        .ok_or(CodeVmError::ArithmeticOverflow)?;
 
     try_write(
         vm_memory_info,
@@ -24,6 +24,8 @@ use crate::opcode::*;
     | 8 | mut | TokenAccount |     | PDA | relay_vault      | A derived token account owned by the relay.  |
     | 9 | mut | TokenAccount |     |     | external_address | Required when making external transfers.     |
     | 10|     | Program      |     |     | token_program    | Required when making token transfers.        |
+    | 11|     | Sysvar       |     |     | instructions     | Required when signatures are verified by the |
+    |   |     |              |     |     |                  | Ed25519 precompile (see SigVerifyMode).      |
 
 
     Derived account seeds:
@@ -46,35 +48,81 @@ use crate::opcode::*;
 */
 pub fn process_exec(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let args = ExecIx::try_from_slice(data)?;
-    let ctx = ExecContext::try_from(accounts)?;
+    let mut ctx = ExecContext::try_from(accounts)?;
 
     check_signer(ctx.vm_authority_info)?;
     check_mut(ctx.vm_info)?;
 
     let vm = load_vm_checked(ctx.vm_info, ctx.vm_authority_info)?;
 
+    check_not_paused(vm)?;
+
     ctx.check_memory_banks()?;
 
-    let ix = Opcode::try_from(args.opcode).unwrap();
+    exec_opcode(&mut ctx, &args)?;
+
+    vm.advance_poh(CodeInstruction::ExecIx, accounts, data);
 
-    match ix {
+    Ok(())
+}
 
-        Opcode::TransferOp             => process_transfer(&ctx, &args),
-        Opcode::WithdrawOp             => process_withdraw(&ctx, &args),
-        Opcode::RelayOp                => process_relay(&ctx, &args),
+pub fn exec_opcode(ctx: &mut ExecContext, args: &ExecIxData) -> ProgramResult {
+    let ix = Opcode::try_from(args.opcode)
+        .map_err(|_| CodeVmError::InvalidOpcode)?;
+    let vm = load_vm(ctx.vm_info)?;
 
-        Opcode::ExternalTransferOp     => process_external_transfer(&ctx, &args),
-        Opcode::ExternalWithdrawOp     => process_external_withdraw(&ctx, &args),
-        Opcode::ExternalRelayOp        => process_external_relay(&ctx, &args),
+    check_condition(
+        vm.is_opcode_enabled(args.opcode),
+        CodeVmError::OpcodeDisabled,
+        "the opcode is disabled",
+    )?;
 
-        Opcode::ConditionalTransferOp  => process_conditional_transfer(&ctx, &args),
+    let result = match ix {
 
-        Opcode::AirdropOp              => process_airdrop(&ctx, &args),
+        Opcode::TransferOp             => process_transfer(ctx, args),
+        Opcode::WithdrawOp             => process_withdraw(ctx, args),
+        Opcode::RelayOp                => process_relay(ctx, args),
 
-        _ => Err(ProgramError::InvalidInstructionData),
-    }?;
+        Opcode::MultisigTransferOp     => process_multisig_transfer(ctx, args),
+        Opcode::MultisigWithdrawOp     => process_multisig_withdraw(ctx, args),
 
-    vm.advance_poh(CodeInstruction::ExecIx, accounts, data);
+        Opcode::ExternalTransferOp     => process_external_transfer(ctx, args),
+        Opcode::ExternalWithdrawOp     => process_external_withdraw(ctx, args),
+        Opcode::ExternalRelayOp        => process_external_relay(ctx, args),
+
+        Opcode::ConditionalTransferOp  => process_conditional_transfer(ctx, args),
+
+        Opcode::AirdropOp              => process_airdrop(ctx, args),
+        Opcode::PayoutOp               => process_payout(ctx, args),
+
+        Opcode::EscrowFundOp           => process_escrow_fund(ctx, args),
+        Opcode::EscrowClaimOp          => process_escrow_claim(ctx, args),
+        Opcode::EscrowRefundOp         => process_escrow_refund(ctx, args),
+
+        Opcode::StreamCreateOp         => process_stream_create(ctx, args),
+        Opcode::StreamWithdrawOp       => process_stream_withdraw(ctx, args),
+        Opcode::StreamCancelOp         => process_stream_cancel(ctx, args),
+
+        Opcode::ApproveOp              => process_approve(ctx, args),
+        Opcode::TransferFromOp         => process_transfer_from(ctx, args),
+
+        Opcode::CloseNonceOp           => process_close_nonce(ctx, args),
+        Opcode::CloseTimelockOp        => process_close_timelock(ctx, args),
+        Opcode::CloseRelayOp           => process_close_relay(ctx, args),
+        Opcode::CloseEscrowOp          => process_close_escrow(ctx, args),
+        Opcode::CloseStreamOp          => process_close_stream(ctx, args),
+        Opcode::CloseAllowanceOp       => process_close_allowance(ctx, args),
+
+        _ => Err(CodeVmError::InvalidOpcode.into()),
+    };
+
+    result?;
+
+    OpcodeEvent {
+        opcode: args.opcode,
+        mem_indicies: args.mem_indicies.clone(),
+        mem_banks: args.mem_banks.clone(),
+    }.log();
 
     Ok(())
 }
@@ -91,6 +139,7 @@ pub struct ExecContext<'a, 'b> {
     pub relay_vault_info: Option<&'a AccountInfo<'b>>,
     pub external_address_info: Option<&'a AccountInfo<'b>>,
     pub token_program_info: Option<&'a AccountInfo<'b>>,
+    pub instructions_info: Option<&'a AccountInfo<'b>>,
 }
 
 impl<'a, 'b> ExecContext<'a, 'b> {
@@ -107,6 +156,7 @@ impl<'a, 'b> ExecContext<'a, 'b> {
             relay_vault_info,
             external_address_info,
             token_program_info,
+            instructions_info,
         ) = match accounts {
             [ a0, a1, a2, a3, a4, a5, a6, a7, a8, a9, a10 ] => (
                 a0,
@@ -120,6 +170,21 @@ impl<'a, 'b> ExecContext<'a, 'b> {
                 get_optional(a8),
                 get_optional(a9),
                 get_optional(a10),
+                None,
+            ),
+            [ a0, a1, a2, a3, a4, a5, a6, a7, a8, a9, a10, a11 ] => (
+                a0,
+                a1,
+                get_optional(a2),
+                get_optional(a3),
+                get_optional(a4),
+                get_optional(a5),
+                get_optional(a6),
+                get_optional(a7),
+                get_optional(a8),
+                get_optional(a9),
+                get_optional(a10),
+                Some(a11),
             ),
             _ => return Err(ProgramError::NotEnoughAccountKeys),
         };
@@ -136,6 +201,7 @@ impl<'a, 'b> ExecContext<'a, 'b> {
             relay_vault_info,
             external_address_info,
             token_program_info,
+            instructions_info,
         })
     }
 
@@ -174,12 +240,55 @@ impl<'a, 'b> ExecContext<'a, 'b> {
         Ok(())
     }
 
-    pub fn get_banks(&self) -> [Option<&AccountInfo<'b>>; 4] {
-        [
+    /// The memory bank at `bank`, as given by the opcode's `mem_banks`. Fails
+    /// if the index is out of range or the bank was not provided.
+    pub fn get_bank(&self, bank: u8) -> Result<&'a AccountInfo<'b>, CodeVmError> {
+        let banks = [
             self.mem_a_info,
             self.mem_b_info,
             self.mem_c_info,
             self.mem_d_info,
-        ]
+        ];
+
+        banks.get(bank as usize)
+            .copied()
+            .flatten()
+            .ok_or(CodeVmError::MissingAccount)
+    }
+}
+
+impl ExecState for ExecContext<'_, '_> {
+    fn get_vm_address(&self) -> &Pubkey {
+        self.vm_info.key
+    }
+
+    fn try_read(&self, bank: u8, index: u16) -> Result<VirtualAccount, ProgramError> {
+        try_read(self.get_bank(bank)?, index)
+    }
+
+    fn try_write(&mut self, bank: u8, index: u16, va: &VirtualAccount) -> ProgramResult {
+        try_write(self.get_bank(bank)?, index, va)
+    }
+
+    fn try_delete(&mut self, bank: u8, index: u16) -> ProgramResult {
+        try_delete(self.get_bank(bank)?, index)
+    }
+
+    fn check_is_empty(&self, bank: u8, index: u16) -> ProgramResult {
+        check_is_empty(self.get_bank(bank)?, index)
+    }
+
+    fn sig_verify(
+        &self,
+        vm: &CodeVmAccount,
+        pubkey: &[u8],
+        sig: &[u8],
+        message: &[u8],
+    ) -> ProgramResult {
+        vm_sig_verify(vm, self.instructions_info, pubkey, sig, message)
+    }
+
+    fn get_unix_timestamp(&self) -> Result<i64, ProgramError> {
+        Ok(Clock::get()?.unix_timestamp)
     }
 }
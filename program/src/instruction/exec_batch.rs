@@ -0,0 +1,62 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use super::{exec_opcode, ExecContext};
+
+/*
+    This instruction is used to execute an ordered list of VM opcodes against
+    the same set of accounts. Either every opcode in the batch succeeds or the
+    whole instruction fails, so a sequence like transfer + withdraw + airdrop
+    for a single user can be applied atomically.
+
+    Each entry is processed exactly as if it had been sent as its own ExecIx.
+    The PoH is advanced once per entry (using the ExecIx discriminator), which
+    means the resulting VM state is the same as sending the entries one by one.
+
+    Note, opcodes set the virtual durable nonce to the current PoH when used.
+    Advancing the PoH between entries makes sure that a signed intent can't be
+    replayed by a later entry in the same batch.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type         | Req | PDA | Name             | Description                                  |
+    |---|-----|------------- |-----|-----|------------------|----------------------------------------------|
+    |...| The same as the vm_exec instruction.                                                             |
+
+
+    Instruction data:
+
+    0. ops: [ExecIxData]   - The opcodes to execute, in order. Each entry has
+                             the same layout as the vm_exec instruction data.
+*/
+pub fn process_exec_batch(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = ExecBatchIx::try_from_slice(data)?;
+    let mut ctx = ExecContext::try_from(accounts)?;
+
+    check_signer(ctx.vm_authority_info)?;
+    check_mut(ctx.vm_info)?;
+
+    let vm = load_vm_checked(ctx.vm_info, ctx.vm_authority_info)?;
+
+    check_not_paused(vm)?;
+
+    ctx.check_memory_banks()?;
+
+    check_condition(
+        !args.ops.is_empty(),
+        CodeVmError::InvalidInstructionData,
+        "at least one opcode must be provided",
+    )?;
+
+    for op in args.ops.iter() {
+        exec_opcode(&mut ctx, op)?;
+
+        // Strip the ExecIx discriminator, the PoH is advanced using the same
+        // data that a standalone ExecIx would have used.
+        let op_data = ExecIx::try_to_bytes(op.clone())?;
+
+        vm.advance_poh(CodeInstruction::ExecIx, accounts, &op_data[1..]);
+    }
+
+    Ok(())
+}
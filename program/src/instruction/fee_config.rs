@@ -0,0 +1,70 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction configures the operator fee charged by the VM.
+
+    When set, TransferOp, ExternalTransferOp and AirdropOp deduct a fee of
+    `fee_flat + amount * fee_bps / 10_000` from the source account, in addition
+    to the transferred amount, and credit it to the virtual timelock account
+    owned by `fee_collector`. That account is passed as the last memory index
+    of the opcode, and the owner's signature must cover the fee (see
+    create_fee_message).
+
+    Setting both `fee_bps` and `fee_flat` to zero disables the fee.
+
+    The fee fields grew the VM account, so a VM created before they were added
+    must be migrated first (see MigrateVmIx).
+
+    Accounts expected by this instruction:
+    
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+
+    Instruction data:
+
+    0. fee_collector: Pubkey  - The owner of the fee-collector virtual account.
+    1. fee_flat: u64          - A flat fee charged per operation.
+    2. fee_bps: u16           - A fee in basis points of the amount.
+*/
+pub fn process_set_fee_config(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = SetFeeConfigIx::try_from_bytes(data)?.to_struct()?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+
+    check_condition(
+        args.fee_bps <= MAX_FEE_BPS,
+        CodeVmError::InvalidInstructionData,
+        "the fee in basis points is too large",
+    )?;
+
+    let has_fee = args.fee_bps > 0 || args.fee_flat > 0;
+    check_condition(
+        !has_fee || args.fee_collector != Pubkey::default(),
+        CodeVmError::InvalidInstructionData,
+        "a fee collector is required when charging a fee",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    vm.fee_collector = args.fee_collector;
+    vm.fee_flat = args.fee_flat;
+    vm.fee_bps = args.fee_bps;
+    vm.advance_poh(CodeInstruction::SetFeeConfigIx, accounts, data);
+
+    Ok(())
+}
@@ -85,8 +85,10 @@ pub fn process_init_memory(accounts: &[AccountInfo<'_>], data: &[u8]) -> Program
     memory.set_num_accounts(args.num_accounts as u32);
     memory.set_account_size(args.account_size as u16);
     memory.version = 1;
+    memory.set_counted();
     println!("{:#?}", std::mem::size_of::<MemoryAccount>());
 
+    vm.add_child()?;
     vm.advance_poh(CodeInstruction::InitMemoryIx, accounts, data);
 
     Ok(())
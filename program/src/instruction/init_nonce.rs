@@ -45,6 +45,8 @@ pub fn process_init_nonce(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramR
 
     let vm = load_vm_checked(vm_info, vm_authority_info)?;
 
+    check_not_paused(vm)?;
+
     check_memory(vm_memory_info, vm_info)?;
     check_is_empty(vm_memory_info, args.account_index)?;
 
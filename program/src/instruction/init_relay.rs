@@ -59,8 +59,11 @@ pub fn process_init_relay(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramR
 
     let vm = load_vm_checked(vm_info, vm_authority_info)?;
 
+    check_not_paused(vm)?;
+
     check_condition(
         mint_info.key == &vm.mint,
+        CodeVmError::AccountMismatch,
         "mint account does not match VM instance",
     )?;
 
@@ -119,6 +122,7 @@ pub fn process_init_relay(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramR
     relay.vm = vm_info.key.clone();
     relay.bump = args.relay_bump;
     relay.name = args.name;
+    relay.counted = 1;
     relay.num_levels = RELAY_STATE_DEPTH as u8;
     relay.num_history = RELAY_HISTORY_ITEMS as u8;
 
@@ -131,6 +135,7 @@ pub fn process_init_relay(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramR
 
     relay.recent_roots.push(relay.history.get_root().as_ref());
 
+    vm.add_child()?;
     vm.advance_poh(CodeInstruction::InitRelayIx, accounts, data);
 
     Ok(())
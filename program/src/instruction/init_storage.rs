@@ -19,6 +19,10 @@ use steel::*;
     decompress process works in reverse to this instruction but also requires a
     merkle proof.
 
+    The storage account keeps a changelog of the last few tree updates, so that
+    a proof made against a recent root can still be used after other accounts
+    were compressed or decompressed in the meantime.
+
     Accounts expected by this instruction:
     
     | # | R/W | Type    | PDA | Name           | Description                              |
@@ -74,9 +78,12 @@ pub fn process_init_storage(accounts: &[AccountInfo<'_>], data: &[u8]) -> Progra
         &code_vm_api::id()
     )?;
 
-    create_account::<StorageAccount>(
+    // The account also holds a changelog of recent tree updates, after the
+    // account state, so create_account::<StorageAccount> is too small.
+    allocate_account(
         vm_storage_info,
         &code_vm_api::ID,
+        StorageAccount::get_size_with_changelog(),
         &[
             CODE_VM, 
             VM_STORAGE_ACCOUNT,
@@ -88,11 +95,14 @@ pub fn process_init_storage(accounts: &[AccountInfo<'_>], data: &[u8]) -> Progra
         vm_authority_info,
     )?;
 
+    vm_storage_info.data.borrow_mut()[0] = StorageAccount::discriminator();
+
     let storage = vm_storage_info.to_account_mut::<StorageAccount>(&code_vm_api::ID)?;
 
     storage.vm = vm_info.key.clone();
     storage.bump = args.vm_storage_bump;
     storage.name = args.name;
+    storage.counted = 1;
     storage.depth = COMPRESSED_STATE_DEPTH as u8; // not really needed but we have a few free bytes.
 
     storage.compressed_state.init(&[
@@ -101,6 +111,7 @@ pub fn process_init_storage(accounts: &[AccountInfo<'_>], data: &[u8]) -> Progra
         vm_info.key.as_ref()
     ]);
 
+    vm.add_child()?;
     vm.advance_poh(CodeInstruction::InitStorageIx, accounts, data);
 
     Ok(())
@@ -51,6 +51,8 @@ pub fn process_init_timelock(accounts: &[AccountInfo<'_>], data: &[u8]) -> Progr
 
     let vm = load_vm_checked(vm_info, vm_authority_info)?;
 
+    check_not_paused(vm)?;
+
     check_memory(vm_memory_info, vm_info)?;
     check_is_empty(vm_memory_info, args.account_index)?;
 
@@ -65,7 +67,7 @@ pub fn process_init_timelock(accounts: &[AccountInfo<'_>], data: &[u8]) -> Progr
     );
 
     if args.virtual_timelock_bump != timelock_bump {
-        return Err(ProgramError::InvalidArgument);
+        return Err(CodeVmError::InvalidInstructionData.into());
     }
 
     let (unlock_address, unlock_bump) = pdas::find_unlock_address(
@@ -74,7 +76,7 @@ pub fn process_init_timelock(accounts: &[AccountInfo<'_>], data: &[u8]) -> Progr
         vm_info.key);
     
     if args.unlock_pda_bump != unlock_bump {
-        return Err(ProgramError::InvalidArgument);
+        return Err(CodeVmError::InvalidInstructionData.into());
     }
 
     // We could technically require the user to provide the withdraw_bump,
@@ -26,6 +26,65 @@ use solana_program::msg;
 
 */
 pub fn process_init_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let account_owner_info = accounts.first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    check_signer(account_owner_info)?;
+
+    init_unlock(accounts, data, CodeInstruction::InitUnlockIx)
+}
+
+/*
+    This instruction is the same as init_unlock, for a virtual account owned by
+    a multisig (see MultisigTransferOp). A multisig owner has no private key,
+    so instead of signing the transaction, at least `threshold` of its signers
+    sign the init_unlock message (see create_init_unlock_message).
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type        | PDA | Name           | Description                       |
+    |---|-----|-------------|-----|----------------|-----------------------------------|
+    | 0 | mut | Address     |     | account_owner  | The multisig owner address.       |
+    |...| The same as the init_unlock instruction.                                    |
+    | 6 |     | Sysvar      |     | instructions   | Optional, required when signatures|
+    |   |     |             |     |                | are verified by the Ed25519       |
+    |   |     |             |     |                | precompile.                       |
+
+
+    Instruction data:
+
+    0. multisig: MultisigOwner            - The threshold and signer set of the owner.
+    1. signatures: [MultisigSignature]    - The signatures of the init_unlock message.
+*/
+pub fn process_multisig_init_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = MultisigInitUnlockIx::try_from_slice(data)?;
+
+    let (account_owner_info, vm_info, instructions_info) = match accounts {
+        [a0, _, a2, _, _, _] => (a0, a2, None),
+        [a0, _, a2, _, _, _, a6] => (a0, a2, Some(a6)),
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+
+    let vm = load_vm(vm_info)?;
+    let hash = create_init_unlock_message(vm_info.key, account_owner_info.key);
+
+    vm_multisig_verify(
+        vm,
+        instructions_info,
+        account_owner_info.key,
+        &args.multisig,
+        &args.signatures,
+        hash.as_ref(),
+    )?;
+
+    init_unlock(&accounts[..6], data, CodeInstruction::MultisigInitUnlockIx)
+}
+
+fn init_unlock(
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+    ix: CodeInstruction,
+) -> ProgramResult {
 
     let [
         account_owner_info,
@@ -38,7 +97,6 @@ pub fn process_init_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> Program
         return Err(ProgramError::NotEnoughAccountKeys);        
     };
 
-    check_signer(account_owner_info)?;
     check_signer(payer_info)?;
     check_program(system_program_info, &system_program::id())?;
     check_sysvar(rent_sysvar_info, &sysvar::rent::id())?;
@@ -72,6 +130,7 @@ pub fn process_init_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> Program
 
     check_condition(
         unlock_pda.eq(&unlock_pda_info.key),
+        CodeVmError::AccountMismatch,
         "unlock PDA does not match the given owner",
     )?;
 
@@ -123,7 +182,15 @@ pub fn process_init_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> Program
     msg!("current time: {}", now);
     msg!("the timelock can be released after: {}", unlock_at);
 
-    vm.advance_poh(CodeInstruction::InitUnlockIx, accounts, data);
+    UnlockEvent {
+        unlock_pda: *unlock_pda_info.key,
+        owner: unlock_pda.owner,
+        address: unlock_pda.address,
+        state: unlock_pda.state,
+        unlock_at,
+    }.log();
+
+    vm.advance_poh(ix, accounts, data);
 
     Ok(())
 }
@@ -50,6 +50,7 @@ pub fn process_init_vm(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResu
 
     check_condition(
         args.lock_duration > 0, 
+        CodeVmError::InvalidInstructionData,
         "lock_duration must be greater than 0",
     )?;
 
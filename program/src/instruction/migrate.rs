@@ -0,0 +1,118 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction upgrades a legacy memory account to the current format.
+
+    Legacy memory accounts share the data layout of current ones, but were
+    created for a single virtual account type (the layout byte in packed_info).
+    Current memory accounts can hold any virtual account type in any slot. Before
+    the legacy flag is dropped, every allocated slot is checked to decode as a
+    virtual account that the legacy layout allowed. Writes to a legacy memory
+    account are held to the same layout (see try_write), so a failing slot can
+    only predate that check, and can be closed or relocated before retrying.
+
+    Large memory accounts can't be checked in one transaction, so the work is
+    done in chunks of num_accounts slots. The index of the next slot to check is
+    kept on-chain in packed_info. Once the last slot has been checked, the
+    account is switched to MemoryVersion::Current and packed_info is cleared.
+
+    Accounts expected by this instruction:
+    
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+    | 2 | mut | Memory  | PDA | vm_memory      | The legacy memory account to upgrade.    |
+
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. vm_memory: [ "code_vm", "vm_memory_account", <self.name>, <vm> ]
+
+
+    Instruction data:
+
+    0. num_accounts: u32    - The number of slots to check in this transaction.
+*/
+pub fn process_migrate(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+
+    let args = MigrateMemoryIx::try_from_bytes(data)?.to_struct()?;
+    let [
+        vm_authority_info,
+        vm_info,
+        vm_memory_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(vm_memory_info)?;
+
+    check_condition(
+        args.num_accounts > 0,
+        CodeVmError::InvalidInstructionData,
+        "num_accounts must be greater than zero",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+    let memory = load_memory(vm_memory_info, vm_info)?;
+
+    check_condition(
+        memory.get_version() == MemoryVersion::Legacy,
+        CodeVmError::InvalidMemoryLayout,
+        "the memory account is not in the legacy format",
+    )?;
+
+    let capacity = memory.get_capacity();
+    let account_size = memory.get_account_size();
+    let layout = memory.get_legacy_layout();
+
+    check_condition(
+        vm_memory_info.data_len() >= MemoryAccount::get_size_with_data(capacity, account_size),
+        CodeVmError::InvalidMemoryLayout,
+        "the memory account must be fully resized before it can be migrated",
+    )?;
+
+    let start = memory.get_migration_cursor() as usize;
+    let end = start
+        .saturating_add(args.num_accounts as usize)
+        .min(capacity);
+
+    {
+        let mem_data = MemoryAccount::get_data(vm_memory_info)?;
+        let mem = SliceAllocator::try_from_slice(&mem_data, capacity, account_size)?;
+
+        for index in start..end {
+            let Some(item) = mem.read_item(index as u16) else {
+                continue;
+            };
+
+            let va = VirtualAccount::unpack(&item)?;
+            check_condition(
+                layout.allows(&va),
+                CodeVmError::InvalidMemoryLayout,
+                "the virtual account does not match the legacy memory layout",
+            )?;
+        }
+    }
+
+    if end == capacity {
+        memory.version = MemoryVersion::Current as u8;
+        memory.packed_info = [0; 6];
+    } else {
+        memory.set_migration_cursor(end as u32);
+    }
+
+    MemoryMigrateEvent {
+        vm_memory: *vm_memory_info.key,
+        migrated: end as u32,
+        capacity: capacity as u32,
+    }.log();
+
+    vm.advance_poh(CodeInstruction::MigrateMemoryIx, accounts, data);
+
+    Ok(())
+}
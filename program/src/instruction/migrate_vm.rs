@@ -0,0 +1,82 @@
+use code_vm_api::prelude::*;
+use solana_program::system_program;
+use steel::*;
+
+/*
+    This instruction upgrades a legacy VM account to the current size.
+
+    VM accounts created before the sig verify mode, fee, authority rotation and
+    pause fields were added are smaller than CodeVmAccount, so they can't be
+    loaded by any other instruction until they are migrated. All of the new
+    fields default to zero, which keeps the legacy behaviour (in-program
+    signature verification, no fee, the original authority, not paused), so
+    the account is only grown and the new bytes are zeroed.
+
+    The fee fields (SetFeeConfigIx) were the first to grow the account, so
+    this migration is what lets VMs created before the fee was added be
+    configured with one; later fields are covered by the same migration.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The legacy VM account to upgrade.        |
+    | 2 |     | Program |     | system_program | The system program.                      |
+
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+
+
+    Instruction data:
+
+    <none>
+*/
+pub fn process_migrate_vm(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    MigrateVmIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+        system_program_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_program(system_program_info, &system_program::id())?;
+
+    vm_info.has_owner(&code_vm_api::ID)?;
+
+    let legacy_size = CodeVmAccount::get_legacy_size();
+
+    check_condition(
+        vm_info.data_len() == legacy_size,
+        CodeVmError::NotLegacyVm,
+        "the VM account is not in the legacy format",
+    )?;
+
+    if vm_info.try_borrow_data()?[0] != CodeVmAccount::discriminator() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    resize_account(
+        vm_info,
+        vm_authority_info,
+        CodeVmAccount::get_size(),
+        system_program_info,
+    )?;
+
+    vm_info.try_borrow_mut_data()?[legacy_size..].fill(0);
+
+    // The seeds and the authority can only be checked once the account has
+    // the current size.
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    vm.advance_poh(CodeInstruction::MigrateVmIx, accounts, data);
+
+    Ok(())
+}
@@ -1,7 +1,17 @@
+mod authority;
+mod close_memory;
+mod close_relay;
+mod close_storage;
+mod close_vm;
+mod compact;
 mod compress;
+mod compress_batch;
 mod decompress;
+mod decompress_batch;
 mod deposit;
 mod exec;
+mod exec_batch;
+mod fee_config;
 mod init_memory;
 mod init_nonce;
 mod init_relay;
@@ -9,15 +19,32 @@ mod init_storage;
 mod init_timelock;
 mod init_unlock;
 mod init_vm;
+mod migrate;
+mod migrate_vm;
+mod pause;
+mod relocate;
+mod require_versioned;
 mod resize;
+mod rollover;
+mod sig_verify_mode;
 mod snapshot;
 mod unlock;
 mod withdraw;
 
+pub use authority::*;
+pub use close_memory::*;
+pub use close_relay::*;
+pub use close_storage::*;
+pub use close_vm::*;
+pub use compact::*;
 pub use compress::*;
+pub use compress_batch::*;
 pub use decompress::*;
+pub use decompress_batch::*;
 pub use deposit::*;
 pub use exec::*;
+pub use exec_batch::*;
+pub use fee_config::*;
 pub use init_memory::*;
 pub use init_nonce::*;
 pub use init_relay::*;
@@ -25,7 +52,14 @@ pub use init_storage::*;
 pub use init_timelock::*;
 pub use init_unlock::*;
 pub use init_vm::*;
+pub use migrate::*;
+pub use migrate_vm::*;
+pub use pause::*;
+pub use relocate::*;
+pub use require_versioned::*;
 pub use resize::*;
+pub use rollover::*;
+pub use sig_verify_mode::*;
 pub use snapshot::*;
 pub use unlock::*;
 pub use withdraw::*;
\ No newline at end of file
@@ -0,0 +1,58 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction is an incident-response switch for the VM authority.
+
+    While the VM is paused, instructions that create or move virtual account
+    state are rejected: exec, exec_batch, compress, decompress, deposit,
+    init_nonce, init_timelock, init_relay and snapshot. Individual opcodes can
+    also be disabled, without pausing the whole VM, using a bitmask indexed by
+    opcode value.
+
+    The non-custodial exits (init_unlock, unlock and withdraw) never check
+    either flag, so users can always leave the VM.
+
+    Accounts expected by this instruction:
+    
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+
+    Instruction data:
+
+    0. paused: u8                 - 1 to pause the VM, 0 to resume it.
+    1. disabled_opcodes: [u8; 32] - A bit per opcode value, set to disable it.
+*/
+pub fn process_set_pause(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = SetPauseIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+
+    check_condition(
+        args.paused <= 1,
+        CodeVmError::InvalidInstructionData,
+        "paused must be 0 or 1",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    vm.paused = args.paused;
+    vm.disabled_opcodes = args.disabled_opcodes;
+    vm.advance_poh(CodeInstruction::SetPauseIx, accounts, data);
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction moves a virtual account from one memory slot to another,
+    atomically. The slots can be in different memory accounts, which lets the
+    VM authority spread busy accounts over several memory banks to reduce
+    write-lock contention.
+
+    Unlike a CompressIx followed by a DecompressIx, the account never leaves
+    the VM's working memory, so no signature over the account state or merkle
+    proof is needed. The account state is copied as-is.
+
+    To move an account within the same memory account, pass it as both
+    src_memory and dst_memory.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type    | PDA | Name         | Description                              |
+    |---|-----|---------|-----|--------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm           | The VM instance state account.           |
+    | 2 | mut | Memory  | PDA | src_memory   | The memory account to move from.         |
+    | 3 | mut | Memory  | PDA | dst_memory   | The memory account to move to.           |
+
+
+    Derived account seeds:
+
+    1. vm:          [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. src_memory:  [ "code_vm", "vm_memory_account", <self.name>, <vm> ]
+    3. dst_memory:  [ "code_vm", "vm_memory_account", <self.name>, <vm> ]
+
+    Instruction data:
+
+    0. src_index: u16   - The index of the account in src_memory.
+    1. dst_index: u16   - The (empty) index to move the account to in dst_memory.
+*/
+pub fn process_relocate(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = RelocateIx::try_from_bytes(data)?.to_struct()?;
+    let [
+        vm_authority_info,
+        vm_info,
+        src_memory_info,
+        dst_memory_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(src_memory_info)?;
+    check_mut(dst_memory_info)?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    check_not_paused(vm)?;
+
+    check_memory(src_memory_info, vm_info)?;
+    check_memory(dst_memory_info, vm_info)?;
+
+    let is_same_slot = src_memory_info.key.eq(dst_memory_info.key)
+        && args.src_index == args.dst_index;
+
+    check_condition(
+        !is_same_slot,
+        CodeVmError::DuplicateAccount,
+        "the source and destination must be different slots",
+    )?;
+
+    check_is_empty(dst_memory_info, args.dst_index)?;
+
+    let va = try_read(src_memory_info, args.src_index)?;
+
+    try_write(dst_memory_info, args.dst_index, &va)?;
+    try_delete(src_memory_info, args.src_index)?;
+
+    vm.advance_poh(CodeInstruction::RelocateIx, accounts, data);
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction makes the VM reject v0 signed messages.
+
+    A v0 message only covers the accounts, amount and virtual nonce, so a
+    signed intent stays valid until the nonce moves. A v1 message is wrapped in
+    an envelope that binds it to this program and VM and to a `valid_until`
+    time (see create_versioned_message). Once this flag is set, every opcode
+    that verifies an owner signature requires the opcode data to carry a
+    `valid_until`, and fails with UnversionedMessage otherwise.
+
+    Accounts expected by this instruction:
+    
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+
+    Instruction data:
+
+    0. require_versioned: u8  - 1 to require v1 messages, 0 to also accept v0.
+*/
+pub fn process_set_require_versioned(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = SetRequireVersionedIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+
+    check_condition(
+        args.require_versioned <= 1,
+        CodeVmError::InvalidInstructionData,
+        "require_versioned must be 0 or 1",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    vm.require_versioned = args.require_versioned;
+    vm.advance_poh(CodeInstruction::SetRequireVersionedIx, accounts, data);
+
+    Ok(())
+}
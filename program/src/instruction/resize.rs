@@ -50,6 +50,7 @@ pub fn process_resize(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResul
 
     check_condition(
         args.account_size as usize > MemoryAccount::get_size(),
+        CodeVmError::InvalidInstructionData,
         "account_size must be greater than the base size of a memory account",
     )?;
 
@@ -68,11 +69,13 @@ pub fn process_resize(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResul
     let max_size = MemoryAccount::get_size_with_data(capacity, account_size);
     check_condition(
         args.account_size as usize <= max_size,
+        CodeVmError::InvalidInstructionData,
         "account_size must be less than or equal to the maximum size for this type of memory account",
     )?; 
 
     check_condition(
         args.account_size as usize >= vm_memory_info.data_len(),
+        CodeVmError::InvalidInstructionData,
         "account_size must be greater than or equal to the current size of the memory account",
     )?;
 
@@ -0,0 +1,103 @@
+use code_vm_api::prelude::*;
+use solana_program::system_program;
+use steel::*;
+
+/*
+    This instruction archives the merkle tree of a storage account and starts
+    a new, empty one in its place. A tree can only hold 2^depth leaves, so a
+    storage account that is about to run out of leaves must be rolled over
+    before more accounts can be compressed into it.
+
+    Each tree is identified by a tree id. The active tree has the id stored in
+    the account, archived trees have the ids below it. Only the root of an
+    archived tree is kept, after the changelog, so accounts in it can still be
+    decompressed or withdrawn with a proof against that root. New accounts are
+    always compressed into the active tree.
+
+    The changelog only describes the active tree, so it is cleared. Storage
+    accounts created without a changelog get one here, together with the
+    first archived root. That growth fits in the 10,240 bytes an account can
+    grow by in one instruction, later rollovers only add a root each.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+    | 2 | mut | Storage | PDA | vm_storage     | The storage account to roll over.        |
+    | 3 |     | Program |     | system_program | The system program.                      |
+
+
+    Derived account seeds:
+
+    1. vm:         [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+    2. vm_storage: [ "code_vm", "vm_storage_account", <self.name>, <vm> ]
+
+
+    Instruction data:
+
+    <none>
+*/
+pub fn process_rollover_storage(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    RolloverStorageIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+        vm_storage_info,
+        system_program_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+    check_mut(vm_storage_info)?;
+    check_program(system_program_info, &system_program::id())?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+    let storage = load_storage(vm_storage_info, vm_info)?;
+
+    check_condition(
+        storage.compressed_state.get_next_index() > 0,
+        CodeVmError::InvalidInstructionData,
+        "the active storage tree is empty",
+    )?;
+
+    check_condition(
+        storage.tree_id < u16::MAX,
+        CodeVmError::MerkleTreeFull,
+        "the storage account has no tree ids left",
+    )?;
+
+    let tree_id = storage.tree_id;
+    let root = storage.compressed_state.get_root();
+
+    resize_account(
+        vm_storage_info,
+        vm_authority_info,
+        StorageAccount::get_size_with_archived_trees(tree_id as usize + 1),
+        system_program_info,
+    )?;
+
+    let storage = vm_storage_info.to_account_mut::<StorageAccount>(&code_vm_api::ID)?;
+    let name = storage.name;
+
+    storage.tree_id = tree_id + 1;
+    storage.compressed_state.init(&[
+        MERKLE_TREE_SEED,
+        name.as_ref(),
+        vm_info.key.as_ref()
+    ]);
+
+    StorageAccount::get_archived_roots_mut(vm_storage_info)?[tree_id as usize] = root;
+
+    if let Some(mut changelog) = StorageAccount::get_changelog_mut(vm_storage_info)? {
+        *changelog = StorageChangeLog::zeroed();
+    }
+
+    vm.advance_poh(CodeInstruction::RolloverStorageIx, accounts, data);
+
+    Ok(())
+}
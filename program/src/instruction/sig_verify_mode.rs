@@ -0,0 +1,56 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+/*
+    This instruction selects how owner signatures are verified by the VM.
+
+    SigVerifyMode::Program verifies ed25519 signatures in-program using the
+    curve25519 syscalls. This is the default.
+
+    SigVerifyMode::Precompile expects every signature to have been verified by
+    an Ed25519 program instruction earlier in the same transaction. The exec
+    and compress instructions then need the instructions sysvar, and only
+    check (through introspection) that the exact (pubkey, signature, message)
+    triple was included. This is much cheaper in compute units.
+
+    Accounts expected by this instruction:
+    
+    | # | R/W | Type    | PDA | Name           | Description                              |
+    |---|-----|---------|-----|----------------|------------------------------------------|
+    | 0 | mut | Signer  |     | vm_authority   | The authority of the VM.                 |
+    | 1 | mut | Vm      | PDA | vm             | The VM instance state account.           |
+
+    Derived account seeds:
+
+    1. vm:        [ "code_vm", <mint>, <vm_authority>, <lock_duration> ]
+
+    Instruction data:
+
+    0. mode: u8    - The SigVerifyMode to use.
+*/
+pub fn process_set_sig_verify_mode(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = SetSigVerifyModeIx::try_from_bytes(data)?;
+
+    let [
+        vm_authority_info,
+        vm_info,
+    ] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);        
+    };
+
+    check_signer(vm_authority_info)?;
+    check_mut(vm_info)?;
+
+    check_condition(
+        SigVerifyMode::try_from(args.mode).is_ok(),
+        CodeVmError::InvalidInstructionData,
+        "invalid signature verification mode",
+    )?;
+
+    let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    vm.sig_verify_mode = args.mode;
+    vm.advance_poh(CodeInstruction::SetSigVerifyModeIx, accounts, data);
+
+    Ok(())
+}
@@ -43,6 +43,9 @@ pub fn process_snapshot(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramRes
     relay.save_recent_root();
 
     let vm = load_vm_checked(vm_info, vm_authority_info)?;
+
+    check_not_paused(vm)?;
+
     vm.advance_poh(CodeInstruction::SnapshotIx, accounts, data);
 
     Ok(())
@@ -24,6 +24,64 @@ use solana_program::msg;
 
 */
 pub fn process_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let account_owner_info = accounts.first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    check_signer(account_owner_info)?;
+
+    unlock(accounts, data, CodeInstruction::UnlockIx)
+}
+
+/*
+    This instruction is the same as unlock, for a virtual account owned by a
+    multisig. At least `threshold` of its signers sign the unlock message (see
+    create_unlock_message) instead of the owner signing the transaction.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Type        | PDA | Name           | Description                       |
+    |---|-----|-------------|-----|----------------|-----------------------------------|
+    | 0 | mut | Address     |     | account_owner  | The multisig owner address.       |
+    |...| The same as the unlock instruction.                                         |
+    | 4 |     | Sysvar      |     | instructions   | Optional, required when signatures|
+    |   |     |             |     |                | are verified by the Ed25519       |
+    |   |     |             |     |                | precompile.                       |
+
+
+    Instruction data:
+
+    0. multisig: MultisigOwner            - The threshold and signer set of the owner.
+    1. signatures: [MultisigSignature]    - The signatures of the unlock message.
+*/
+pub fn process_multisig_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = MultisigUnlockIx::try_from_slice(data)?;
+
+    let (account_owner_info, vm_info, instructions_info) = match accounts {
+        [a0, _, a2, _] => (a0, a2, None),
+        [a0, _, a2, _, a4] => (a0, a2, Some(a4)),
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+
+    let vm = load_vm(vm_info)?;
+    let hash = create_unlock_message(vm_info.key, account_owner_info.key);
+
+    vm_multisig_verify(
+        vm,
+        instructions_info,
+        account_owner_info.key,
+        &args.multisig,
+        &args.signatures,
+        hash.as_ref(),
+    )?;
+
+    unlock(&accounts[..4], data, CodeInstruction::MultisigUnlockIx)
+}
+
+fn unlock(
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+    ix: CodeInstruction,
+) -> ProgramResult {
 
     let [
         account_owner_info,
@@ -34,7 +92,6 @@ pub fn process_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResul
         return Err(ProgramError::NotEnoughAccountKeys);        
     };
 
-    check_signer(account_owner_info)?;
     check_signer(payer_info)?;
 
     let vm = vm_info.to_account_mut::<CodeVmAccount>(&code_vm_api::ID)?;
@@ -68,6 +125,7 @@ pub fn process_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResul
 
     check_condition(
         unlock_pda.state == TimelockState::WaitingForTimeout as u8,
+        CodeVmError::InvalidUnlockState,
         "invalid unlock state"
     )?;
 
@@ -78,12 +136,21 @@ pub fn process_unlock(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResul
     
     check_condition(
         unlock_pda.unlock_at < now,
+        CodeVmError::TooEarly,
         "unlock time has not passed yet"
     )?;
 
     unlock_pda.state = TimelockState::Unlocked as u8;
 
-    vm.advance_poh(CodeInstruction::UnlockIx, accounts, data);
+    UnlockEvent {
+        unlock_pda: *unlock_pda_info.key,
+        owner: unlock_pda.owner,
+        address: unlock_pda.address,
+        state: unlock_pda.state,
+        unlock_at: unlock_pda.unlock_at,
+    }.log();
+
+    vm.advance_poh(ix, accounts, data);
 
     Ok(())
 }
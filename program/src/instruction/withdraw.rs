@@ -34,6 +34,73 @@ pub fn process_withdraw(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramRes
     let ctx = WithdrawContext::try_from(accounts)?;
 
     check_signer(ctx.depositor_info)?;
+
+    withdraw(&ctx, &args, accounts, data, CodeInstruction::WithdrawIx)
+}
+
+/*
+    This instruction is the same as withdraw, for a virtual account or deposit
+    owned by a multisig. At least `threshold` of its signers sign the withdraw
+    message (see create_withdraw_unlocked_message) instead of the owner signing
+    the transaction.
+
+    The message commits to the external address, so the same signatures can be
+    used for each of the owner's accounts and deposits, but only to send the
+    tokens to that address.
+
+    Accounts expected by this instruction:
+
+    | # | R/W | Req | PDA | Type         | Name             | Description                            |
+    |---|-----|-----|-----|------------  |------------------|----------------------------------------|
+    | 0 | mut | Yes |     | Address      | depositor        | The multisig owner address.            |
+    |...| The same as the withdraw instruction.                                                         |
+    |14 |     |     |     | Sysvar       | instructions     | Optional, required when signatures are |
+    |   |     |     |     |              |                  | verified by the Ed25519 precompile.    |
+
+
+    Instruction data:
+
+    0. multisig: MultisigOwner            - The threshold and signer set of the owner.
+    1. signatures: [MultisigSignature]    - The signatures of the withdraw message.
+    2. withdraw: WithdrawIxData           - The same as the withdraw instruction.
+*/
+pub fn process_multisig_withdraw(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let args = MultisigWithdrawIx::try_from_slice(data)?;
+
+    let (accounts, instructions_info) = match accounts.len() {
+        14 => (accounts, None),
+        15 => (&accounts[..14], Some(&accounts[14])),
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+
+    let ctx = WithdrawContext::try_from(accounts)?;
+    let vm = load_vm(ctx.vm_info)?;
+
+    let hash = create_withdraw_unlocked_message(
+        ctx.vm_info.key,
+        ctx.depositor_info.key,
+        ctx.external_address_info.key,
+    );
+
+    vm_multisig_verify(
+        vm,
+        instructions_info,
+        ctx.depositor_info.key,
+        &args.multisig,
+        &args.signatures,
+        hash.as_ref(),
+    )?;
+
+    withdraw(&ctx, &args.withdraw, accounts, data, CodeInstruction::MultisigWithdrawIx)
+}
+
+fn withdraw(
+    ctx: &WithdrawContext,
+    args: &WithdrawIxData,
+    accounts: &[AccountInfo<'_>],
+    data: &[u8],
+    ix: CodeInstruction,
+) -> ProgramResult {
     check_signer(ctx.payer_info)?;
     check_mut(ctx.vm_info)?;
     check_mut(ctx.external_address_info)?;
@@ -71,19 +138,19 @@ pub fn process_withdraw(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramRes
     match args {
 
         WithdrawIxData::FromDeposit { .. } => 
-            process_withdraw_from_deposit(&ctx, &args),
+            process_withdraw_from_deposit(ctx, args),
 
         WithdrawIxData::FromMemory { .. } => 
-            process_withdraw_from_memory(&ctx, &args),
+            process_withdraw_from_memory(ctx, args),
 
         WithdrawIxData::FromStorage { .. } => 
-            process_withdraw_from_storage(&ctx, &args),
+            process_withdraw_from_storage(ctx, args),
 
     }?;
 
     let vm = load_vm(ctx.vm_info)?;
 
-    vm.advance_poh(CodeInstruction::WithdrawIx, accounts, data);
+    vm.advance_poh(ix, accounts, data);
 
     Ok(())
 }
@@ -94,33 +161,37 @@ fn process_withdraw_from_memory(
 ) -> ProgramResult {
     let account_index = match data {
         WithdrawIxData::FromMemory { account_index } => Ok(*account_index),
-        _ => Err(ProgramError::InvalidInstructionData),
+        _ => Err(CodeVmError::InvalidInstructionData),
     }?;
 
     check_condition(
         ctx.vm_memory_info.is_some(),
+        CodeVmError::MissingAccount,
         "vm_memory account is required for memory withdraw",
     )?;
 
     check_condition(
         ctx.vm_omnibus.is_some(),
+        CodeVmError::MissingAccount,
         "vm_omnibus account is required for memory withdraw",
     )?;
 
     let vm_info = ctx.vm_info;
     let vm = load_vm(vm_info)?;
 
-    let vm_omnibus = ctx.vm_omnibus.unwrap();
-    let vm_memory_info = ctx.vm_memory_info.unwrap();
+    let vm_omnibus = ctx.vm_omnibus.ok_or(CodeVmError::MissingAccount)?;
+    let vm_memory_info = ctx.vm_memory_info.ok_or(CodeVmError::MissingAccount)?;
     let va = try_read(vm_memory_info, account_index)?;
-    let vta = va.into_inner_timelock().unwrap();
+    let vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
 
     check_condition(
         vta.owner.eq(ctx.depositor_info.key),
+        CodeVmError::InvalidOwner,
         "depositor does not match the owner of the timelock account",
     )?;
 
-    transfer_signed(
+    transfer_signed_with_event(
         vm_omnibus,
         vm_omnibus,
         ctx.external_address_info,
@@ -145,42 +216,55 @@ fn process_withdraw_from_storage(
     ctx: &WithdrawContext,
     data: &WithdrawIxData,
 ) -> ProgramResult {
-    let (packed_va, proof, signature) = match data {
+    let (packed_va, tree_id, leaf_index, proof, signature) = match data {
         WithdrawIxData::FromStorage {
             packed_va,
+            tree_id,
+            leaf_index,
             proof,
             signature,
-        } => Ok((packed_va, proof, signature)),
-        _ => Err(ProgramError::InvalidInstructionData),
+        } => Ok((packed_va, tree_id, leaf_index, proof, signature)),
+        _ => Err(CodeVmError::InvalidInstructionData),
     }?;
 
     let vm_info = ctx.vm_info;
     let vm = load_vm(vm_info)?;
 
     let va = VirtualAccount::unpack(packed_va)?;
-    let vta = va.into_inner_timelock().unwrap();
+    let vta = va.into_inner_timelock()
+        .ok_or(CodeVmError::WrongAccountVariant)?;
 
     let va_hash = va.get_hash();
-    let sig_hash = hashv(&[signature.as_ref(), va_hash.as_ref()]);
 
-    sig_verify(vm.authority.as_ref(), signature.as_ref(), va_hash.as_ref())?;
+    // Proving that this leaf is in storage is what authenticates the account
+    // (see decompress). The authority that signed it may have been rotated.
 
     check_condition(
         ctx.vm_omnibus.is_some(),
+        CodeVmError::MissingAccount,
         "vm_omnibus account is required for storage withdraw",
     )?;
 
     check_condition(
         ctx.vm_storage_info.is_some(),
+        CodeVmError::MissingAccount,
         "vm_storage account is required for storage withdraw",
     )?;
 
-    let vm_omnibus = ctx.vm_omnibus.unwrap();
-    let vm_storage_info = ctx.vm_storage_info.unwrap();
+    let vm_omnibus = ctx.vm_omnibus.ok_or(CodeVmError::MissingAccount)?;
+    let vm_storage_info = ctx.vm_storage_info.ok_or(CodeVmError::MissingAccount)?;
+
+    let leaf = try_decompress(vm_storage_info, *tree_id, signature, &va_hash, *leaf_index, proof)?;
 
-    try_decompress(vm_storage_info, sig_hash, proof)?;
+    DecompressEvent {
+        vm: *vm_info.key,
+        vm_storage: *vm_storage_info.key,
+        tree_id: *tree_id,
+        leaf_index: *leaf_index,
+        leaf,
+    }.log();
 
-    transfer_signed(
+    transfer_signed_with_event(
         vm_omnibus,
         vm_omnibus,
         ctx.external_address_info,
@@ -205,24 +289,26 @@ fn process_withdraw_from_deposit(
 ) -> ProgramResult {
     let bump = match data {
         WithdrawIxData::FromDeposit { bump } => Ok(*bump),
-        _ => Err(ProgramError::InvalidInstructionData),
+        _ => Err(CodeVmError::InvalidInstructionData),
     }?;
 
     check_condition(
         ctx.deposit_pda_info.is_some(),
+        CodeVmError::MissingAccount,
         "deposit_pda account is required for deposit withdraw",
     )?;
 
     check_condition(
         ctx.deposit_ata_info.is_some(),
+        CodeVmError::MissingAccount,
         "deposit_ata account is required for deposit withdraw",
     )?;
 
-    let deposit_ata_info = ctx.deposit_ata_info.unwrap();
-    let deposit_pda_info = ctx.deposit_pda_info.unwrap();
+    let deposit_ata_info = ctx.deposit_ata_info.ok_or(CodeVmError::MissingAccount)?;
+    let deposit_pda_info = ctx.deposit_pda_info.ok_or(CodeVmError::MissingAccount)?;
     let token_account = deposit_ata_info.to_token_account()?;
 
-    transfer_signed(
+    transfer_signed_with_event(
         deposit_pda_info,
         deposit_ata_info,
         ctx.external_address_info,
@@ -331,6 +417,7 @@ impl<'a, 'b> WithdrawContext<'a, 'b> {
 
         check_condition(
             self.unlock_pda_info.key.eq(&unlock_address),
+            CodeVmError::AccountMismatch,
             "unlock_pda does not match the expected unlock address",
         )?;
 
@@ -340,16 +427,19 @@ impl<'a, 'b> WithdrawContext<'a, 'b> {
 
         check_condition(
             unlock_state.is_unlocked(),
+            CodeVmError::InvalidUnlockState,
             "unlock_pda is not in the unlocked state",
         )?;
 
         check_condition(
             unlock_state.owner.eq(&owner),
+            CodeVmError::AccountMismatch,
             "unlock_pda owner does not match the expected owner",
         )?;
 
         check_condition(
             unlock_state.vm.eq(&self.vm_info.key),
+            CodeVmError::AccountMismatch,
             "unlock_pda vm does not match the expected vm",
         )?;
 
@@ -362,7 +452,8 @@ impl<'a, 'b> WithdrawContext<'a, 'b> {
 
         let vm_info = self.vm_info;
         let unlock_pda_info = self.unlock_pda_info;
-        let withdraw_receipt_info = self.withdraw_receipt_info.unwrap();
+        let withdraw_receipt_info = self.withdraw_receipt_info
+            .ok_or(CodeVmError::MissingAccount)?;
 
         let (receipt_address, bump) =
             find_withdraw_receipt_address(&unlock_pda_info.key, nonce, vm_info.key);
@@ -371,6 +462,7 @@ impl<'a, 'b> WithdrawContext<'a, 'b> {
 
         check_condition(
             withdraw_receipt_info.key.eq(&receipt_address),
+            CodeVmError::AccountMismatch,
             "withdraw_receipt does not match the expected receipt address",
         )?;
 
@@ -385,7 +477,7 @@ impl<'a, 'b> WithdrawContext<'a, 'b> {
                 vm_info.key.as_ref(),
                 &[bump],
             ],
-            self.system_program_info.unwrap(),
+            self.system_program_info.ok_or(CodeVmError::MissingAccount)?,
             self.payer_info,
         )?;
 
@@ -17,7 +17,7 @@ pub fn process_instruction(
     let (ix, data) = parse_instruction(&code_vm_api::ID, program_id, data)?;
 
     match ix {
-        CodeInstruction::Unknown => return Err(ProgramError::InvalidInstructionData),
+        CodeInstruction::Unknown => return Err(CodeVmError::InvalidInstructionData.into()),
 
         CodeInstruction::InitVmIx        => process_init_vm(accounts, data)?,
         CodeInstruction::InitMemoryIx    => process_init_memory(accounts, data)?,
@@ -36,6 +36,34 @@ pub fn process_instruction(
         CodeInstruction::DepositIx       => process_deposit(accounts, data)?,
         CodeInstruction::WithdrawIx      => process_withdraw(accounts, data)?,
         CodeInstruction::UnlockIx        => process_unlock(accounts, data)?,
+
+        CodeInstruction::ExecBatchIx     => process_exec_batch(accounts, data)?,
+        CodeInstruction::SetSigVerifyModeIx => process_set_sig_verify_mode(accounts, data)?,
+        CodeInstruction::SetFeeConfigIx => process_set_fee_config(accounts, data)?,
+        CodeInstruction::ProposeAuthorityIx => process_propose_authority(accounts, data)?,
+        CodeInstruction::AcceptAuthorityIx => process_accept_authority(accounts, data)?,
+        CodeInstruction::SetPauseIx => process_set_pause(accounts, data)?,
+
+        CodeInstruction::CloseMemoryIx => process_close_memory(accounts, data)?,
+        CodeInstruction::CloseStorageIx => process_close_storage(accounts, data)?,
+        CodeInstruction::CloseRelayIx => process_close_relay(accounts, data)?,
+        CodeInstruction::CloseVmIx => process_close_vm(accounts, data)?,
+
+        CodeInstruction::CompactMemoryIx => process_compact(accounts, data)?,
+        CodeInstruction::MigrateMemoryIx => process_migrate(accounts, data)?,
+        CodeInstruction::RelocateIx => process_relocate(accounts, data)?,
+
+        CodeInstruction::CompressBatchIx => process_compress_batch(accounts, data)?,
+        CodeInstruction::DecompressBatchIx => process_decompress_batch(accounts, data)?,
+
+        CodeInstruction::RolloverStorageIx => process_rollover_storage(accounts, data)?,
+
+        CodeInstruction::MultisigInitUnlockIx => process_multisig_init_unlock(accounts, data)?,
+        CodeInstruction::MultisigUnlockIx => process_multisig_unlock(accounts, data)?,
+        CodeInstruction::MultisigWithdrawIx => process_multisig_withdraw(accounts, data)?,
+
+        CodeInstruction::MigrateVmIx => process_migrate_vm(accounts, data)?,
+        CodeInstruction::SetRequireVersionedIx => process_set_require_versioned(accounts, data)?,
     }
 
     Ok(())
@@ -0,0 +1,44 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used to create a spending allowance on a virtual
+    timelock account. The signature of the source account is required to
+    authorize the allowance.
+
+    The delegate can then move up to `amount` tokens out of the source account,
+    until `expires_at`, without further signatures from the owner (see
+    transfer_from). No tokens are reserved; the allowance is only a cap.
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    0. signature: [u8;64]  - The signature of the source account owner.
+    1. amount: [u64]       - The maximum amount the delegate can spend.
+    2. expires_at: [i64]   - The unix timestamp after which the allowance can't be used.
+    3. delegate: [u8;32]   - The key allowed to spend from the source account.
+    4. valid_until: [i64] - Optional. If present, the signature is over a v1 (versioned) message.
+*/
+pub fn process_approve(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    let vm = load_vm(ctx.vm_info)?;
+
+    exec::approve(ctx, vm, data)
+}
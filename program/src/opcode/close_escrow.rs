@@ -0,0 +1,37 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used to close an empty virtual escrow account and free
+    its memory slot. Funded escrows can only be settled with escrow_claim or
+    escrow_refund.
+
+    No additional signature is needed; the vm_exec instruction is already
+    signed by the VM authority.
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    (none)
+*/
+pub fn process_close_escrow(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    exec::close_escrow(ctx, data)
+}
@@ -0,0 +1,37 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used to close a virtual durable nonce account and free
+    its memory slot. Nonce accounts are created by the VM authority, so they
+    are closed by it as well.
+
+    No additional signature is needed; the vm_exec instruction is already
+    signed by the VM authority.
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    (none)
+*/
+pub fn process_close_nonce(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    exec::close_nonce(ctx, data)
+}
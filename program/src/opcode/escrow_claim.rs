@@ -0,0 +1,37 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used to release a hash-time-locked escrow to its
+    recipient. Knowledge of the preimage is the authorization, no signature is
+    required. The funds can only go to a virtual timelock account owned by the
+    escrow recipient, and only before `refund_after` has passed.
+
+    The escrow account is deleted once claimed.
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    0. preimage: [u8;32]   - The value that hashes (sha256) to the hashlock.
+*/
+pub fn process_escrow_claim(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    exec::escrow_claim(ctx, data)
+}
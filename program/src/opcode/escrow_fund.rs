@@ -0,0 +1,45 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used to move tokens from a virtual timelock account into
+    a new hash-time-locked escrow account. The signature of the source account
+    is required to authorize the funding.
+
+    The escrow can later be claimed by the recipient with the preimage of the
+    hashlock (see escrow_claim), or returned to the sender once `refund_after`
+    has passed (see escrow_refund).
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    0. signature: [u8;64]  - The signature of the source account owner.
+    1. amount: [u64]       - The amount to lock in the escrow.
+    2. refund_after: [i64] - The unix timestamp after which a refund is possible.
+    3. recipient: [u8;32]  - The owner that can claim the escrow.
+    4. hashlock: [u8;32]   - The sha256 hash of the claim preimage.
+    5. valid_until: [i64] - Optional. If present, the signature is over a v1 (versioned) message.
+*/
+pub fn process_escrow_fund(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    let vm = load_vm(ctx.vm_info)?;
+
+    exec::escrow_fund(ctx, vm, data)
+}
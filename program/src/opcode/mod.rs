@@ -1,17 +1,51 @@
 mod airdrop;
+mod approve;
+mod close_allowance;
+mod close_escrow;
+mod close_nonce;
+mod close_relay;
+mod close_stream;
+mod close_timelock;
 mod conditional_transfer;
+mod escrow_claim;
+mod escrow_fund;
+mod escrow_refund;
 mod external_relay;
 mod external_transfer;
 mod external_withdraw;
+mod multisig_transfer;
+mod multisig_withdraw;
+mod payout;
 mod relay;
+mod stream_cancel;
+mod stream_create;
+mod stream_withdraw;
 mod transfer;
+mod transfer_from;
 mod withdraw;
 
 pub use airdrop::*;
+pub use approve::*;
+pub use close_allowance::*;
+pub use close_escrow::*;
+pub use close_nonce::*;
+pub use close_relay::*;
+pub use close_stream::*;
+pub use close_timelock::*;
 pub use conditional_transfer::*;
+pub use escrow_claim::*;
+pub use escrow_fund::*;
+pub use escrow_refund::*;
 pub use external_relay::*;
 pub use external_transfer::*;
 pub use external_withdraw::*;
+pub use multisig_transfer::*;
+pub use multisig_withdraw::*;
+pub use payout::*;
 pub use relay::*;
+pub use stream_cancel::*;
+pub use stream_create::*;
+pub use stream_withdraw::*;
 pub use transfer::*;
+pub use transfer_from::*;
 pub use withdraw::*;
\ No newline at end of file
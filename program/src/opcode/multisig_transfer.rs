@@ -0,0 +1,43 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used to transfer tokens from one virtual account to
+    another virtual account, where the source account is held by an m-of-n
+    multisig. At least `threshold` of the committed signers must sign the same
+    message as a regular transfer.
+
+    The source account owner must be the address of the provided multisig (see
+    MultisigOwner::get_address).
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    0. multisig: MultisigOwner         - The threshold and signer keys.
+    1. signatures: [MultisigSignature] - The signatures, by signer index.
+    2. amount: [u64]                   - The amount to transfer.
+    3. valid_until: [i64] - Optional. If present, the signature is over a v1 (versioned) message.
+*/
+pub fn process_multisig_transfer(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    let vm = load_vm(ctx.vm_info)?;
+
+    exec::multisig_transfer(ctx, vm, data)
+}
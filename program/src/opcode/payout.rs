@@ -0,0 +1,47 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used to transfer tokens from *one* virtual account to a
+    number of virtual accounts, where each destination receives its own amount.
+    The signature of the source account is required to authorize the payout.
+
+    Unlike the airdrop opcode, the amounts are part of the signed message, so
+    the source owner commits to exactly who gets what.
+
+    If the VM charges an operator fee (see SetFeeConfigIx), the fee is also
+    deducted from the source account and credited to the fee-collector virtual
+    account, passed as the last memory index. The signed message must then
+    cover the fee (see create_fee_message).
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    0. signature: [u8;64]  - The signature of the source account owner.
+    1. amounts: [u64]      - The amount for each destination, in the same order
+                             as the destination mem_indicies.
+    2. valid_until: [i64] - Optional. If present, the signature is over a v1 (versioned) message.
+*/
+pub fn process_payout(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    let vm = load_vm(ctx.vm_info)?;
+
+    exec::payout(ctx, vm, data)
+}
@@ -0,0 +1,45 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used to move tokens from a virtual timelock account into
+    a new stream account, which releases them linearly to a beneficiary between
+    `start_time` and `end_time`. The signature of the source account is required
+    to authorize the stream.
+
+    The beneficiary collects vested tokens with stream_withdraw, and the funder
+    can stop the stream at any time with stream_cancel.
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    0. signature: [u8;64]  - The signature of the source account owner.
+    1. amount: [u64]       - The total amount to stream.
+    2. start_time: [i64]   - The unix timestamp at which vesting starts.
+    3. end_time: [i64]     - The unix timestamp at which everything has vested.
+    4. beneficiary: [u8;32]- The owner that receives the vested tokens.
+    5. valid_until: [i64] - Optional. If present, the signature is over a v1 (versioned) message.
+*/
+pub fn process_stream_create(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    let vm = load_vm(ctx.vm_info)?;
+
+    exec::stream_create(ctx, vm, data)
+}
@@ -0,0 +1,47 @@
+use code_vm_api::prelude::*;
+use steel::*;
+
+use crate::ExecContext;
+
+/*
+    This instruction is used by a delegate to transfer tokens out of a virtual
+    account, within the limits of an allowance created by the owner (see
+    approve). The signature of the delegate is required to authorize the
+    transfer.
+
+    The allowance is decremented by the transferred amount, and deleted once
+    it has been fully spent.
+
+    If the VM charges an operator fee (see SetFeeConfigIx), the fee is also
+    deducted from the source account and from the allowance, and credited to
+    the fee-collector virtual account, passed as the last memory index. The
+    delegate's signed message must then cover the fee (see create_fee_message).
+
+    Extra accounts required by this instruction:
+    
+    | # | R/W | Type         | Req | PDA | Name   | Description  |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    |...| The same as the vm_exec instruction.                   |
+    |---|-----|------------- |-----|-----|--------|--------------|
+    | 6 |     | <None>       |     |     |        |              |
+    | 7 |     | <None>       |     |     |        |              |
+    | 8 |     | <None>       |     |     |        |              |
+    | 9 |     | <None>       |     |     |        |              |
+    |10 |     | <None>       |     |     |        |              |
+
+
+    Instruction data:
+
+    0. signature: [u8;64]  - The signature of the delegate.
+    1. amount: [u64]       - The amount to transfer.
+    2. valid_until: [i64] - Optional. If present, the signature is over a v1 (versioned) message.
+*/
+pub fn process_transfer_from(
+    ctx: &mut ExecContext,
+    data: &ExecIxData,
+) -> ProgramResult {
+
+    let vm = load_vm(ctx.vm_info)?;
+
+    exec::transfer_from(ctx, vm, data)
+}
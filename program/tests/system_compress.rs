@@ -2,7 +2,7 @@
 pub mod utils;
 use utils::*;
 
-use code_vm_api::{prelude::*, utils::hashv};
+use code_vm_api::{prelude::*, utils::get_storage_leaf_value};
 use solana_sdk::signer::Signer;
 
 #[test]
@@ -28,7 +28,7 @@ fn run_system_account_compress() {
     let va_hash = va.get_hash();
 
     let sig = Signature::new(payer.sign_message(va_hash.as_ref()).as_ref());
-    let sig_hash = hashv(&[sig.as_ref(), va_hash.as_ref()]);
+    let sig_hash = get_storage_leaf_value(&sig, &va_hash, 0);
 
     assert!(tx_account_compress(
         &mut svm, 
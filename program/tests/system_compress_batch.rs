@@ -0,0 +1,151 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::{prelude::*, utils::{get_compress_batch_message, get_storage_leaf_value}};
+use litesvm::LiteSVM;
+use solana_sdk::{signer::Signer, transaction::Transaction};
+
+#[test]
+fn run_system_account_compress_batch() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN+1;
+
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, capacity, account_size, name);
+
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    let account_indices: Vec<u16> = vec![3, 0, 7];
+    for account_index in account_indices.iter() {
+        let owner = create_keypair().pubkey();
+        assert!(tx_create_virtual_nonce(&mut svm, &payer, vm_address, vm_mem_address, owner, *account_index).is_ok());
+    }
+
+    let vas: Vec<VirtualAccount> = account_indices.iter()
+        .map(|index| get_virtual_account(&svm, vm_mem_address, *index))
+        .collect();
+    let va_hashes: Vec<Hash> = vas.iter().map(|va| va.get_hash()).collect();
+
+    let message = get_compress_batch_message(&va_hashes);
+    let sig = Signature::new(payer.sign_message(message.as_ref()).as_ref());
+
+    // A repeated index is rejected, even when signed
+    let repeated = get_compress_batch_message(&[va_hashes[0], va_hashes[0]]);
+    let repeated_sig = Signature::new(payer.sign_message(repeated.as_ref()).as_ref());
+    let ix = system_account_compress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, vec![3, 3], repeated_sig);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    assert!(send_tx(&mut svm, tx).is_err());
+
+    // The signature must cover the accounts in the same order
+    let ix = system_account_compress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, vec![0, 3, 7], sig);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    assert!(send_tx(&mut svm, tx).is_err());
+
+    let ix = system_account_compress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, account_indices.clone(), sig);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    assert!(send_tx(&mut svm, tx).is_ok());
+
+    for account_index in account_indices.iter() {
+        assert!(get_virtual_account_data(&svm, vm_mem_address, *account_index).is_none());
+    }
+
+    // Each account gets the same leaf as a single compress would have used
+    let mut expected = MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::new(&[
+        MERKLE_TREE_SEED,
+        create_name(name).as_ref(),
+        vm_address.as_ref()
+    ]);
+    let leaves: Vec<Hash> = va_hashes.iter()
+        .enumerate()
+        .map(|(i, va_hash)| get_storage_leaf_value(&sig, va_hash, i as u64))
+        .collect();
+    for leaf in leaves.iter() {
+        assert!(expected.try_insert(*leaf).is_ok());
+    }
+
+    let compressed_mem = get_storage_account(&svm, vm_storage_address).compressed_state;
+    assert_eq!(expected.get_root(), compressed_mem.get_root());
+
+    // Any of them can be decompressed with the batch signature
+    let layer: Vec<Hash> = leaves.iter()
+        .map(|leaf| MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::as_leaf(*leaf))
+        .collect();
+    let proof = expected.get_merkle_proof(&layer, 1);
+    assert!(tx_account_decompress(
+        &mut svm,
+        &payer,
+        vm_address,
+        vm_mem_address,
+        vm_storage_address,
+        None,
+        None,
+        42,
+        vas[1].pack(),
+        0,
+        1,
+        proof,
+        sig
+    ).is_ok());
+
+    let va = get_virtual_account(&svm, vm_mem_address, 42);
+    assert_eq!(va.get_hash(), va_hashes[1]);
+}
+
+#[test]
+fn run_system_account_compress_batch_too_large() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN+1;
+
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, capacity, account_size, name);
+
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    let account_indices: Vec<u16> = (0..MAX_COMPRESS_BATCH_SIZE as u16 + 1).collect();
+    for account_index in account_indices.iter() {
+        let owner = create_keypair().pubkey();
+        assert!(tx_create_virtual_nonce(&mut svm, &payer, vm_address, vm_mem_address, owner, *account_index).is_ok());
+    }
+
+    let sign = |svm: &LiteSVM, indices: &[u16]| {
+        let va_hashes: Vec<Hash> = indices.iter()
+            .map(|index| get_virtual_account(svm, vm_mem_address, *index).get_hash())
+            .collect();
+        let message = get_compress_batch_message(&va_hashes);
+        Signature::new(payer.sign_message(message.as_ref()).as_ref())
+    };
+
+    // One account over the limit is rejected, even when signed
+    let sig = sign(&svm, &account_indices);
+    let ix = system_account_compress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, account_indices.clone(), sig);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    assert!(send_tx(&mut svm, tx).is_err());
+
+    // A full batch logs an event for every account
+    let account_indices = account_indices[..MAX_COMPRESS_BATCH_SIZE].to_vec();
+    let sig = sign(&svm, &account_indices);
+    let ix = system_account_compress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, account_indices, sig);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    let meta = send_tx(&mut svm, tx).unwrap();
+
+    let events = get_program_data(&meta).iter()
+        .filter(|data| matches!(ParsedEvent::try_from_bytes(data), Ok(ParsedEvent::Compress(_))))
+        .count();
+    assert_eq!(events, MAX_COMPRESS_BATCH_SIZE);
+}
@@ -2,7 +2,8 @@
 pub mod utils;
 use utils::*;
 
-use code_vm_api::{prelude::*, utils::hashv};
+use code_vm_api::{prelude::*, utils::get_storage_leaf_value};
+use litesvm::LiteSVM;
 use solana_sdk::signer::Signer;
 
 #[test]
@@ -28,7 +29,7 @@ fn run_system_account_decompress() {
     let va_hash = va.get_hash();
 
     let sig = Signature::new(payer.sign_message(va_hash.as_ref()).as_ref());
-    let sig_hash = hashv(&[sig.as_ref(), va_hash.as_ref()]);
+    let sig_hash = get_storage_leaf_value(&sig, &va_hash, 0);
     
     assert!(tx_account_compress(
         &mut svm, 
@@ -66,6 +67,8 @@ fn run_system_account_decompress() {
         None,
         account_index,
         packed_va,
+        0,
+        0,
         proof.clone(),
         sig
     ).is_ok());
@@ -83,4 +86,195 @@ fn run_system_account_decompress() {
 
     let va = get_virtual_account(&svm, vm_mem_address, account_index);
     assert!(va.is_nonce());
-}
\ No newline at end of file
+}
+#[test]
+fn run_system_account_decompress_with_stale_proof() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN+1;
+
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, capacity, account_size, name);
+
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    let mut expected = MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::new(&[
+        MERKLE_TREE_SEED,
+        create_name(name).as_ref(),
+        vm_address.as_ref()
+    ]);
+
+    let compress = |svm: &mut LiteSVM, expected: &mut MerkleTree<{StorageAccount::MERKLE_TREE_DEPTH}>, account_index: u16| {
+        let owner = create_keypair().pubkey();
+        assert!(tx_create_virtual_nonce(svm, &payer, vm_address, vm_mem_address, owner, account_index).is_ok());
+
+        let va = get_virtual_account(svm, vm_mem_address, account_index);
+        let va_hash = va.get_hash();
+        let sig = Signature::new(payer.sign_message(va_hash.as_ref()).as_ref());
+        let sig_hash = get_storage_leaf_value(&sig, &va_hash, account_index as u64);
+
+        assert!(tx_account_compress(svm, &payer, vm_address, vm_mem_address, vm_storage_address, account_index, sig).is_ok());
+        assert!(expected.try_insert(sig_hash).is_ok());
+
+        (va, sig, sig_hash)
+    };
+
+    let (va_a, sig_a, sig_hash_a) = compress(&mut svm, &mut expected, 0);
+    let (va_b, sig_b, sig_hash_b) = compress(&mut svm, &mut expected, 1);
+
+    let leaf = |sig_hash| MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::as_leaf(sig_hash);
+    let stale_proof_a = expected.get_merkle_proof(&[leaf(sig_hash_a), leaf(sig_hash_b)], 0);
+    let stale_proof_b = expected.get_merkle_proof(&[leaf(sig_hash_a), leaf(sig_hash_b)], 1);
+
+    // Another account lands in storage before the proofs are used
+    compress(&mut svm, &mut expected, 2);
+
+    let compressed_mem = get_storage_account(&svm, vm_storage_address).compressed_state;
+    assert!(!compressed_mem.contains(&stale_proof_a, sig_hash_a));
+
+    // The proof is fast-forwarded, but only for the right leaf index
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 40, va_a.pack(), 0, 2, stale_proof_a.clone(), sig_a
+    ).is_err());
+
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 40, va_a.pack(), 0, 0, stale_proof_a, sig_a
+    ).is_ok());
+
+    // The second proof is still good after both changes
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 41, va_b.pack(), 0, 1, stale_proof_b, sig_b
+    ).is_ok());
+
+    assert_eq!(get_virtual_account(&svm, vm_mem_address, 40).get_hash(), va_a.get_hash());
+    assert_eq!(get_virtual_account(&svm, vm_mem_address, 41).get_hash(), va_b.get_hash());
+}
+
+#[test]
+fn run_system_account_decompress_from_events() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN+1;
+
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, capacity, account_size, name);
+
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    // Rebuild cold storage from nothing but the logged events
+    let mut cold = ColdStorage::new();
+
+    for account_index in 0..3 {
+        let owner = create_keypair().pubkey();
+        assert!(tx_create_virtual_nonce(&mut svm, &payer, vm_address, vm_mem_address, owner, account_index).is_ok());
+
+        let va = get_virtual_account(&svm, vm_mem_address, account_index);
+        let sig = Signature::new(payer.sign_message(va.get_hash().as_ref()).as_ref());
+
+        let meta = tx_account_compress(&mut svm, &payer, vm_address, vm_mem_address, vm_storage_address, account_index, sig).unwrap();
+        assert!(cold.apply_logs(&meta.logs).is_ok());
+    }
+
+    let accounts: Vec<ColdAccount> = cold.get_accounts(vm_storage_address).into_iter().cloned().collect();
+    assert_eq!(accounts.len(), 3);
+
+    for (i, account) in accounts.iter().enumerate() {
+        assert_eq!(account.leaf_index, i as u64);
+        assert_eq!(account.tree_id, 0);
+
+        let tree = get_storage_account(&svm, vm_storage_address).compressed_state;
+        let proof = cold.get_merkle_proof(&tree, vm_storage_address, account.tree_id, account.leaf_index).unwrap();
+
+        let meta = tx_account_decompress(
+            &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+            None, None, 40 + i as u16, account.packed_va.clone(), account.tree_id, account.leaf_index, proof, account.signature
+        ).unwrap();
+
+        assert!(cold.apply_logs(&meta.logs).is_ok());
+
+        let va = get_virtual_account(&svm, vm_mem_address, 40 + i as u16);
+        assert_eq!(va.pack(), account.packed_va);
+    }
+
+    assert!(cold.get_accounts(vm_storage_address).is_empty());
+    assert!(get_storage_account(&svm, vm_storage_address).compressed_state.is_empty());
+}
+
+#[test]
+fn run_system_account_decompress_race() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN+1;
+
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, capacity, account_size, name);
+
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    let mut expected = MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::new(&[
+        MERKLE_TREE_SEED,
+        create_name(name).as_ref(),
+        vm_address.as_ref()
+    ]);
+
+    let mut compressed = vec![];
+    for account_index in 0..2 {
+        let owner = create_keypair().pubkey();
+        assert!(tx_create_virtual_nonce(&mut svm, &payer, vm_address, vm_mem_address, owner, account_index).is_ok());
+
+        let va = get_virtual_account(&svm, vm_mem_address, account_index);
+        let va_hash = va.get_hash();
+        let sig = Signature::new(payer.sign_message(va_hash.as_ref()).as_ref());
+        let sig_hash = get_storage_leaf_value(&sig, &va_hash, account_index as u64);
+
+        assert!(tx_account_compress(&mut svm, &payer, vm_address, vm_mem_address, vm_storage_address, account_index, sig).is_ok());
+        assert!(expected.try_insert(sig_hash).is_ok());
+
+        compressed.push((va, sig, sig_hash));
+    }
+    let (va_a, sig_a, sig_hash_a) = compressed[0];
+    let (va_b, sig_b, sig_hash_b) = compressed[1];
+
+    // Both proofs are made against the current root
+    let leaf = |sig_hash| MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::as_leaf(sig_hash);
+    let leaves = [leaf(sig_hash_a), leaf(sig_hash_b)];
+    let proof_a = expected.get_merkle_proof(&leaves, 0);
+    let proof_b = expected.get_merkle_proof(&leaves, 1);
+
+    // The leaf commits to its index, so the wrong one is rejected
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 40, va_a.pack(), 0, 1, proof_a.clone(), sig_a
+    ).is_err());
+
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 40, va_a.pack(), 0, 0, proof_a, sig_a
+    ).is_ok());
+
+    // The first decompress is in the changelog, so the second proof is
+    // fast-forwarded past it
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 41, va_b.pack(), 0, 1, proof_b, sig_b
+    ).is_ok());
+
+    assert_eq!(get_virtual_account(&svm, vm_mem_address, 40).get_hash(), va_a.get_hash());
+    assert_eq!(get_virtual_account(&svm, vm_mem_address, 41).get_hash(), va_b.get_hash());
+    assert!(get_storage_account(&svm, vm_storage_address).compressed_state.is_empty());
+}
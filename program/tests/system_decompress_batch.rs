@@ -0,0 +1,102 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::{prelude::*, utils::{get_compress_batch_message, get_storage_leaf_value}};
+use solana_sdk::{pubkey::Pubkey, signer::Signer, transaction::Transaction};
+
+#[test]
+fn run_system_account_decompress_batch() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN+1;
+
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, capacity, account_size, name);
+
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    let account_indices: Vec<u16> = vec![0, 1, 2, 3, 4];
+    for account_index in account_indices.iter() {
+        let owner = create_keypair().pubkey();
+        assert!(tx_create_virtual_nonce(&mut svm, &payer, vm_address, vm_mem_address, owner, *account_index).is_ok());
+    }
+
+    let vas: Vec<VirtualAccount> = account_indices.iter()
+        .map(|index| get_virtual_account(&svm, vm_mem_address, *index))
+        .collect();
+    let va_hashes: Vec<Hash> = vas.iter().map(|va| va.get_hash()).collect();
+
+    let message = get_compress_batch_message(&va_hashes);
+    let sig = Signature::new(payer.sign_message(message.as_ref()).as_ref());
+
+    let ix = system_account_compress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, account_indices.clone(), sig);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    assert!(send_tx(&mut svm, tx).is_ok());
+
+    let mut expected = MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::new(&[
+        MERKLE_TREE_SEED,
+        create_name(name).as_ref(),
+        vm_address.as_ref()
+    ]);
+    let sig_hashes: Vec<Hash> = va_hashes.iter()
+        .enumerate()
+        .map(|(i, va_hash)| get_storage_leaf_value(&sig, va_hash, i as u64))
+        .collect();
+    for sig_hash in sig_hashes.iter() {
+        assert!(expected.try_insert(*sig_hash).is_ok());
+    }
+
+    let leaves: Vec<Hash> = sig_hashes.iter()
+        .map(|sig_hash| MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::as_leaf(*sig_hash))
+        .collect();
+
+    let leaf_indices = [1usize, 2, 4];
+    let proof = expected.get_merkle_multi_proof(&leaves, &leaf_indices);
+    assert!(proof.len() < leaf_indices.len() * StorageAccount::MERKLE_TREE_DEPTH);
+
+    let entries = |dst: &[u16]| -> Vec<(DecompressBatchAccount, Option<Pubkey>, Option<Pubkey>)> {
+        leaf_indices.iter().zip(dst.iter())
+            .map(|(leaf_index, account_index)| (DecompressBatchAccount {
+                account_index: *account_index,
+                leaf_index: *leaf_index as u64,
+                packed_va: vas[*leaf_index].pack(),
+                signature: sig,
+            }, None, None))
+            .collect()
+    };
+
+    // The multi-proof must cover every account
+    let ix = system_account_decompress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, 0, entries(&[40, 41, 42]), proof[1..].to_vec());
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    assert!(send_tx(&mut svm, tx).is_err());
+
+    // Each account needs its own memory slot
+    let ix = system_account_decompress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, 0, entries(&[40, 40, 42]), proof.clone());
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    assert!(send_tx(&mut svm, tx).is_err());
+
+    let ix = system_account_decompress_batch(
+        payer.pubkey(), vm_address, vm_mem_address, vm_storage_address, 0, entries(&[40, 41, 42]), proof);
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+    assert!(send_tx(&mut svm, tx).is_ok());
+
+    for (leaf_index, account_index) in leaf_indices.iter().zip([40u16, 41, 42].iter()) {
+        let va = get_virtual_account(&svm, vm_mem_address, *account_index);
+        assert_eq!(va.get_hash(), va_hashes[*leaf_index]);
+
+        let proof = expected.get_merkle_proof(&leaves, *leaf_index);
+        assert!(expected.try_remove(&proof, sig_hashes[*leaf_index]).is_ok());
+    }
+
+    // The storage root matches removing the same accounts one by one
+    let compressed_mem = get_storage_account(&svm, vm_storage_address).compressed_state;
+    assert_eq!(expected.get_root(), compressed_mem.get_root());
+}
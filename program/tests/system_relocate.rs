@@ -0,0 +1,58 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::prelude::*;
+use solana_sdk::signer::Signer;
+
+#[test]
+fn run_relocate() {
+    let mut ctx = TestContext::new(21);
+    let mem_a = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_1");
+
+    let vta_ctx = ctx.create_timelock_account(mem_a, 0);
+    ctx.deposit_tokens_to_timelock(mem_a, &vta_ctx, 100)
+        .unwrap();
+
+    let before = ctx.get_virtual_timelock(mem_a, vta_ctx.index);
+
+    // Only the VM authority can relocate accounts
+    let other = create_payer(&mut ctx.svm);
+    let ix = system_account_relocate(other.pubkey(), ctx.vm_address, mem_a, 0, mem_b, 5);
+    assert!(!send_ix(&mut ctx.svm, &other, ix));
+
+    let ix = system_account_relocate(ctx.payer.pubkey(), ctx.vm_address, mem_a, 0, mem_b, 5);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    assert!(!ctx.has_virtual_account(mem_a, 0));
+    assert_eq!(ctx.get_virtual_timelock(mem_b, 5), before);
+
+    // Within the same memory account
+    let ix = system_account_relocate(ctx.payer.pubkey(), ctx.vm_address, mem_b, 5, mem_b, 1);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    assert!(!ctx.has_virtual_account(mem_b, 5));
+    assert_eq!(ctx.get_virtual_timelock(mem_b, 1), before);
+}
+
+#[test]
+fn run_relocate_invalid_destination() {
+    let mut ctx = TestContext::new(21);
+    let mem_a = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let mem_b = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+
+    ctx.create_timelock_account(mem_a, 0);
+    ctx.create_timelock_account(mem_a, 1);
+
+    // The destination slot is taken
+    let ix = system_account_relocate(ctx.payer.pubkey(), ctx.vm_address, mem_a, 0, mem_a, 1);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    // The destination slot is too small
+    let ix = system_account_relocate(ctx.payer.pubkey(), ctx.vm_address, mem_a, 0, mem_b, 0);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    assert!(ctx.has_virtual_account(mem_a, 0));
+    assert!(!ctx.has_virtual_account(mem_b, 0));
+}
@@ -177,7 +177,7 @@ fn run_withdraw_from_storage() {
     let va = VirtualAccount::Timelock(vta);
     let va_hash = va.get_hash();
     let sig = Signature::new(payer.sign_message(va_hash.as_ref()).as_ref());
-    let sig_hash = hashv(&[sig.as_ref(), va_hash.as_ref()]);
+    let sig_hash = get_storage_leaf_value(&sig, &va_hash, 0);
 
     assert!(tx_account_compress(
         &mut svm, 
@@ -228,6 +228,8 @@ fn run_withdraw_from_storage() {
         destination,
         WithdrawIxData::FromStorage { 
             packed_va: va.pack(), 
+            tree_id: 0,
+            leaf_index: 0,
             proof,
             signature: sig,
         } 
@@ -96,13 +96,38 @@ impl TestContext {
         }
     }
 
+    pub fn create_timelock_account_with_owner(
+        &mut self,
+        mem_b: Pubkey,
+        index: u16,
+        owner: Pubkey,
+    ) -> VirtualTimelockAccount {
+        create_timelock_with_owner(
+            &mut self.svm,
+            &self.payer,
+            self.vm_address,
+            mem_b,
+            index,
+            owner,
+        )
+    }
+
     pub fn deposit_tokens_to_timelock(
         &mut self,
         mem_b: Pubkey,
         vta_ctx: &TimelockAccountContext,
         amount: u64,
     ) -> TransactionResult {
-        let depositor = vta_ctx.key.pubkey();
+        self.deposit_tokens_to_owner(mem_b, vta_ctx.key.pubkey(), vta_ctx.index, amount)
+    }
+
+    pub fn deposit_tokens_to_owner(
+        &mut self,
+        mem_b: Pubkey,
+        depositor: Pubkey,
+        index: u16,
+        amount: u64,
+    ) -> TransactionResult {
         let (deposit_pda, deposit_pda_bump) =
             find_timelock_deposit_pda(&self.vm_address, &depositor);
         let deposit_ata = create_ata(&mut self.svm, &self.payer, &self.mint_pk, &deposit_pda);
@@ -125,7 +150,7 @@ impl TestContext {
             deposit_pda,
             deposit_ata,
             self.vm.omnibus.vault,
-            vta_ctx.index,
+            index,
             amount,
             deposit_pda_bump,
         )
@@ -282,6 +307,18 @@ impl TestContext {
         get_virtual_timelock(&self.svm, mem, index)
     }
 
+    pub fn get_virtual_escrow(&self, mem: Pubkey, index: u16) -> VirtualEscrowAccount {
+        get_virtual_escrow(&self.svm, mem, index)
+    }
+
+    pub fn get_virtual_stream(&self, mem: Pubkey, index: u16) -> VirtualStreamAccount {
+        get_virtual_stream(&self.svm, mem, index)
+    }
+
+    pub fn get_virtual_allowance(&self, mem: Pubkey, index: u16) -> VirtualAllowanceAccount {
+        get_virtual_allowance(&self.svm, mem, index)
+    }
+
     pub fn has_virtual_account(&self, mem: Pubkey, index: u16) -> bool {
         has_virtual_account(&self.svm, mem, index)
     }
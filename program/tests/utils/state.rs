@@ -90,6 +90,21 @@ pub fn get_virtual_relay(svm: &LiteSVM, vm_memory: Pubkey, account_index: u16) -
     va.into_inner_relay().unwrap()
 }
 
+pub fn get_virtual_escrow(svm: &LiteSVM, vm_memory: Pubkey, account_index: u16) -> VirtualEscrowAccount {
+    let va = get_virtual_account(svm, vm_memory, account_index);
+    va.into_inner_escrow().unwrap()
+}
+
+pub fn get_virtual_stream(svm: &LiteSVM, vm_memory: Pubkey, account_index: u16) -> VirtualStreamAccount {
+    let va = get_virtual_account(svm, vm_memory, account_index);
+    va.into_inner_stream().unwrap()
+}
+
+pub fn get_virtual_allowance(svm: &LiteSVM, vm_memory: Pubkey, account_index: u16) -> VirtualAllowanceAccount {
+    let va = get_virtual_account(svm, vm_memory, account_index);
+    va.into_inner_allowance().unwrap()
+}
+
 pub fn create_durable_nonce(
     svm: &mut LiteSVM,
     payer: &Keypair,
@@ -121,9 +136,28 @@ pub fn create_timelock(
     vm_memory: Pubkey,
     account_index: u16,
 ) -> (VirtualTimelockAccount, Keypair) {
-    let vm = get_vm_account(&svm, vm_address);
     let signer = create_keypair();
-    let owner = signer.pubkey();
+    let vta = create_timelock_with_owner(
+        svm,
+        payer,
+        vm_address,
+        vm_memory,
+        account_index,
+        signer.pubkey(),
+    );
+
+    (vta, signer)
+}
+
+pub fn create_timelock_with_owner(
+    svm: &mut LiteSVM,
+    payer: &Keypair,
+    vm_address: Pubkey,
+    vm_memory: Pubkey,
+    account_index: u16,
+    owner: Pubkey,
+) -> VirtualTimelockAccount {
+    let vm = get_vm_account(&svm, vm_address);
 
     let (timelock_address, virtual_timelock_bump) = find_virtual_timelock_address(
         &vm.get_mint(), 
@@ -156,9 +190,7 @@ pub fn create_timelock(
     ).is_ok());
 
     // Grab the virtual account data from the memory account
-    let vta = get_virtual_timelock(svm, vm_memory, account_index);
-
-    (vta, signer)
+    get_virtual_timelock(svm, vm_memory, account_index)
 }
 
 pub fn create_and_resize_memory(
@@ -367,6 +399,8 @@ pub fn tx_account_decompress(
     withdraw_receipt: Option<Pubkey>,
     account_index: u16,
     packed_va: Vec<u8>,
+    tree_id: u16,
+    leaf_index: u64,
     proof: Vec<Hash>,
     signature: Signature,
 ) -> TransactionResult {
@@ -381,6 +415,8 @@ pub fn tx_account_decompress(
         withdraw_receipt, 
         account_index, 
         packed_va, 
+        tree_id,
+        leaf_index,
         proof, 
         signature
     );
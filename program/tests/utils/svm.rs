@@ -1,7 +1,7 @@
 #![cfg(test)]
 use std::path::PathBuf;
 use code_vm_api::prelude::CodeInstruction;
-use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction};
+use solana_sdk::{clock::Clock, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction};
 use litesvm::{types::{FailedTransactionMetadata, TransactionMetadata, TransactionResult}, LiteSVM};
 use litesvm_token::{CreateAssociatedTokenAccount, CreateMint, MintTo, spl_token::{state::Account}, get_spl_account};
 use pretty_hex::*;
@@ -35,6 +35,30 @@ pub fn send_tx(svm: &mut LiteSVM, tx: Transaction) -> TransactionResult {
     res
 }
 
+/// Send a single instruction, signed and paid for by `signer`.
+pub fn send_ix(svm: &mut LiteSVM, signer: &Keypair, ix: Instruction) -> bool {
+    let blockhash = svm.latest_blockhash();
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&signer.pubkey()), &[signer], blockhash);
+    send_tx(svm, tx).is_ok()
+}
+
+pub fn set_time(svm: &mut LiteSVM, unix_timestamp: i64) {
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp = unix_timestamp;
+    svm.set_sysvar::<Clock>(&clock);
+
+    // Moving to a new slot also lets an identical transaction be sent again
+    svm.expire_blockhash();
+}
+
+/// The data of every `Program data:` log line, as logged by sol_log_data.
+pub fn get_program_data(meta: &TransactionMetadata) -> Vec<Vec<u8>> {
+    meta.logs.iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .flat_map(|data| data.split(' ').map(|field| base64::decode(field).unwrap()))
+        .collect()
+}
+
 pub fn create_payer(svm: &mut LiteSVM) -> Keypair {
     let payer_kp = Keypair::new();
     let payer_pk = payer_kp.pubkey();
@@ -0,0 +1,116 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::{prelude::*, utils::get_storage_leaf_value};
+use solana_sdk::signer::Signer;
+
+#[test]
+fn run_rotate_authority() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let new_authority = create_payer(&mut svm);
+    let other = create_payer(&mut svm);
+
+    // Accepting requires a proposal
+    assert!(!send_ix(&mut svm, &new_authority, vm_accept_authority(new_authority.pubkey(), vm_address)));
+
+    // Only the current authority can propose
+    assert!(!send_ix(&mut svm, &other, vm_propose_authority(other.pubkey(), vm_address, other.pubkey())));
+    assert!(send_ix(&mut svm, &payer, vm_propose_authority(payer.pubkey(), vm_address, new_authority.pubkey())));
+
+    let vm = get_vm_account(&svm, vm_address);
+    assert_eq!(vm.get_current_authority(), payer.pubkey());
+    assert_eq!(vm.get_pending_authority(), Some(new_authority.pubkey()));
+
+    // Only the proposed key can accept
+    assert!(!send_ix(&mut svm, &other, vm_accept_authority(other.pubkey(), vm_address)));
+
+    // Same transaction as the first accept, so it needs a fresh blockhash
+    svm.expire_blockhash();
+    assert!(send_ix(&mut svm, &new_authority, vm_accept_authority(new_authority.pubkey(), vm_address)));
+
+    let vm = get_vm_account(&svm, vm_address);
+    assert_eq!(vm.authority, payer.pubkey());
+    assert_eq!(vm.get_current_authority(), new_authority.pubkey());
+    assert_eq!(vm.get_pending_authority(), None);
+
+    // The old authority no longer controls the VM
+    let ix = vm_set_sig_verify_mode(payer.pubkey(), vm_address, SigVerifyMode::Program);
+    assert!(!send_ix(&mut svm, &payer, ix));
+
+    let ix = vm_set_sig_verify_mode(new_authority.pubkey(), vm_address, SigVerifyMode::Program);
+    assert!(send_ix(&mut svm, &new_authority, ix));
+}
+
+#[test]
+fn run_decompress_after_rotation() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, 100, VirtualDurableNonce::LEN + 1, name);
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    let account_index = 0;
+    assert!(tx_create_virtual_nonce(&mut svm, &payer, vm_address, vm_mem_address, create_keypair().pubkey(), account_index).is_ok());
+
+    // Compress with the original authority
+    let va = get_virtual_account(&svm, vm_mem_address, account_index);
+    let va_hash = va.get_hash();
+    let sig = Signature::new(payer.sign_message(va_hash.as_ref()).as_ref());
+    let sig_hash = get_storage_leaf_value(&sig, &va_hash, 0);
+
+    assert!(tx_account_compress(&mut svm, &payer, vm_address, vm_mem_address, vm_storage_address, account_index, sig).is_ok());
+
+    // Rotate to a new authority
+    let new_authority = create_payer(&mut svm);
+    assert!(send_ix(&mut svm, &payer, vm_propose_authority(payer.pubkey(), vm_address, new_authority.pubkey())));
+    assert!(send_ix(&mut svm, &new_authority, vm_accept_authority(new_authority.pubkey(), vm_address)));
+
+    // The new authority can still bring back accounts compressed before the rotation
+    let mut expected = MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::new(&[
+        MERKLE_TREE_SEED,
+        create_name(name).as_ref(),
+        vm_address.as_ref()
+    ]);
+    assert!(expected.try_insert(sig_hash).is_ok());
+    let proof = expected.get_merkle_proof(&[sig_hash], 0);
+
+    assert!(tx_account_decompress(
+        &mut svm,
+        &payer,
+        vm_address,
+        vm_mem_address,
+        vm_storage_address,
+        None,
+        None,
+        account_index,
+        va.pack(),
+        0,
+        0,
+        proof.clone(),
+        sig
+    ).is_err());
+
+    assert!(tx_account_decompress(
+        &mut svm,
+        &new_authority,
+        vm_address,
+        vm_mem_address,
+        vm_storage_address,
+        None,
+        None,
+        account_index,
+        va.pack(),
+        0,
+        0,
+        proof,
+        sig
+    ).is_ok());
+
+    assert!(get_virtual_account(&svm, vm_mem_address, account_index).is_nonce());
+}
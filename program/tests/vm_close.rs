@@ -0,0 +1,204 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use code_vm_api::prelude::*;
+use solana_sdk::signer::Signer;
+
+fn get_lamports(ctx: &TestContext, address: Pubkey) -> u64 {
+    ctx.svm.get_account(&address).map_or(0, |account| account.lamports)
+}
+
+fn is_closed(ctx: &TestContext, address: Pubkey) -> bool {
+    get_lamports(ctx, address) == 0
+}
+
+#[test]
+fn run_close_memory() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let vdn_ctx = ctx.create_durable_nonce_account(mem, 0);
+
+    let destination = create_keypair().pubkey();
+    let ix = vm_memory_close(ctx.payer.pubkey(), ctx.vm_address, mem, destination);
+
+    // The memory account still holds a virtual account
+    assert!(ctx.ix_send(&[ix.clone()]).is_err());
+
+    assert!(ctx.exec_opcode(
+        [Some(mem), None, None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        CloseNonceOp {}.to_bytes(),
+        vec![vdn_ctx.index],
+        vec![0],
+    ).is_ok());
+
+    // Same transaction as before, so it needs a fresh blockhash
+    ctx.svm.expire_blockhash();
+
+    let rent = get_lamports(&ctx, mem);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    assert!(is_closed(&ctx, mem));
+    assert_eq!(get_lamports(&ctx, destination), rent);
+}
+
+#[test]
+fn run_close_storage() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let (storage, _) = create_storage_account(&mut ctx.svm, &ctx.payer, ctx.vm_address, "storage_0");
+
+    let vdn_ctx = ctx.create_durable_nonce_account(mem, 0);
+
+    let va = get_virtual_account(&ctx.svm, mem, vdn_ctx.index);
+    let va_hash = va.get_hash();
+    let sig = Signature::new(ctx.payer.sign_message(va_hash.as_ref()).as_ref());
+
+    let (svm, payer) = (&mut ctx.svm, &ctx.payer);
+    assert!(tx_account_compress(svm, payer, ctx.vm_address, mem, storage, vdn_ctx.index, sig).is_ok());
+
+    // The storage account still holds a compressed account
+    let destination = create_keypair().pubkey();
+    let ix = vm_storage_close(ctx.payer.pubkey(), ctx.vm_address, storage, destination);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    // An empty storage account can be closed
+    let (empty, _) = create_storage_account(&mut ctx.svm, &ctx.payer, ctx.vm_address, "storage_1");
+    let rent = get_lamports(&ctx, empty);
+
+    let ix = vm_storage_close(ctx.payer.pubkey(), ctx.vm_address, empty, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    assert!(is_closed(&ctx, empty));
+    assert_eq!(get_lamports(&ctx, destination), rent);
+}
+
+#[test]
+fn run_close_relay() {
+    let mut ctx = TestContext::new(21);
+    let destination = create_keypair().pubkey();
+
+    // The relay treasury must be drained first
+    let funded = ctx.create_relay("relay_0", 100);
+    let ix = relay_close(ctx.payer.pubkey(), ctx.vm_address, funded.relay_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    let relay = ctx.create_relay("relay_1", 0);
+    let vault = relay.relay.treasury.vault;
+    let rent = get_lamports(&ctx, relay.relay_address) + get_lamports(&ctx, vault);
+
+    // Only the VM authority can close the relay
+    let other = create_payer(&mut ctx.svm);
+    let ix = relay_close(other.pubkey(), ctx.vm_address, relay.relay_address, destination);
+    assert!(!send_ix(&mut ctx.svm, &other, ix));
+
+    let ix = relay_close(ctx.payer.pubkey(), ctx.vm_address, relay.relay_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    assert!(is_closed(&ctx, relay.relay_address));
+    assert!(is_closed(&ctx, vault));
+    assert_eq!(get_lamports(&ctx, destination), rent);
+}
+
+#[test]
+fn run_close_vm() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let destination = create_keypair().pubkey();
+
+    // The omnibus must be empty
+    let vta_ctx = ctx.create_timelock_account(mem, 0);
+    ctx.deposit_tokens_to_timelock(mem, &vta_ctx, 100)
+        .unwrap();
+
+    let ix = vm_close(ctx.payer.pubkey(), ctx.vm_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    let mut ctx = TestContext::new(22);
+    let omnibus = ctx.vm.omnibus.vault;
+    let rent = get_lamports(&ctx, ctx.vm_address) + get_lamports(&ctx, omnibus);
+
+    let ix = vm_close(ctx.payer.pubkey(), ctx.vm_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    assert!(is_closed(&ctx, ctx.vm_address));
+    assert!(is_closed(&ctx, omnibus));
+    assert_eq!(get_lamports(&ctx, destination), rent);
+}
+
+#[test]
+fn run_close_vm_with_children() {
+    let mut ctx = TestContext::new(21);
+    let destination = create_keypair().pubkey();
+
+    let mem = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let (storage, _) = create_storage_account(&mut ctx.svm, &ctx.payer, ctx.vm_address, "storage_0");
+    let relay = ctx.create_relay("relay_0", 0);
+
+    assert_eq!(get_vm_account(&ctx.svm, ctx.vm_address).num_children, 3);
+
+    // The memory, storage and relay accounts would be stranded
+    let ix = vm_close(ctx.payer.pubkey(), ctx.vm_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    let ix = vm_memory_close(ctx.payer.pubkey(), ctx.vm_address, mem, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    let ix = vm_storage_close(ctx.payer.pubkey(), ctx.vm_address, storage, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    let ix = relay_close(ctx.payer.pubkey(), ctx.vm_address, relay.relay_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    assert_eq!(get_vm_account(&ctx.svm, ctx.vm_address).num_children, 0);
+
+    let ix = vm_close(ctx.payer.pubkey(), ctx.vm_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert!(is_closed(&ctx, ctx.vm_address));
+}
+
+#[test]
+fn run_close_vm_with_uncounted_children() {
+    let mut ctx = TestContext::new(21);
+    let destination = create_keypair().pubkey();
+
+    let mem = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let (storage, _) = create_storage_account(&mut ctx.svm, &ctx.payer, ctx.vm_address, "storage_0");
+
+    // Make the memory account look like one created before the VM counted
+    // its children, so only the storage account is counted
+    let mut info = ctx.svm.get_account(&mem).unwrap();
+    let mut header = MemoryAccount::unpack(&info.data);
+    header.packed_info = [0; 6];
+    info.data[8..MemoryAccount::get_size()].copy_from_slice(header.to_bytes());
+    ctx.svm.set_account(mem, info).unwrap();
+
+    let mut info = ctx.svm.get_account(&ctx.vm_address).unwrap();
+    let mut vm = CodeVmAccount::unpack(&info.data);
+    vm.num_children = 1;
+    info.data[8..CodeVmAccount::get_size()].copy_from_slice(vm.to_bytes());
+    ctx.svm.set_account(ctx.vm_address, info).unwrap();
+
+    // Closing the uncounted memory account leaves the count alone
+    let ix = vm_memory_close(ctx.payer.pubkey(), ctx.vm_address, mem, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert_eq!(get_vm_account(&ctx.svm, ctx.vm_address).num_children, 1);
+
+    // The storage account is still open
+    let ix = vm_close(ctx.payer.pubkey(), ctx.vm_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    let ix = vm_storage_close(ctx.payer.pubkey(), ctx.vm_address, storage, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert_eq!(get_vm_account(&ctx.svm, ctx.vm_address).num_children, 0);
+
+    let ix = vm_close(ctx.payer.pubkey(), ctx.vm_address, destination);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert!(is_closed(&ctx, ctx.vm_address));
+}
@@ -0,0 +1,67 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::prelude::*;
+use litesvm::types::TransactionResult;
+use solana_sdk::{instruction::InstructionError, signature::Signer, transaction::TransactionError};
+
+fn get_error(result: TransactionResult) -> Option<CodeVmError> {
+    match result.err()?.err {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) =>
+            CodeVmError::try_from(code).ok(),
+        _ => None,
+    }
+}
+
+#[test]
+fn run_error_codes() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_a_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_b_ctx = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    assert!(ctx.deposit_tokens_to_timelock(mem_b, &vta_a_ctx, 100).is_ok());
+
+    let transfer = |ctx: &mut TestContext, amount: u64, banks: Vec<u8>| {
+        let hash = create_transfer_message(
+            &ctx.vm,
+            &vta_a_ctx.account,
+            &vta_b_ctx.account,
+            &vdn_ctx.account,
+            amount,
+        );
+        let signature = vta_a_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+        let data = TransferOp::from_struct(ParsedTransferOp { amount, signature }).to_bytes();
+
+        ctx.exec_opcode(
+            [Some(mem_a), Some(mem_b), None, None],
+            None, None, None, None, None,
+            data,
+            vec![vdn_ctx.index, vta_a_ctx.index, vta_b_ctx.index],
+            banks,
+        )
+    };
+
+    // More than the source holds
+    let result = transfer(&mut ctx, 1_000, vec![0, 1, 1]);
+    assert_eq!(get_error(result), Some(CodeVmError::InsufficientFunds));
+
+    // The nonce bank given where a timelock account is expected
+    let result = transfer(&mut ctx, 42, vec![0, 0, 1]);
+    assert_eq!(get_error(result), Some(CodeVmError::WrongAccountVariant));
+
+    // A memory bank that was not passed in
+    let result = transfer(&mut ctx, 42, vec![0, 1, 2]);
+    assert_eq!(get_error(result), Some(CodeVmError::MissingAccount));
+
+    let ix = vm_set_pause(ctx.payer.pubkey(), ctx.vm_address, true, &[]);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    let result = transfer(&mut ctx, 42, vec![0, 1, 1]);
+    assert_eq!(get_error(result), Some(CodeVmError::Paused));
+}
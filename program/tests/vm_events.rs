@@ -0,0 +1,101 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use solana_sdk::signature::Signer;
+use code_vm_api::prelude::*;
+use litesvm::types::TransactionMetadata;
+
+fn get_events(meta: &TransactionMetadata) -> Vec<ParsedEvent> {
+    get_program_data(meta)
+        .iter()
+        .map(|data| ParsedEvent::try_from_bytes(data).unwrap())
+        .collect()
+}
+
+#[test]
+fn run_deposit_and_transfer_events() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_a_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_b_ctx = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    // A deposit moves tokens into the omnibus and credits the virtual account
+    let meta = ctx.deposit_tokens_to_timelock(mem_b, &vta_a_ctx, 100).unwrap();
+    let events = get_events(&meta);
+
+    let ParsedEvent::TokenTransfer(transfer) = &events[0] else {
+        panic!("expected a token transfer, got {:?}", events[0]);
+    };
+    assert_eq!(transfer.destination, ctx.vm.omnibus.vault);
+    assert_eq!(transfer.amount, 100);
+
+    let ParsedEvent::AccountWrite(write) = &events[1] else {
+        panic!("expected an account write, got {:?}", events[1]);
+    };
+    assert_eq!(write.vm_memory, mem_b);
+    assert_eq!(write.account_index, vta_a_ctx.index);
+    assert_eq!((write.previous_balance, write.balance), (0, 100));
+    assert_eq!(VirtualAccount::unpack(&write.packed_va).unwrap().get_balance(), 100);
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert_eq!(events[2], ParsedEvent::Instruction(InstructionEvent {
+        instruction: CodeInstruction::DepositIx as u8,
+        slot: vm.slot,
+        poh: vm.poh,
+    }));
+    assert_eq!(events.len(), 3);
+
+    // A transfer writes the source, destination and nonce, then the opcode
+    let amount = 42;
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &vta_a_ctx.account,
+        &vta_b_ctx.account,
+        &vdn_ctx.account,
+        amount,
+    );
+    let signature = vta_a_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+
+    let mem_indices = vec![vdn_ctx.index, vta_a_ctx.index, vta_b_ctx.index];
+    let mem_banks = vec![0, 1, 1];
+    let data = TransferOp::from_struct(ParsedTransferOp { amount, signature }).to_bytes();
+
+    let meta = ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, None, None, None, None,
+        data,
+        mem_indices.clone(),
+        mem_banks.clone(),
+    ).unwrap();
+
+    let events = get_events(&meta);
+    let writes: Vec<&AccountWriteEvent> = events.iter()
+        .filter_map(|event| match event {
+            ParsedEvent::AccountWrite(write) => Some(write),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(writes.len(), 3);
+    assert_eq!((writes[0].account_index, writes[0].previous_balance, writes[0].balance), (vta_a_ctx.index, 100, 58));
+    assert_eq!((writes[1].account_index, writes[1].previous_balance, writes[1].balance), (vta_b_ctx.index, 0, 42));
+    assert_eq!((writes[2].vm_memory, writes[2].account_index), (mem_a, vdn_ctx.index));
+
+    assert_eq!(events[3], ParsedEvent::Opcode(OpcodeEvent {
+        opcode: Opcode::TransferOp as u8,
+        mem_indicies: mem_indices,
+        mem_banks,
+    }));
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert_eq!(events[4], ParsedEvent::Instruction(InstructionEvent {
+        instruction: CodeInstruction::ExecIx as u8,
+        slot: vm.slot,
+        poh: vm.poh,
+    }));
+}
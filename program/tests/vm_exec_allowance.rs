@@ -0,0 +1,173 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use solana_sdk::signature::{Keypair, Signer};
+use code_vm_api::prelude::*;
+
+const DEPOSIT_AMOUNT: u64 = 100;
+const ALLOWANCE_AMOUNT: u64 = 50;
+
+struct AllowanceSetup {
+    mem_a: Pubkey,
+    mem_b: Pubkey,
+    mem_c: Pubkey,
+    owner: TimelockAccountContext,
+    merchant: TimelockAccountContext,
+    delegate: Keypair,
+    allowance_index: u16,
+    expires_at: i64,
+}
+
+/// Approves `delegate` to spend `ALLOWANCE_AMOUNT` from `owner`.
+fn setup_allowance(ctx: &mut TestContext) -> AllowanceSetup {
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let mem_c = ctx.create_memory(10, VirtualAllowanceAccount::LEN + 1, "mem_allowance_0");
+
+    let owner = ctx.create_timelock_account(mem_b, 0);
+    let merchant = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+    let delegate = create_keypair();
+
+    ctx.deposit_tokens_to_timelock(mem_b, &owner, DEPOSIT_AMOUNT)
+        .unwrap();
+
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let expires_at = now + 3600;
+    let allowance_index = 0;
+
+    let msg = create_approve_message(
+        &ctx.vm,
+        &owner.account,
+        &delegate.pubkey(),
+        ALLOWANCE_AMOUNT,
+        expires_at,
+        &vdn_ctx.account,
+    );
+    let signature = owner.key.sign_message(msg.as_ref()).as_ref().try_into().unwrap();
+
+    let data = ApproveOp::from_struct(ParsedApproveOp {
+        signature,
+        amount: ALLOWANCE_AMOUNT,
+        expires_at,
+        delegate: delegate.pubkey(),
+    }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), Some(mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![vdn_ctx.index, owner.index, allowance_index],
+        vec![0, 1, 2],
+    )
+    .unwrap();
+
+    AllowanceSetup {
+        mem_a,
+        mem_b,
+        mem_c,
+        owner,
+        merchant,
+        delegate,
+        allowance_index,
+        expires_at,
+    }
+}
+
+fn transfer_from(ctx: &mut TestContext, setup: &AllowanceSetup, signer: &Keypair, amount: u64) -> bool {
+    let vdn = get_virtual_nonce(&ctx.svm, setup.mem_a, 0);
+    let allowance = ctx.get_virtual_allowance(setup.mem_c, setup.allowance_index);
+
+    let msg = create_transfer_from_message(
+        &ctx.vm,
+        &allowance,
+        &setup.owner.account,
+        &setup.merchant.account,
+        &vdn,
+        amount,
+    );
+    let signature = signer.sign_message(msg.as_ref()).as_ref().try_into().unwrap();
+
+    let data = TransferFromOp::from_struct(ParsedTransferFromOp {
+        signature,
+        amount,
+    }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(setup.mem_a), Some(setup.mem_b), Some(setup.mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![0, setup.allowance_index, setup.owner.index, setup.merchant.index],
+        vec![0, 2, 1, 1],
+    )
+    .is_ok()
+}
+
+#[test]
+fn run_approve() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_allowance(&mut ctx);
+
+    let allowance = ctx.get_virtual_allowance(setup.mem_c, setup.allowance_index);
+    assert_eq!(allowance.owner, setup.owner.account.owner);
+    assert_eq!(allowance.delegate, setup.delegate.pubkey());
+    assert_eq!(allowance.remaining, ALLOWANCE_AMOUNT);
+    assert_eq!(allowance.expires_at, setup.expires_at);
+
+    // No tokens are reserved by the allowance
+    let owner_vta = ctx.get_virtual_timelock(setup.mem_b, setup.owner.index);
+    assert_eq!(owner_vta.balance, DEPOSIT_AMOUNT);
+}
+
+#[test]
+fn run_transfer_from() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_allowance(&mut ctx);
+
+    // Only the delegate can spend the allowance
+    let other = create_keypair();
+    assert!(!transfer_from(&mut ctx, &setup, &other, 10));
+    assert!(!transfer_from(&mut ctx, &setup, &setup.owner.key, 10));
+
+    assert!(transfer_from(&mut ctx, &setup, &setup.delegate, 20));
+
+    let allowance = ctx.get_virtual_allowance(setup.mem_c, setup.allowance_index);
+    assert_eq!(allowance.remaining, ALLOWANCE_AMOUNT - 20);
+
+    // Can't spend more than what's left
+    assert!(!transfer_from(&mut ctx, &setup, &setup.delegate, ALLOWANCE_AMOUNT));
+
+    // Spending the rest removes the allowance
+    assert!(transfer_from(&mut ctx, &setup, &setup.delegate, ALLOWANCE_AMOUNT - 20));
+    assert!(!ctx.has_virtual_account(setup.mem_c, setup.allowance_index));
+
+    let owner_vta = ctx.get_virtual_timelock(setup.mem_b, setup.owner.index);
+    let merchant_vta = ctx.get_virtual_timelock(setup.mem_b, setup.merchant.index);
+    assert_eq!(owner_vta.balance, DEPOSIT_AMOUNT - ALLOWANCE_AMOUNT);
+    assert_eq!(merchant_vta.balance, ALLOWANCE_AMOUNT);
+}
+
+#[test]
+fn run_transfer_from_expired() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_allowance(&mut ctx);
+
+    let mut clock = ctx.svm.get_sysvar::<Clock>();
+    clock.unix_timestamp = setup.expires_at + 1;
+    ctx.svm.set_sysvar::<Clock>(&clock);
+
+    assert!(!transfer_from(&mut ctx, &setup, &setup.delegate, 10));
+
+    let owner_vta = ctx.get_virtual_timelock(setup.mem_b, setup.owner.index);
+    assert_eq!(owner_vta.balance, DEPOSIT_AMOUNT);
+}
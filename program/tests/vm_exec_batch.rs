@@ -0,0 +1,155 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use solana_sdk::signature::Signer;
+use code_vm_api::prelude::*;
+
+fn to_exec_data(data: Vec<u8>, mem_indicies: Vec<u16>, mem_banks: Vec<u8>) -> ExecIxData {
+    ExecIxData {
+        opcode: data[0],
+        mem_indicies,
+        mem_banks,
+        data: data[1..].to_vec(),
+    }
+}
+
+#[test]
+fn run_batch_transfer() {
+    // Initialize the test context
+    let mut ctx = TestContext::new(21);
+
+    // Create memory accounts
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    // Create timelock accounts
+    let vta_a_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_b_ctx = ctx.create_timelock_account(mem_b, 1);
+
+    // Create durable nonce account
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    // -- 1) Deposit tokens into `vta_a_ctx` so we have something to send
+    let deposit_amount = 100;
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_a_ctx, deposit_amount)
+        .unwrap();
+
+    // -- 2) Sign a transfer from A to B using the current nonce value
+    let amount_a = 42;
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &vta_a_ctx.account,
+        &vta_b_ctx.account,
+        &vdn_ctx.account,
+        amount_a,
+    );
+    let signature = vta_a_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let op_a = to_exec_data(
+        TransferOp::from_struct(ParsedTransferOp { amount: amount_a, signature }).to_bytes(),
+        vec![vdn_ctx.index, vta_a_ctx.index, vta_b_ctx.index],
+        vec![0, 1, 1],
+    );
+
+    // -- 3) Sign a transfer from B back to A. The first opcode advances the
+    // nonce to the PoH at the start of the batch.
+    let amount_b = 12;
+    let mut vdn = vdn_ctx.account;
+    vdn.value = get_vm_account(&ctx.svm, ctx.vm_address).poh;
+
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &vta_b_ctx.account,
+        &vta_a_ctx.account,
+        &vdn,
+        amount_b,
+    );
+    let signature = vta_b_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let op_b = to_exec_data(
+        TransferOp::from_struct(ParsedTransferOp { amount: amount_b, signature }).to_bytes(),
+        vec![vdn_ctx.index, vta_b_ctx.index, vta_a_ctx.index],
+        vec![0, 1, 1],
+    );
+
+    // -- 4) Execute both opcodes in a single instruction
+    let ix = vm_exec_batch(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        Some(mem_a),
+        Some(mem_b),
+        None,
+        None,
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        vec![op_a, op_b],
+    );
+    ctx.ix_send(&[ix]).unwrap();
+
+    // -- 5) Verify final balances
+    let src_vta = ctx.get_virtual_timelock(mem_b, vta_a_ctx.index);
+    let dst_vta = ctx.get_virtual_timelock(mem_b, vta_b_ctx.index);
+    assert_eq!(src_vta.balance, deposit_amount - amount_a + amount_b);
+    assert_eq!(dst_vta.balance, amount_a - amount_b);
+}
+
+#[test]
+fn run_batch_replay_fails() {
+    // Initialize the test context
+    let mut ctx = TestContext::new(21);
+
+    // Create memory accounts
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    // Create timelock accounts
+    let vta_a_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_b_ctx = ctx.create_timelock_account(mem_b, 1);
+
+    // Create durable nonce account
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    let deposit_amount = 100;
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_a_ctx, deposit_amount)
+        .unwrap();
+
+    // Sign a single transfer, then try to apply it twice in the same batch
+    let amount = 10;
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &vta_a_ctx.account,
+        &vta_b_ctx.account,
+        &vdn_ctx.account,
+        amount,
+    );
+    let signature = vta_a_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let op = to_exec_data(
+        TransferOp::from_struct(ParsedTransferOp { amount, signature }).to_bytes(),
+        vec![vdn_ctx.index, vta_a_ctx.index, vta_b_ctx.index],
+        vec![0, 1, 1],
+    );
+
+    let ix = vm_exec_batch(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        Some(mem_a),
+        Some(mem_b),
+        None,
+        None,
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        vec![op.clone(), op],
+    );
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    // Nothing was applied
+    let src_vta = ctx.get_virtual_timelock(mem_b, vta_a_ctx.index);
+    let dst_vta = ctx.get_virtual_timelock(mem_b, vta_b_ctx.index);
+    assert_eq!(src_vta.balance, deposit_amount);
+    assert_eq!(dst_vta.balance, 0);
+}
@@ -0,0 +1,81 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use solana_sdk::signature::{Keypair, Signer};
+use code_vm_api::prelude::*;
+
+fn exec_close(ctx: &mut TestContext, mem: Pubkey, index: u16, data: Vec<u8>) -> bool {
+    ctx.exec_opcode(
+        [Some(mem), None, None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![index],
+        vec![0],
+    )
+    .is_ok()
+}
+
+fn close_timelock_data(ctx: &TestContext, mem: Pubkey, index: u16, signer: &Keypair) -> Vec<u8> {
+    let va = VirtualAccount::Timelock(ctx.get_virtual_timelock(mem, index));
+    let hash = create_close_message(&ctx.vm_address, &va);
+    let signature = signer.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+
+    CloseTimelockOp { signature }.to_bytes()
+}
+
+#[test]
+fn run_close_timelock() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_ctx = ctx.create_timelock_account(mem, 0);
+
+    // Only the owner can close the account
+    let other = create_keypair();
+    let data = close_timelock_data(&ctx, mem, vta_ctx.index, &other);
+    assert!(!exec_close(&mut ctx, mem, vta_ctx.index, data));
+
+    let data = close_timelock_data(&ctx, mem, vta_ctx.index, &vta_ctx.key);
+    assert!(exec_close(&mut ctx, mem, vta_ctx.index, data));
+    assert!(!ctx.has_virtual_account(mem, vta_ctx.index));
+
+    // The slot can be reused
+    let vta_ctx = ctx.create_timelock_account(mem, 0);
+    assert!(ctx.has_virtual_account(mem, vta_ctx.index));
+}
+
+#[test]
+fn run_close_timelock_with_balance() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_ctx = ctx.create_timelock_account(mem, 0);
+    ctx.deposit_tokens_to_timelock(mem, &vta_ctx, 100)
+        .unwrap();
+
+    let data = close_timelock_data(&ctx, mem, vta_ctx.index, &vta_ctx.key);
+    assert!(!exec_close(&mut ctx, mem, vta_ctx.index, data));
+    assert_eq!(ctx.get_virtual_timelock(mem, vta_ctx.index).balance, 100);
+}
+
+#[test]
+fn run_close_nonce() {
+    let mut ctx = TestContext::new(21);
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+    let vta_ctx = ctx.create_timelock_account(mem_b, 0);
+
+    // The opcode must match the account type
+    assert!(!exec_close(&mut ctx, mem_b, vta_ctx.index, CloseNonceOp {}.to_bytes()));
+
+    assert!(exec_close(&mut ctx, mem_a, vdn_ctx.index, CloseNonceOp {}.to_bytes()));
+    assert!(!ctx.has_virtual_account(mem_a, vdn_ctx.index));
+}
@@ -0,0 +1,180 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use solana_sdk::signature::Signer;
+use code_vm_api::prelude::*;
+
+const PREIMAGE: [u8; 32] = [7u8; 32];
+const DEPOSIT_AMOUNT: u64 = 100;
+const ESCROW_AMOUNT: u64 = 40;
+
+struct EscrowSetup {
+    mem_b: Pubkey,
+    mem_c: Pubkey,
+    sender: TimelockAccountContext,
+    recipient: TimelockAccountContext,
+    escrow_index: u16,
+    refund_after: i64,
+}
+
+/// Funds an escrow from `sender` to `recipient` that unlocks with `PREIMAGE`.
+fn setup_funded_escrow(ctx: &mut TestContext) -> EscrowSetup {
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let mem_c = ctx.create_memory(10, VirtualEscrowAccount::LEN + 1, "mem_escrow_0");
+
+    let sender = ctx.create_timelock_account(mem_b, 0);
+    let recipient = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    ctx.deposit_tokens_to_timelock(mem_b, &sender, DEPOSIT_AMOUNT)
+        .unwrap();
+
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let refund_after = now + 3600;
+    let hashlock = hash(&PREIMAGE);
+    let escrow_index = 0;
+
+    let msg = create_escrow_fund_message(
+        &ctx.vm,
+        &sender.account,
+        &recipient.account.owner,
+        &hashlock,
+        refund_after,
+        ESCROW_AMOUNT,
+        &vdn_ctx.account,
+    );
+    let signature = sender.key.sign_message(msg.as_ref()).as_ref().try_into().unwrap();
+
+    let data = EscrowFundOp::from_struct(ParsedEscrowFundOp {
+        signature,
+        amount: ESCROW_AMOUNT,
+        refund_after,
+        recipient: recipient.account.owner,
+        hashlock,
+    }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), Some(mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![vdn_ctx.index, sender.index, escrow_index],
+        vec![0, 1, 2],
+    )
+    .unwrap();
+
+    EscrowSetup {
+        mem_b,
+        mem_c,
+        sender,
+        recipient,
+        escrow_index,
+        refund_after,
+    }
+}
+
+fn claim(ctx: &mut TestContext, setup: &EscrowSetup, preimage: [u8; 32], dst_index: u16) -> bool {
+    let data = EscrowClaimOp { preimage }.to_bytes();
+
+    ctx.exec_opcode(
+        [None, Some(setup.mem_b), Some(setup.mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![setup.escrow_index, dst_index],
+        vec![2, 1],
+    )
+    .is_ok()
+}
+
+fn refund(ctx: &mut TestContext, setup: &EscrowSetup, dst_index: u16) -> bool {
+    let data = EscrowRefundOp {}.to_bytes();
+
+    ctx.exec_opcode(
+        [None, Some(setup.mem_b), Some(setup.mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![setup.escrow_index, dst_index],
+        vec![2, 1],
+    )
+    .is_ok()
+}
+
+#[test]
+fn run_escrow_fund() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_funded_escrow(&mut ctx);
+
+    let escrow = ctx.get_virtual_escrow(setup.mem_c, setup.escrow_index);
+    assert_eq!(escrow.sender, setup.sender.account.owner);
+    assert_eq!(escrow.recipient, setup.recipient.account.owner);
+    assert_eq!(escrow.hashlock, hash(&PREIMAGE));
+    assert_eq!(escrow.refund_after, setup.refund_after);
+    assert_eq!(escrow.balance, ESCROW_AMOUNT);
+
+    let src_vta = ctx.get_virtual_timelock(setup.mem_b, setup.sender.index);
+    assert_eq!(src_vta.balance, DEPOSIT_AMOUNT - ESCROW_AMOUNT);
+}
+
+#[test]
+fn run_escrow_claim() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_funded_escrow(&mut ctx);
+
+    // Wrong preimage
+    assert!(!claim(&mut ctx, &setup, [8u8; 32], setup.recipient.index));
+
+    // Right preimage, but the destination is not owned by the recipient
+    assert!(!claim(&mut ctx, &setup, PREIMAGE, setup.sender.index));
+
+    assert!(claim(&mut ctx, &setup, PREIMAGE, setup.recipient.index));
+
+    let dst_vta = ctx.get_virtual_timelock(setup.mem_b, setup.recipient.index);
+    assert_eq!(dst_vta.balance, ESCROW_AMOUNT);
+    assert!(!ctx.has_virtual_account(setup.mem_c, setup.escrow_index));
+}
+
+#[test]
+fn run_escrow_claim_after_deadline_fails() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_funded_escrow(&mut ctx);
+
+    set_time(&mut ctx.svm, setup.refund_after + 1);
+    assert!(!claim(&mut ctx, &setup, PREIMAGE, setup.recipient.index));
+
+    let escrow = ctx.get_virtual_escrow(setup.mem_c, setup.escrow_index);
+    assert_eq!(escrow.balance, ESCROW_AMOUNT);
+}
+
+#[test]
+fn run_escrow_refund() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_funded_escrow(&mut ctx);
+
+    // Too early
+    assert!(!refund(&mut ctx, &setup, setup.sender.index));
+
+    set_time(&mut ctx.svm, setup.refund_after + 1);
+
+    // Only the sender can get the refund
+    assert!(!refund(&mut ctx, &setup, setup.recipient.index));
+
+    assert!(refund(&mut ctx, &setup, setup.sender.index));
+
+    let src_vta = ctx.get_virtual_timelock(setup.mem_b, setup.sender.index);
+    assert_eq!(src_vta.balance, DEPOSIT_AMOUNT);
+    assert!(!ctx.has_virtual_account(setup.mem_c, setup.escrow_index));
+}
@@ -0,0 +1,457 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use solana_sdk::signature::Signer;
+use code_vm_api::prelude::*;
+
+#[test]
+fn run_transfer_with_fee() {
+    // Initialize the test context
+    let mut ctx = TestContext::new(21);
+
+    // Create memory accounts
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    // Create timelock accounts, the last one collects fees
+    let vta_a_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_b_ctx = ctx.create_timelock_account(mem_b, 1);
+    let fee_ctx = ctx.create_timelock_account(mem_b, 2);
+
+    // Create durable nonce account
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    let deposit_amount = 2_000;
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_a_ctx, deposit_amount)
+        .unwrap();
+
+    // Charge 1% plus a flat fee of 2
+    let ix = vm_set_fee_config(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        fee_ctx.account.owner,
+        2,
+        100,
+    );
+    ctx.ix_send(&[ix]).unwrap();
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert!(vm.has_fee());
+
+    let amount = 1_000;
+    let fee = vm.get_fee(amount).unwrap();
+    assert_eq!(fee, 12);
+
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &vta_a_ctx.account,
+        &vta_b_ctx.account,
+        &vdn_ctx.account,
+        amount,
+    );
+
+    let mem_indices = vec![vdn_ctx.index, vta_a_ctx.index, vta_b_ctx.index, fee_ctx.index];
+    let mem_banks = vec![0, 1, 1, 1];
+
+    // A signature that doesn't cover the fee is rejected
+    let signature = vta_a_ctx
+        .key
+        .sign_message(hash.as_ref())
+        .as_ref()
+        .try_into()
+        .unwrap();
+    let data = TransferOp::from_struct(ParsedTransferOp { amount, signature }).to_bytes();
+
+    assert!(ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, None, None, None, None,
+        data,
+        mem_indices.clone(),
+        mem_banks.clone(),
+    ).is_err());
+
+    // The fee-collector account must be provided
+    let hash = create_fee_message(&hash, &fee_ctx.account.owner, fee);
+    let signature = vta_a_ctx
+        .key
+        .sign_message(hash.as_ref())
+        .as_ref()
+        .try_into()
+        .unwrap();
+    let data = TransferOp::from_struct(ParsedTransferOp { amount, signature }).to_bytes();
+
+    assert!(ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, None, None, None, None,
+        data.clone(),
+        mem_indices[..3].to_vec(),
+        mem_banks[..3].to_vec(),
+    ).is_err());
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, None, None, None, None,
+        data,
+        mem_indices,
+        mem_banks,
+    )
+    .unwrap();
+
+    let src_vta = ctx.get_virtual_timelock(mem_b, vta_a_ctx.index);
+    let dst_vta = ctx.get_virtual_timelock(mem_b, vta_b_ctx.index);
+    let fee_vta = ctx.get_virtual_timelock(mem_b, fee_ctx.index);
+    assert_eq!(src_vta.balance, deposit_amount - amount - fee);
+    assert_eq!(dst_vta.balance, amount);
+    assert_eq!(fee_vta.balance, fee);
+}
+
+#[test]
+fn run_self_transfer_with_fee() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_ctx = ctx.create_timelock_account(mem_b, 0);
+    let fee_ctx = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    let deposit_amount = 2_000;
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_ctx, deposit_amount)
+        .unwrap();
+
+    let ix = vm_set_fee_config(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        fee_ctx.account.owner,
+        2,
+        100,
+    );
+    ctx.ix_send(&[ix]).unwrap();
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    let amount = 1_000;
+    let fee = vm.get_fee(amount).unwrap();
+
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &vta_ctx.account,
+        &vta_ctx.account,
+        &vdn_ctx.account,
+        amount,
+    );
+    let hash = create_fee_message(&hash, &fee_ctx.account.owner, fee);
+    let signature = vta_ctx
+        .key
+        .sign_message(hash.as_ref())
+        .as_ref()
+        .try_into()
+        .unwrap();
+    let data = TransferOp::from_struct(ParsedTransferOp { amount, signature }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, None, None, None, None,
+        data,
+        vec![vdn_ctx.index, vta_ctx.index, vta_ctx.index, fee_ctx.index],
+        vec![0, 1, 1, 1],
+    )
+    .unwrap();
+
+    // Only the fee leaves the account, and no tokens are created
+    let vta = ctx.get_virtual_timelock(mem_b, vta_ctx.index);
+    let fee_vta = ctx.get_virtual_timelock(mem_b, fee_ctx.index);
+    assert_eq!(vta.balance, deposit_amount - fee);
+    assert_eq!(fee_vta.balance, fee);
+    assert_eq!(vta.balance + fee_vta.balance, deposit_amount);
+}
+
+#[test]
+fn run_payout_with_fee() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_src_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_dst_a_ctx = ctx.create_timelock_account(mem_b, 1);
+    let vta_dst_b_ctx = ctx.create_timelock_account(mem_b, 2);
+    let fee_ctx = ctx.create_timelock_account(mem_b, 3);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    let deposit_amount = 2_000;
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_src_ctx, deposit_amount)
+        .unwrap();
+
+    let ix = vm_set_fee_config(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        fee_ctx.account.owner,
+        2,
+        100,
+    );
+    ctx.ix_send(&[ix]).unwrap();
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    let amounts = vec![300, 700];
+    let fee = vm.get_fee(1_000).unwrap();
+
+    let payouts = vec![
+        (vta_dst_a_ctx.account.owner, amounts[0]),
+        (vta_dst_b_ctx.account.owner, amounts[1]),
+    ];
+    let hash = create_payout_message(&ctx.vm, &vta_src_ctx.account, &payouts, &vdn_ctx.account);
+    let hash = create_fee_message(&hash, &fee_ctx.account.owner, fee);
+    let signature = Signature::new(vta_src_ctx.key.sign_message(hash.as_ref()).as_ref());
+
+    let data = PayoutOp::try_to_bytes(PayoutOpData { signature, amounts }).unwrap();
+    let mem_indices = vec![
+        vdn_ctx.index,
+        vta_src_ctx.index,
+        vta_dst_a_ctx.index,
+        vta_dst_b_ctx.index,
+        fee_ctx.index,
+    ];
+
+    // The fee-collector account must be provided
+    assert!(ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, None, None, None, None,
+        data.clone(),
+        mem_indices[..4].to_vec(),
+        vec![0, 1, 1, 1],
+    ).is_err());
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, None, None, None, None,
+        data,
+        mem_indices,
+        vec![0, 1, 1, 1, 1],
+    )
+    .unwrap();
+
+    let src_vta = ctx.get_virtual_timelock(mem_b, vta_src_ctx.index);
+    let fee_vta = ctx.get_virtual_timelock(mem_b, fee_ctx.index);
+    assert_eq!(src_vta.balance, deposit_amount - 1_000 - fee);
+    assert_eq!(ctx.get_virtual_timelock(mem_b, vta_dst_a_ctx.index).balance, 300);
+    assert_eq!(ctx.get_virtual_timelock(mem_b, vta_dst_b_ctx.index).balance, 700);
+    assert_eq!(fee_vta.balance, fee);
+}
+
+#[test]
+fn run_transfer_from_with_fee() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let mem_c = ctx.create_memory(10, VirtualAllowanceAccount::LEN + 1, "mem_allowance_0");
+
+    let owner_ctx = ctx.create_timelock_account(mem_b, 0);
+    let merchant_ctx = ctx.create_timelock_account(mem_b, 1);
+    let fee_ctx = ctx.create_timelock_account(mem_b, 2);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+    let delegate = create_keypair();
+
+    ctx.deposit_tokens_to_timelock(mem_b, &owner_ctx, 2_000)
+        .unwrap();
+
+    // Approve before the fee is turned on
+    let expires_at = ctx.svm.get_sysvar::<Clock>().unix_timestamp + 3600;
+    let hash = create_approve_message(
+        &ctx.vm,
+        &owner_ctx.account,
+        &delegate.pubkey(),
+        1_012,
+        expires_at,
+        &vdn_ctx.account,
+    );
+    let signature = owner_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let data = ApproveOp::from_struct(ParsedApproveOp {
+        signature,
+        amount: 1_012,
+        expires_at,
+        delegate: delegate.pubkey(),
+    }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), Some(mem_c), None],
+        None, None, None, None, None,
+        data,
+        vec![vdn_ctx.index, owner_ctx.index, 0],
+        vec![0, 1, 2],
+    )
+    .unwrap();
+
+    let ix = vm_set_fee_config(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        fee_ctx.account.owner,
+        2,
+        100,
+    );
+    ctx.ix_send(&[ix]).unwrap();
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    let amount = 1_000;
+    let fee = vm.get_fee(amount).unwrap();
+    assert_eq!(fee, 12);
+
+    let vdn = get_virtual_nonce(&ctx.svm, mem_a, vdn_ctx.index);
+    let allowance = ctx.get_virtual_allowance(mem_c, 0);
+    let hash = create_transfer_from_message(
+        &ctx.vm,
+        &allowance,
+        &owner_ctx.account,
+        &merchant_ctx.account,
+        &vdn,
+        amount,
+    );
+
+    let mem_indices = vec![vdn_ctx.index, 0, owner_ctx.index, merchant_ctx.index, fee_ctx.index];
+    let mem_banks = vec![0, 2, 1, 1, 1];
+
+    // A signature that doesn't cover the fee is rejected
+    let signature = delegate.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let data = TransferFromOp::from_struct(ParsedTransferFromOp { signature, amount }).to_bytes();
+
+    assert!(ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), Some(mem_c), None],
+        None, None, None, None, None,
+        data,
+        mem_indices.clone(),
+        mem_banks.clone(),
+    ).is_err());
+
+    let hash = create_fee_message(&hash, &fee_ctx.account.owner, fee);
+    let signature = delegate.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let data = TransferFromOp::from_struct(ParsedTransferFromOp { signature, amount }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), Some(mem_c), None],
+        None, None, None, None, None,
+        data,
+        mem_indices,
+        mem_banks,
+    )
+    .unwrap();
+
+    // The fee is spent from the allowance too, which is now used up
+    assert!(!ctx.has_virtual_account(mem_c, 0));
+
+    let owner_vta = ctx.get_virtual_timelock(mem_b, owner_ctx.index);
+    let merchant_vta = ctx.get_virtual_timelock(mem_b, merchant_ctx.index);
+    let fee_vta = ctx.get_virtual_timelock(mem_b, fee_ctx.index);
+    assert_eq!(owner_vta.balance, 2_000 - amount - fee);
+    assert_eq!(merchant_vta.balance, amount);
+    assert_eq!(fee_vta.balance, fee);
+}
+
+#[test]
+fn run_self_transfer_from_with_fee() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let mem_c = ctx.create_memory(10, VirtualAllowanceAccount::LEN + 1, "mem_allowance_0");
+
+    let owner_ctx = ctx.create_timelock_account(mem_b, 0);
+    let fee_ctx = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+    let delegate = create_keypair();
+
+    ctx.deposit_tokens_to_timelock(mem_b, &owner_ctx, 2_000)
+        .unwrap();
+
+    let expires_at = ctx.svm.get_sysvar::<Clock>().unix_timestamp + 3600;
+    let hash = create_approve_message(
+        &ctx.vm,
+        &owner_ctx.account,
+        &delegate.pubkey(),
+        1_500,
+        expires_at,
+        &vdn_ctx.account,
+    );
+    let signature = owner_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let data = ApproveOp::from_struct(ParsedApproveOp {
+        signature,
+        amount: 1_500,
+        expires_at,
+        delegate: delegate.pubkey(),
+    }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), Some(mem_c), None],
+        None, None, None, None, None,
+        data,
+        vec![vdn_ctx.index, owner_ctx.index, 0],
+        vec![0, 1, 2],
+    )
+    .unwrap();
+
+    let ix = vm_set_fee_config(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        fee_ctx.account.owner,
+        2,
+        100,
+    );
+    ctx.ix_send(&[ix]).unwrap();
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    let amount = 1_000;
+    let fee = vm.get_fee(amount).unwrap();
+
+    let vdn = get_virtual_nonce(&ctx.svm, mem_a, vdn_ctx.index);
+    let allowance = ctx.get_virtual_allowance(mem_c, 0);
+    let hash = create_transfer_from_message(
+        &ctx.vm,
+        &allowance,
+        &owner_ctx.account,
+        &owner_ctx.account,
+        &vdn,
+        amount,
+    );
+    let hash = create_fee_message(&hash, &fee_ctx.account.owner, fee);
+    let signature = delegate.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let data = TransferFromOp::from_struct(ParsedTransferFromOp { signature, amount }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), Some(mem_c), None],
+        None, None, None, None, None,
+        data,
+        vec![vdn_ctx.index, 0, owner_ctx.index, owner_ctx.index, fee_ctx.index],
+        vec![0, 2, 1, 1, 1],
+    )
+    .unwrap();
+
+    // Only the fee leaves the account, and no tokens are created
+    let owner_vta = ctx.get_virtual_timelock(mem_b, owner_ctx.index);
+    let fee_vta = ctx.get_virtual_timelock(mem_b, fee_ctx.index);
+    assert_eq!(owner_vta.balance, 2_000 - fee);
+    assert_eq!(fee_vta.balance, fee);
+    assert_eq!(ctx.get_virtual_allowance(mem_c, 0).remaining, 1_500 - amount - fee);
+}
+
+#[test]
+fn run_set_fee_config_invalid() {
+    let mut ctx = TestContext::new(21);
+    let collector = create_keypair().pubkey();
+
+    // More than 100%
+    let ix = vm_set_fee_config(ctx.payer.pubkey(), ctx.vm_address, collector, 0, 10_001);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    // A fee without a collector
+    let ix = vm_set_fee_config(ctx.payer.pubkey(), ctx.vm_address, Pubkey::default(), 5, 0);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    // Disabling the fee doesn't need a collector
+    let ix = vm_set_fee_config(ctx.payer.pubkey(), ctx.vm_address, Pubkey::default(), 0, 0);
+    ctx.ix_send(&[ix]).unwrap();
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert!(!vm.has_fee());
+}
@@ -0,0 +1,280 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use solana_sdk::signature::{Keypair, Signer};
+use code_vm_api::prelude::*;
+
+struct MultisigSetup {
+    mem_a: Pubkey,
+    mem_b: Pubkey,
+    vdn_ctx: DurableNonceContext,
+    keys: Vec<Keypair>,
+    multisig: MultisigOwner,
+    src_vta: VirtualTimelockAccount,
+    src_index: u16,
+    dst_ctx: TimelockAccountContext,
+}
+
+/// Creates a 2-of-3 multisig held timelock account with `deposit_amount`
+/// tokens, and a regular timelock account to send them to.
+fn setup_multisig(ctx: &mut TestContext, deposit_amount: u64) -> MultisigSetup {
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let keys = vec![create_keypair(), create_keypair(), create_keypair()];
+    let multisig = MultisigOwner {
+        threshold: 2,
+        signers: keys.iter().map(|k| k.pubkey()).collect(),
+    };
+    let owner = multisig.get_address();
+
+    let src_index = 0;
+    ctx.create_timelock_account_with_owner(mem_b, src_index, owner);
+    ctx.deposit_tokens_to_owner(mem_b, owner, src_index, deposit_amount)
+        .unwrap();
+    let src_vta = ctx.get_virtual_timelock(mem_b, src_index);
+
+    let dst_ctx = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    MultisigSetup {
+        mem_a,
+        mem_b,
+        vdn_ctx,
+        keys,
+        multisig,
+        src_vta,
+        src_index,
+        dst_ctx,
+    }
+}
+
+fn sign_all(keys: &[(u8, &Keypair)], hash: &Hash) -> Vec<MultisigSignature> {
+    keys.iter()
+        .map(|(signer_index, key)| MultisigSignature {
+            signer_index: *signer_index,
+            signature: Signature::new(key.sign_message(hash.as_ref()).as_ref()),
+        })
+        .collect()
+}
+
+fn multisig_transfer(
+    ctx: &mut TestContext,
+    setup: &MultisigSetup,
+    signatures: Vec<MultisigSignature>,
+    amount: u64,
+) -> bool {
+    let data = MultisigTransferOp::try_to_bytes(MultisigTransferOpData {
+        multisig: setup.multisig.clone(),
+        signatures,
+        amount,
+    }).unwrap();
+
+    ctx.exec_opcode(
+        [Some(setup.mem_a), Some(setup.mem_b), None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![setup.vdn_ctx.index, setup.src_index, setup.dst_ctx.index],
+        vec![0, 1, 1],
+    )
+    .is_ok()
+}
+
+#[test]
+fn run_multisig_transfer() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_multisig(&mut ctx, 100);
+
+    let amount = 42;
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &setup.src_vta,
+        &setup.dst_ctx.account,
+        &setup.vdn_ctx.account,
+        amount,
+    );
+
+    // 1-of-3 is not enough
+    let sigs = sign_all(&[(0, &setup.keys[0])], &hash);
+    assert!(!multisig_transfer(&mut ctx, &setup, sigs, amount));
+
+    // The same signer twice is not enough
+    let sigs = sign_all(&[(0, &setup.keys[0]), (0, &setup.keys[0])], &hash);
+    assert!(!multisig_transfer(&mut ctx, &setup, sigs, amount));
+
+    // A signature that doesn't match the signer at that index fails
+    let outsider = create_keypair();
+    let sigs = sign_all(&[(0, &setup.keys[0]), (1, &outsider)], &hash);
+    assert!(!multisig_transfer(&mut ctx, &setup, sigs, amount));
+
+    // A different signer set doesn't match the owner
+    let mut other = setup.multisig.clone();
+    other.threshold = 1;
+    let sigs = sign_all(&[(0, &setup.keys[0])], &hash);
+    let data = MultisigTransferOp::try_to_bytes(MultisigTransferOpData {
+        multisig: other,
+        signatures: sigs,
+        amount,
+    }).unwrap();
+    assert!(ctx.exec_opcode(
+        [Some(setup.mem_a), Some(setup.mem_b), None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![setup.vdn_ctx.index, setup.src_index, setup.dst_ctx.index],
+        vec![0, 1, 1],
+    ).is_err());
+
+    // 2-of-3 works with any two signers
+    let sigs = sign_all(&[(2, &setup.keys[2]), (0, &setup.keys[0])], &hash);
+    assert!(multisig_transfer(&mut ctx, &setup, sigs, amount));
+
+    let src_vta = ctx.get_virtual_timelock(setup.mem_b, setup.src_index);
+    let dst_vta = ctx.get_virtual_timelock(setup.mem_b, setup.dst_ctx.index);
+    assert_eq!(src_vta.balance, 100 - amount);
+    assert_eq!(dst_vta.balance, amount);
+}
+
+#[test]
+fn run_multisig_withdraw() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_multisig(&mut ctx, 100);
+
+    let hash = create_withdraw_message(
+        &ctx.vm,
+        &setup.src_vta,
+        &setup.dst_ctx.account,
+        &setup.vdn_ctx.account,
+    );
+    let signatures = sign_all(&[(1, &setup.keys[1]), (2, &setup.keys[2])], &hash);
+
+    let data = MultisigWithdrawOp::try_to_bytes(MultisigWithdrawOpData {
+        multisig: setup.multisig.clone(),
+        signatures,
+    }).unwrap();
+
+    ctx.exec_opcode(
+        [Some(setup.mem_a), Some(setup.mem_b), None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![setup.vdn_ctx.index, setup.src_index, setup.dst_ctx.index],
+        vec![0, 1, 1],
+    )
+    .unwrap();
+
+    let dst_vta = ctx.get_virtual_timelock(setup.mem_b, setup.dst_ctx.index);
+    assert_eq!(dst_vta.balance, 100);
+    assert!(!ctx.has_virtual_account(setup.mem_b, setup.src_index));
+}
+
+#[test]
+fn run_multisig_unlock_and_withdraw() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_multisig(&mut ctx, 100);
+    let owner = setup.multisig.get_address();
+
+    let timelock_address = setup.src_vta.get_timelock_address(
+        &ctx.vm.get_mint(),
+        &ctx.vm.get_authority(),
+        ctx.vm.get_lock_duration(),
+    );
+    let unlock_address = setup.src_vta.get_unlock_address(&timelock_address, &ctx.vm_address);
+    let receipt_address = setup.src_vta.get_withdraw_receipt_address(&unlock_address, &ctx.vm_address);
+
+    // The owner can't sign, the multisig threshold must be met instead
+    let hash = create_init_unlock_message(&ctx.vm_address, &owner);
+    let ix = timelock_multisig_unlock_init(
+        owner,
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        unlock_address,
+        setup.multisig.clone(),
+        sign_all(&[(0, &setup.keys[0])], &hash),
+    );
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    let ix = timelock_multisig_unlock_init(
+        owner,
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        unlock_address,
+        setup.multisig.clone(),
+        sign_all(&[(0, &setup.keys[0]), (2, &setup.keys[2])], &hash),
+    );
+    ctx.ix_send(&[ix]).unwrap();
+
+    let unlock = get_unlock_state(&ctx.svm, unlock_address);
+    assert_eq!(unlock.owner, owner);
+
+    let mut clock = ctx.svm.get_sysvar::<Clock>();
+    clock.unix_timestamp = unlock.unlock_at + 1;
+    ctx.svm.set_sysvar::<Clock>(&clock);
+
+    // Signatures for one action can't be used for another
+    let ix = timelock_multisig_unlock_finalize(
+        owner,
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        unlock_address,
+        setup.multisig.clone(),
+        sign_all(&[(0, &setup.keys[0]), (2, &setup.keys[2])], &hash),
+    );
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    let hash = create_unlock_message(&ctx.vm_address, &owner);
+    let ix = timelock_multisig_unlock_finalize(
+        owner,
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        unlock_address,
+        setup.multisig.clone(),
+        sign_all(&[(0, &setup.keys[0]), (1, &setup.keys[1])], &hash),
+    );
+    ctx.ix_send(&[ix]).unwrap();
+
+    let destination = create_ata(&mut ctx.svm, &ctx.payer, &ctx.mint_pk, &create_keypair().pubkey());
+    let other = create_ata(&mut ctx.svm, &ctx.payer, &ctx.mint_pk, &create_keypair().pubkey());
+
+    let hash = create_withdraw_unlocked_message(&ctx.vm_address, &owner, &destination);
+    let signatures = sign_all(&[(1, &setup.keys[1]), (2, &setup.keys[2])], &hash);
+    let (payer, vm_address, vm_omnibus) = (ctx.payer.pubkey(), ctx.vm_address, ctx.vm.omnibus.vault);
+    let withdraw = |external_address| timelock_multisig_withdraw(
+        owner,
+        payer,
+        vm_address,
+        Some(vm_omnibus),
+        Some(setup.mem_b),
+        None,
+        None,
+        None,
+        unlock_address,
+        Some(receipt_address),
+        external_address,
+        setup.multisig.clone(),
+        signatures.clone(),
+        WithdrawIxData::FromMemory { account_index: setup.src_index },
+    );
+
+    // The signatures only allow withdrawing to the signed destination
+    let ix = withdraw(other);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    let ix = withdraw(destination);
+    ctx.ix_send(&[ix]).unwrap();
+
+    assert_eq!(ctx.get_ata_balance(destination), 100);
+    assert!(!ctx.has_virtual_account(setup.mem_b, setup.src_index));
+}
@@ -0,0 +1,202 @@
+#![cfg(test)]
+
+pub mod utils;
+use utils::*;
+
+use solana_sdk::signature::Signer;
+use code_vm_api::prelude::*;
+
+
+#[test]
+fn run_payout_varied_amounts() {
+    run_payout_test(&[1, 25, 0, 500, 7]);
+}
+
+#[test]
+fn run_payout_single() {
+    run_payout_test(&[100]);
+}
+
+#[test]
+fn run_payout_include_self() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_source = ctx.create_timelock_account(mem_b, 0);
+    let vta_dest = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    let deposit_amount = 100;
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_source, deposit_amount)
+        .unwrap();
+
+    let payouts = vec![
+        (vta_dest.account.owner, 30),
+        (vta_source.account.owner, 20),
+    ];
+
+    let hash = create_payout_message(
+        &ctx.vm,
+        &vta_source.account,
+        &payouts,
+        &vdn_ctx.account,
+    );
+    let signature = Signature::new(vta_source.key.sign_message(hash.as_ref()).as_ref());
+
+    let data = PayoutOp::try_to_bytes(PayoutOpData {
+        signature,
+        amounts: vec![30, 20],
+    }).unwrap();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![vdn_ctx.index, vta_source.index, vta_dest.index, vta_source.index],
+        vec![0, 1, 1, 1],
+    )
+    .unwrap();
+
+    let src_after = ctx.get_virtual_timelock(mem_b, vta_source.index);
+    let dst_after = ctx.get_virtual_timelock(mem_b, vta_dest.index);
+    assert_eq!(src_after.balance, deposit_amount - 30);
+    assert_eq!(dst_after.balance, 30);
+}
+
+#[test]
+fn run_payout_tampered_amounts_fail() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_source = ctx.create_timelock_account(mem_b, 0);
+    let vta_a = ctx.create_timelock_account(mem_b, 1);
+    let vta_b = ctx.create_timelock_account(mem_b, 2);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_source, 100)
+        .unwrap();
+
+    // Sign 10 for A and 40 for B, then try to swap the amounts
+    let payouts = vec![
+        (vta_a.account.owner, 10),
+        (vta_b.account.owner, 40),
+    ];
+
+    let hash = create_payout_message(
+        &ctx.vm,
+        &vta_source.account,
+        &payouts,
+        &vdn_ctx.account,
+    );
+    let signature = Signature::new(vta_source.key.sign_message(hash.as_ref()).as_ref());
+
+    let data = PayoutOp::try_to_bytes(PayoutOpData {
+        signature,
+        amounts: vec![40, 10],
+    }).unwrap();
+
+    let result = ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![vdn_ctx.index, vta_source.index, vta_a.index, vta_b.index],
+        vec![0, 1, 1, 1],
+    );
+    assert!(result.is_err());
+
+    assert_eq!(ctx.get_virtual_timelock(mem_b, vta_source.index).balance, 100);
+    assert_eq!(ctx.get_virtual_timelock(mem_b, vta_a.index).balance, 0);
+    assert_eq!(ctx.get_virtual_timelock(mem_b, vta_b.index).balance, 0);
+}
+
+/// Runs a payout from a single source timelock, where destination `i`
+/// receives `amounts[i]` tokens.
+fn run_payout_test(amounts: &[u64]) {
+    let count = amounts.len();
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(count + 2, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_source = ctx.create_timelock_account(mem_b, 0);
+
+    let mut destinations = Vec::with_capacity(count);
+    for i in 1..=count {
+        let dst_vta = ctx.create_timelock_account(mem_b, i as u16);
+        destinations.push(dst_vta);
+    }
+
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    let total_outflow: u64 = amounts.iter().sum();
+    let deposit_amount = total_outflow + 10;
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_source, deposit_amount)
+        .unwrap();
+
+    let payouts: Vec<_> = destinations
+        .iter()
+        .zip(amounts.iter())
+        .map(|(dst_vta, amount)| (dst_vta.account.owner, *amount))
+        .collect();
+
+    let hash = create_payout_message(
+        &ctx.vm,
+        &vta_source.account,
+        &payouts,
+        &vdn_ctx.account,
+    );
+
+    let signature = Signature::new(
+        vta_source.key.sign_message(hash.as_ref()).as_ref()
+    );
+
+    let data = PayoutOp::try_to_bytes(PayoutOpData {
+        signature,
+        amounts: amounts.to_vec(),
+    }).unwrap();
+
+    let mut mem_indices = vec![vdn_ctx.index, vta_source.index];
+    mem_indices.extend(destinations.iter().map(|d| d.index));
+
+    let mut mem_banks = vec![0, 1]; // 0 for mem_a (nonce), 1 for mem_b (source/dest)
+    mem_banks.extend(std::iter::repeat(1).take(count));
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        mem_indices,
+        mem_banks,
+    )
+    .unwrap();
+
+    let src_after = ctx.get_virtual_timelock(mem_b, vta_source.index);
+    assert_eq!(src_after.balance, deposit_amount - total_outflow);
+
+    for (i, dst) in destinations.iter().enumerate() {
+        let dst_balance = ctx.get_virtual_timelock(mem_b, dst.index).balance;
+        assert_eq!(
+            dst_balance,
+            amounts[i],
+            "Destination #{} did not receive {} tokens",
+            i,
+            amounts[i]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use solana_sdk::signature::Signer;
+use code_vm_api::prelude::*;
+
+#[test]
+fn run_transfer_with_precompile() {
+    // Initialize the test context
+    let mut ctx = TestContext::new(21);
+
+    // Switch the VM over to precompile signature verification
+    let ix = vm_set_sig_verify_mode(ctx.payer.pubkey(), ctx.vm_address, SigVerifyMode::Precompile);
+    ctx.ix_send(&[ix]).unwrap();
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert_eq!(vm.get_sig_verify_mode(), SigVerifyMode::Precompile);
+
+    // Create memory accounts
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    // Create timelock accounts
+    let vta_a_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_b_ctx = ctx.create_timelock_account(mem_b, 1);
+
+    // Create durable nonce account
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    let deposit_amount = 100;
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_a_ctx, deposit_amount)
+        .unwrap();
+
+    let amount = 42;
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &vta_a_ctx.account,
+        &vta_b_ctx.account,
+        &vdn_ctx.account,
+        amount,
+    );
+    let signature = Signature::new(vta_a_ctx.key.sign_message(hash.as_ref()).as_ref());
+
+    let data = TransferOp::from_struct(ParsedTransferOp {
+        amount,
+        signature: signature.into(),
+    }).to_bytes();
+
+    let exec_ix = ctx.get_exec_ix(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![vdn_ctx.index, vta_a_ctx.index, vta_b_ctx.index],
+        vec![0, 1, 1],
+    );
+    let precompile_ix = ed25519_verify(&vta_a_ctx.account.owner, &signature, hash.as_ref());
+
+    // Without the instructions sysvar, the signature can't be checked
+    assert!(ctx.ix_send(&[precompile_ix.clone(), exec_ix.clone()]).is_err());
+
+    // Without the precompile instruction, the signature was never verified
+    let exec_ix = with_instructions_sysvar(exec_ix);
+    assert!(ctx.ix_send(&[exec_ix.clone()]).is_err());
+
+    ctx.ix_send(&[precompile_ix, exec_ix]).unwrap();
+
+    let src_vta = ctx.get_virtual_timelock(mem_b, vta_a_ctx.index);
+    let dst_vta = ctx.get_virtual_timelock(mem_b, vta_b_ctx.index);
+    assert_eq!(src_vta.balance, deposit_amount - amount);
+    assert_eq!(dst_vta.balance, amount);
+}
+
+#[test]
+fn run_set_sig_verify_mode_invalid() {
+    let mut ctx = TestContext::new(21);
+
+    let mut ix = vm_set_sig_verify_mode(ctx.payer.pubkey(), ctx.vm_address, SigVerifyMode::Program);
+    *ix.data.last_mut().unwrap() = 7;
+
+    assert!(ctx.ix_send(&[ix]).is_err());
+}
@@ -0,0 +1,202 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use solana_sdk::signature::Signer;
+use code_vm_api::prelude::*;
+
+const DEPOSIT_AMOUNT: u64 = 1_000;
+const STREAM_AMOUNT: u64 = 600;
+const STREAM_DURATION: i64 = 1_000;
+
+struct StreamSetup {
+    mem_a: Pubkey,
+    mem_b: Pubkey,
+    mem_c: Pubkey,
+    vdn_ctx: DurableNonceContext,
+    funder: TimelockAccountContext,
+    beneficiary: TimelockAccountContext,
+    stream_index: u16,
+    start_time: i64,
+}
+
+/// Creates a stream of `STREAM_AMOUNT` from `funder` to `beneficiary` that
+/// vests over `STREAM_DURATION` seconds, starting now.
+fn setup_stream(ctx: &mut TestContext) -> StreamSetup {
+    let mem_a = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let mem_c = ctx.create_memory(10, VirtualStreamAccount::LEN + 1, "mem_stream_0");
+
+    let funder = ctx.create_timelock_account(mem_b, 0);
+    let beneficiary = ctx.create_timelock_account(mem_b, 1);
+    let mut vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    ctx.deposit_tokens_to_timelock(mem_b, &funder, DEPOSIT_AMOUNT)
+        .unwrap();
+
+    let start_time = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let end_time = start_time + STREAM_DURATION;
+    let stream_index = 0;
+
+    let msg = create_stream_create_message(
+        &ctx.vm,
+        &funder.account,
+        &beneficiary.account.owner,
+        STREAM_AMOUNT,
+        start_time,
+        end_time,
+        &vdn_ctx.account,
+    );
+    let signature = funder.key.sign_message(msg.as_ref()).as_ref().try_into().unwrap();
+
+    let data = StreamCreateOp::from_struct(ParsedStreamCreateOp {
+        signature,
+        amount: STREAM_AMOUNT,
+        start_time,
+        end_time,
+        beneficiary: beneficiary.account.owner,
+    }).to_bytes();
+
+    ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), Some(mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![vdn_ctx.index, funder.index, stream_index],
+        vec![0, 1, 2],
+    )
+    .unwrap();
+
+    // The nonce was advanced by the create opcode
+    vdn_ctx.account = get_virtual_nonce(&ctx.svm, mem_a, vdn_ctx.index);
+
+    StreamSetup {
+        mem_a,
+        mem_b,
+        mem_c,
+        vdn_ctx,
+        funder,
+        beneficiary,
+        stream_index,
+        start_time,
+    }
+}
+
+fn withdraw(ctx: &mut TestContext, setup: &StreamSetup, dst_index: u16) -> bool {
+    let data = StreamWithdrawOp {}.to_bytes();
+
+    ctx.exec_opcode(
+        [None, Some(setup.mem_b), Some(setup.mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![setup.stream_index, dst_index],
+        vec![2, 1],
+    )
+    .is_ok()
+}
+
+#[test]
+fn run_stream_create() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_stream(&mut ctx);
+
+    let stream = ctx.get_virtual_stream(setup.mem_c, setup.stream_index);
+    assert_eq!(stream.funder, setup.funder.account.owner);
+    assert_eq!(stream.beneficiary, setup.beneficiary.account.owner);
+    assert_eq!(stream.start_time, setup.start_time);
+    assert_eq!(stream.end_time, setup.start_time + STREAM_DURATION);
+    assert_eq!(stream.total_amount, STREAM_AMOUNT);
+    assert_eq!(stream.withdrawn, 0);
+
+    let funder_vta = ctx.get_virtual_timelock(setup.mem_b, setup.funder.index);
+    assert_eq!(funder_vta.balance, DEPOSIT_AMOUNT - STREAM_AMOUNT);
+}
+
+#[test]
+fn run_stream_withdraw() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_stream(&mut ctx);
+
+    // Nothing has vested yet
+    assert!(!withdraw(&mut ctx, &setup, setup.beneficiary.index));
+
+    // Halfway through, half of the stream is available
+    set_time(&mut ctx.svm, setup.start_time + STREAM_DURATION / 2);
+
+    // Only the beneficiary can receive the tokens
+    assert!(!withdraw(&mut ctx, &setup, setup.funder.index));
+
+    assert!(withdraw(&mut ctx, &setup, setup.beneficiary.index));
+
+    let beneficiary_vta = ctx.get_virtual_timelock(setup.mem_b, setup.beneficiary.index);
+    assert_eq!(beneficiary_vta.balance, STREAM_AMOUNT / 2);
+
+    let stream = ctx.get_virtual_stream(setup.mem_c, setup.stream_index);
+    assert_eq!(stream.withdrawn, STREAM_AMOUNT / 2);
+
+    // After the end, the rest is available and the stream is closed
+    set_time(&mut ctx.svm, setup.start_time + STREAM_DURATION + 1);
+    assert!(withdraw(&mut ctx, &setup, setup.beneficiary.index));
+
+    let beneficiary_vta = ctx.get_virtual_timelock(setup.mem_b, setup.beneficiary.index);
+    assert_eq!(beneficiary_vta.balance, STREAM_AMOUNT);
+    assert!(!ctx.has_virtual_account(setup.mem_c, setup.stream_index));
+}
+
+#[test]
+fn run_stream_cancel() {
+    let mut ctx = TestContext::new(21);
+    let setup = setup_stream(&mut ctx);
+
+    set_time(&mut ctx.svm, setup.start_time + STREAM_DURATION / 4);
+
+    let stream = ctx.get_virtual_stream(setup.mem_c, setup.stream_index);
+    let msg = create_stream_cancel_message(&stream, &setup.vdn_ctx.account);
+
+    // Only the funder can cancel the stream
+    let bad_signature = setup.beneficiary.key.sign_message(msg.as_ref()).as_ref().try_into().unwrap();
+    let data = StreamCancelOp { signature: bad_signature }.to_bytes();
+    let result = ctx.exec_opcode(
+        [Some(setup.mem_a), Some(setup.mem_b), Some(setup.mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![setup.vdn_ctx.index, setup.stream_index, setup.funder.index, setup.beneficiary.index],
+        vec![0, 2, 1, 1],
+    );
+    assert!(result.is_err());
+
+    let signature = setup.funder.key.sign_message(msg.as_ref()).as_ref().try_into().unwrap();
+    let data = StreamCancelOp { signature }.to_bytes();
+    ctx.exec_opcode(
+        [Some(setup.mem_a), Some(setup.mem_b), Some(setup.mem_c), None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        data,
+        vec![setup.vdn_ctx.index, setup.stream_index, setup.funder.index, setup.beneficiary.index],
+        vec![0, 2, 1, 1],
+    )
+    .unwrap();
+
+    let vested = STREAM_AMOUNT / 4;
+
+    let funder_vta = ctx.get_virtual_timelock(setup.mem_b, setup.funder.index);
+    let beneficiary_vta = ctx.get_virtual_timelock(setup.mem_b, setup.beneficiary.index);
+    assert_eq!(funder_vta.balance, DEPOSIT_AMOUNT - vested);
+    assert_eq!(beneficiary_vta.balance, vested);
+    assert!(!ctx.has_virtual_account(setup.mem_c, setup.stream_index));
+}
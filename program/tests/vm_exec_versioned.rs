@@ -0,0 +1,152 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use litesvm::types::TransactionResult;
+use solana_sdk::signature::Signer;
+use code_vm_api::prelude::*;
+
+fn run_versioned_transfer(
+    signed_vm: Option<Pubkey>,
+    valid_until_offset: i64,
+    advance_clock: i64,
+) -> TransactionResult {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_a_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_b_ctx = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    ctx.deposit_tokens_to_timelock(mem_b, &vta_a_ctx, 100)
+        .unwrap();
+
+    let amount = 42;
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    let valid_until = now + valid_until_offset;
+
+    let hash = create_transfer_message(
+        &ctx.vm,
+        &vta_a_ctx.account,
+        &vta_b_ctx.account,
+        &vdn_ctx.account,
+        amount,
+    );
+    let vm_address = signed_vm.unwrap_or(ctx.vm_address);
+    let hash = create_versioned_message(&vm_address, valid_until, &hash);
+
+    let signature = vta_a_ctx
+        .key
+        .sign_message(hash.as_ref())
+        .as_ref()
+        .try_into()
+        .unwrap();
+
+    if advance_clock != 0 {
+        let mut clock = ctx.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += advance_clock;
+        ctx.svm.set_sysvar::<Clock>(&clock);
+    }
+
+    let mem_indices = vec![vdn_ctx.index, vta_a_ctx.index, vta_b_ctx.index];
+    let mem_banks = vec![0, 1, 1];
+    let data = TransferOp::from_struct(ParsedTransferOp { amount, signature }).to_bytes();
+    let data = append_valid_until(data, valid_until);
+
+    let result = ctx.exec_opcode(
+        [Some(mem_a), Some(mem_b), None, None],
+        None,
+        None,
+        None,
+        None,
+        None,
+        data,
+        mem_indices,
+        mem_banks,
+    );
+
+    if result.is_ok() {
+        let src_vta = ctx.get_virtual_timelock(mem_b, vta_a_ctx.index);
+        let dst_vta = ctx.get_virtual_timelock(mem_b, vta_b_ctx.index);
+        assert_eq!(src_vta.balance, 100 - amount);
+        assert_eq!(dst_vta.balance, amount);
+    }
+
+    result
+}
+
+#[test]
+fn run_versioned_transfer_before_expiry() {
+    assert!(run_versioned_transfer(None, 60, 0).is_ok());
+}
+
+#[test]
+fn run_versioned_transfer_expired() {
+    assert!(run_versioned_transfer(None, 60, 61).is_err());
+}
+
+#[test]
+fn run_versioned_transfer_wrong_vm() {
+    let other_vm = create_keypair().pubkey();
+    assert!(run_versioned_transfer(Some(other_vm), 60, 0).is_err());
+}
+
+fn close_timelock(ctx: &mut TestContext, mem: Pubkey, vta_ctx: &TimelockAccountContext, valid_until: Option<i64>) -> TransactionResult {
+    let va = VirtualAccount::Timelock(ctx.get_virtual_timelock(mem, vta_ctx.index));
+    let hash = create_close_message(&ctx.vm_address, &va);
+    let hash = match valid_until {
+        Some(valid_until) => create_versioned_message(&ctx.vm_address, valid_until, &hash),
+        None => hash,
+    };
+
+    let signature = vta_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let data = CloseTimelockOp { signature }.to_bytes();
+    let data = match valid_until {
+        Some(valid_until) => append_valid_until(data, valid_until),
+        None => data,
+    };
+
+    ctx.exec_opcode(
+        [Some(mem), None, None, None],
+        None,
+        None,
+        None,
+        None,
+        None,
+        data,
+        vec![vta_ctx.index],
+        vec![0],
+    )
+}
+
+#[test]
+fn run_require_versioned() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(10, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let vta_ctx = ctx.create_timelock_account(mem, 0);
+
+    let ix = vm_set_require_versioned(ctx.payer.pubkey(), ctx.vm_address, true);
+    ctx.ix_send(&[ix]).unwrap();
+    assert!(get_vm_account(&ctx.svm, ctx.vm_address).requires_versioned_messages());
+
+    // A v0 message is no longer accepted
+    assert!(close_timelock(&mut ctx, mem, &vta_ctx, None).is_err());
+    assert!(ctx.has_virtual_account(mem, vta_ctx.index));
+
+    let now = ctx.svm.get_sysvar::<Clock>().unix_timestamp;
+    assert!(close_timelock(&mut ctx, mem, &vta_ctx, Some(now + 60)).is_ok());
+    assert!(!ctx.has_virtual_account(mem, vta_ctx.index));
+}
+
+#[test]
+fn run_require_versioned_not_authority() {
+    let mut ctx = TestContext::new(21);
+    let other = create_payer(&mut ctx.svm);
+
+    let ix = vm_set_require_versioned(other.pubkey(), ctx.vm_address, true);
+    assert!(!send_ix(&mut ctx.svm, &other, ix));
+    assert!(!get_vm_account(&ctx.svm, ctx.vm_address).requires_versioned_messages());
+}
@@ -1,6 +1,6 @@
 #![cfg(test)]
 pub mod utils;
-use steel::Discriminator;
+use steel::{Discriminator, Pubkey};
 use utils::*;
 
 use solana_sdk::signature::Signer;
@@ -95,3 +95,86 @@ fn run_transfer_on_legacy_memory() {
     )
     .unwrap();
 }
+
+fn set_legacy(ctx: &mut TestContext, mem: Pubkey, layout: LegacyMemoryLayout) {
+    let mut info = ctx.svm.get_account(&mem).unwrap();
+    let mut header = MemoryAccount::unpack(&info.data);
+
+    header.version = MemoryVersion::Legacy as u8;
+    header.packed_info = [0, 0, 0, 0, 0, layout as u8];
+
+    // Skip the 8 byte discriminator
+    info.data[8..MemoryAccount::get_size()].copy_from_slice(header.to_bytes());
+    ctx.svm.set_account(mem, info).unwrap();
+}
+
+#[test]
+fn run_migrate_legacy_memory() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+
+    let vta_ctx = ctx.create_timelock_account(mem, 42);
+    set_legacy(&mut ctx, mem, LegacyMemoryLayout::Timelock);
+
+    // Migrate in chunks, the progress is kept on the memory account
+    let ix = vm_memory_migrate(ctx.payer.pubkey(), ctx.vm_address, mem, 40);
+    let meta = ctx.ix_send(&[ix]).unwrap();
+
+    let event = get_program_data(&meta)
+        .iter()
+        .find_map(|data| match ParsedEvent::try_from_bytes(data) {
+            Ok(ParsedEvent::MemoryMigrate(event)) => Some(event),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(event, MemoryMigrateEvent { vm_memory: mem, migrated: 40, capacity: 100 });
+
+    let memory = get_memory_account(&ctx.svm, mem);
+    assert_eq!(memory.get_version(), MemoryVersion::Legacy);
+    assert_eq!(memory.get_migration_cursor(), 40);
+
+    let ix = vm_memory_migrate(ctx.payer.pubkey(), ctx.vm_address, mem, 30);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert_eq!(get_memory_account(&ctx.svm, mem).get_migration_cursor(), 70);
+
+    let ix = vm_memory_migrate(ctx.payer.pubkey(), ctx.vm_address, mem, 50);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    let memory = get_memory_account(&ctx.svm, mem);
+    assert_eq!(memory.get_version(), MemoryVersion::Current);
+    assert_eq!(memory.packed_info, [0; 6]);
+    assert_eq!(ctx.get_virtual_timelock(mem, vta_ctx.index).owner, vta_ctx.account.owner);
+
+    // Current memory accounts can't be migrated again
+    let ix = vm_memory_migrate(ctx.payer.pubkey(), ctx.vm_address, mem, 100);
+    assert!(ctx.ix_send(&[ix]).is_err());
+}
+
+#[test]
+fn run_migrate_legacy_memory_with_invalid_account() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_mixed_0");
+
+    ctx.create_durable_nonce_account(mem, 7);
+    set_legacy(&mut ctx, mem, LegacyMemoryLayout::Timelock);
+
+    let ix = vm_memory_migrate(ctx.payer.pubkey(), ctx.vm_address, mem, 100);
+    assert!(ctx.ix_send(&[ix]).is_err());
+    assert_eq!(get_memory_account(&ctx.svm, mem).get_version(), MemoryVersion::Legacy);
+}
+
+#[test]
+fn run_write_wrong_type_to_legacy_memory() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    set_legacy(&mut ctx, mem, LegacyMemoryLayout::Timelock);
+
+    // A legacy timelock memory account can't be given a nonce
+    let owner = create_keypair().pubkey();
+    let (svm, payer) = (&mut ctx.svm, &ctx.payer);
+    assert!(tx_create_virtual_nonce(svm, payer, ctx.vm_address, mem, owner, 0).is_err());
+    assert!(!ctx.has_virtual_account(mem, 0));
+
+    ctx.create_timelock_account(mem, 0);
+    assert!(ctx.has_virtual_account(mem, 0));
+}
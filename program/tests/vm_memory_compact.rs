@@ -0,0 +1,43 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::prelude::*;
+use solana_sdk::signer::Signer;
+
+#[test]
+fn run_mem_compact_test() {
+    let mut ctx = TestContext::new(21);
+
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN + 1;
+    let mem = ctx.create_memory(capacity, account_size, "mem_nonce_0");
+
+    let vdn_a = ctx.create_durable_nonce_account(mem, 2);
+    let vdn_b = ctx.create_durable_nonce_account(mem, 40);
+    let vdn_c = ctx.create_durable_nonce_account(mem, 99);
+
+    // There must be enough room left for every live account
+    let ix = vm_memory_compact(ctx.payer.pubkey(), ctx.vm_address, mem, 2);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    let rent_before = ctx.svm.get_account(&mem).unwrap().lamports;
+    let authority_before = ctx.svm.get_account(&ctx.payer.pubkey()).unwrap().lamports;
+
+    let ix = vm_memory_compact(ctx.payer.pubkey(), ctx.vm_address, mem, 3);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    let mem_account = ctx.svm.get_account(&mem).unwrap();
+    assert_eq!(mem_account.data.len(), MemoryAccount::get_size_with_data(3, account_size));
+    assert_eq!(get_memory_account(&ctx.svm, mem).get_capacity(), 3);
+
+    // The freed rent goes back to the authority (less the transaction fee)
+    let refund = rent_before - mem_account.lamports;
+    assert!(refund > 0);
+    assert!(ctx.svm.get_account(&ctx.payer.pubkey()).unwrap().lamports > authority_before);
+
+    // Accounts below the new capacity stay put, the others fill the free slots
+    assert_eq!(get_virtual_nonce(&ctx.svm, mem, 2).address, vdn_a.account.address);
+    assert_eq!(get_virtual_nonce(&ctx.svm, mem, 0).address, vdn_b.account.address);
+    assert_eq!(get_virtual_nonce(&ctx.svm, mem, 1).address, vdn_c.account.address);
+}
@@ -0,0 +1,73 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::prelude::*;
+use solana_sdk::signature::Signer;
+
+#[test]
+fn run_migrate_legacy_vm() {
+    let mut ctx = TestContext::new(21);
+
+    // Current VM accounts can't be migrated
+    let ix = vm_migrate(ctx.payer.pubkey(), ctx.vm_address);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    // Change the VM account to the legacy size, the fields added since then
+    // are all zero on a new VM
+    let mut info = ctx.svm.get_account(&ctx.vm_address).unwrap();
+    info.data.truncate(CodeVmAccount::get_legacy_size());
+    ctx.svm.set_account(ctx.vm_address, info).unwrap();
+
+    // A legacy VM can't be loaded until it has been migrated
+    let ix = vm_set_pause(ctx.payer.pubkey(), ctx.vm_address, true, &[]);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    // Only the VM authority can migrate it
+    let other = create_payer(&mut ctx.svm);
+    let ix = vm_migrate(other.pubkey(), ctx.vm_address);
+    assert!(!send_ix(&mut ctx.svm, &other, ix));
+
+    let ix = vm_migrate(ctx.payer.pubkey(), ctx.vm_address);
+    ctx.ix_send(&[ix]).unwrap();
+
+    let info = ctx.svm.get_account(&ctx.vm_address).unwrap();
+    assert_eq!(info.data.len(), CodeVmAccount::get_size());
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert_eq!(vm.authority, ctx.vm.authority);
+    assert_eq!(vm.omnibus, ctx.vm.omnibus);
+    assert_eq!(vm.get_current_authority(), ctx.vm.authority);
+    assert_eq!(vm.get_sig_verify_mode(), SigVerifyMode::Program);
+    assert!(!vm.has_fee());
+    assert!(!vm.is_paused());
+
+    // The VM is usable again
+    let ix = vm_set_pause(ctx.payer.pubkey(), ctx.vm_address, true, &[]);
+    ctx.ix_send(&[ix]).unwrap();
+}
+
+#[test]
+fn run_set_fee_on_legacy_vm() {
+    let mut ctx = TestContext::new(21);
+    let fee_collector = create_keypair().pubkey();
+
+    let mut info = ctx.svm.get_account(&ctx.vm_address).unwrap();
+    info.data.truncate(CodeVmAccount::get_legacy_size());
+    ctx.svm.set_account(ctx.vm_address, info).unwrap();
+
+    // The fee fields don't exist on a legacy VM
+    let ix = vm_set_fee_config(ctx.payer.pubkey(), ctx.vm_address, fee_collector, 2, 100);
+    assert!(ctx.ix_send(&[ix.clone()]).is_err());
+
+    let ix_migrate = vm_migrate(ctx.payer.pubkey(), ctx.vm_address);
+    ctx.ix_send(&[ix_migrate]).unwrap();
+
+    ctx.svm.expire_blockhash();
+    ctx.ix_send(&[ix]).unwrap();
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert!(vm.has_fee());
+    assert_eq!(vm.fee_collector, fee_collector);
+    assert_eq!((vm.fee_flat, vm.fee_bps), (2, 100));
+}
@@ -0,0 +1,127 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use steel::*;
+use code_vm_api::prelude::*;
+use solana_sdk::signer::Signer;
+
+fn exec_close_nonce(ctx: &mut TestContext, mem: Pubkey, index: u16) -> bool {
+    ctx.exec_opcode(
+        [Some(mem), None, None, None],
+        None, // vm_omnibus
+        None, // relay
+        None, // relay_vault
+        None, // external_address
+        None, // token_program
+        CloseNonceOp {}.to_bytes(),
+        vec![index],
+        vec![0],
+    )
+    .is_ok()
+}
+
+#[test]
+fn run_pause_vm() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let vdn_ctx = ctx.create_durable_nonce_account(mem, 0);
+
+    // Only the authority can pause the VM
+    let other = create_payer(&mut ctx.svm);
+    let ix = vm_set_pause(other.pubkey(), ctx.vm_address, true, &[]);
+    assert!(!send_ix(&mut ctx.svm, &other, ix));
+
+    let ix = vm_set_pause(ctx.payer.pubkey(), ctx.vm_address, true, &[]);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert!(get_vm_account(&ctx.svm, ctx.vm_address).is_paused());
+
+    // Nothing can be created or executed while paused
+    let (svm, payer) = (&mut ctx.svm, &ctx.payer);
+    assert!(tx_create_virtual_nonce(svm, payer, ctx.vm_address, mem, create_keypair().pubkey(), 1).is_err());
+    assert!(!exec_close_nonce(&mut ctx, mem, vdn_ctx.index));
+
+    let ix = vm_set_pause(ctx.payer.pubkey(), ctx.vm_address, false, &[]);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert!(!get_vm_account(&ctx.svm, ctx.vm_address).is_paused());
+
+    // Same transaction as before, so it needs a fresh blockhash
+    ctx.svm.expire_blockhash();
+    assert!(exec_close_nonce(&mut ctx, mem, vdn_ctx.index));
+}
+
+#[test]
+fn run_disable_opcode() {
+    let mut ctx = TestContext::new(21);
+    let mem = ctx.create_memory(10, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let vdn_ctx = ctx.create_durable_nonce_account(mem, 0);
+
+    let ix = vm_set_pause(ctx.payer.pubkey(), ctx.vm_address, false, &[Opcode::CloseNonceOp]);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert!(!vm.is_opcode_enabled(Opcode::CloseNonceOp as u8));
+    assert!(vm.is_opcode_enabled(Opcode::TransferOp as u8));
+
+    // The rest of the VM keeps working
+    let (svm, payer) = (&mut ctx.svm, &ctx.payer);
+    assert!(tx_create_virtual_nonce(svm, payer, ctx.vm_address, mem, create_keypair().pubkey(), 1).is_ok());
+    assert!(!exec_close_nonce(&mut ctx, mem, vdn_ctx.index));
+
+    let ix = vm_set_pause(ctx.payer.pubkey(), ctx.vm_address, false, &[]);
+    assert!(ctx.ix_send(&[ix]).is_ok());
+
+    ctx.svm.expire_blockhash();
+    assert!(exec_close_nonce(&mut ctx, mem, vdn_ctx.index));
+}
+
+#[test]
+fn run_withdraw_while_paused() {
+    let (mut svm, payer, _mint_owner, mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let (vm_memory, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, 100, VirtualTimelockAccount::LEN + 1, "test");
+
+    let account_index = 7;
+    let (vta, vta_key) =
+        create_timelock(&mut svm, &payer, vm_address, vm_memory, account_index);
+
+    let dest_key = create_keypair();
+    let destination = create_ata(&mut svm, &payer, &mint_pk, &dest_key.pubkey());
+
+    assert!(send_ix(&mut svm, &payer, vm_set_pause(payer.pubkey(), vm_address, true, &[])));
+
+    let vm = get_vm_account(&svm, vm_address);
+    let timelock_address = vta.get_timelock_address(
+        &vm.get_mint(),
+        &vm.get_authority(),
+        vm.get_lock_duration()
+    );
+
+    let unlock_address = vta.get_unlock_address(&timelock_address, &vm_address);
+    let receipt_address = vta.get_withdraw_receipt_address(&unlock_address, &vm_address);
+
+    // The non-custodial exit is never blocked by a pause
+    assert!(tx_unlock_init(&mut svm, &payer, &vta_key, vm_address, unlock_address).is_ok());
+
+    let unlock = get_unlock_state(&svm, unlock_address);
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp = unlock.unlock_at + 1;
+    svm.set_sysvar::<Clock>(&clock);
+
+    assert!(tx_unlock_finalize(&mut svm, &payer, &vta_key, vm_address, unlock_address).is_ok());
+
+    assert!(tx_withdraw_from_memory(
+        &mut svm,
+        &payer,
+        &vta_key,
+        vm_address,
+        vm.omnibus.vault,
+        vm_memory,
+        unlock_address,
+        receipt_address,
+        destination,
+        WithdrawIxData::FromMemory { account_index }
+    ).is_ok());
+}
@@ -0,0 +1,180 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::{prelude::*, utils::get_storage_leaf_value};
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+
+fn load_simulator(ctx: &TestContext, mems: &[Pubkey], storage: Pubkey) -> VmSimulator {
+    let vm_info = ctx.svm.get_account(&ctx.vm_address).unwrap();
+    let mut sim = VmSimulator::from_account_data(ctx.vm_address, &vm_info.data).unwrap();
+
+    for mem in mems {
+        let info = ctx.svm.get_account(mem).unwrap();
+        sim.add_memory(*mem, &info.data).unwrap();
+    }
+
+    let info = ctx.svm.get_account(&storage).unwrap();
+    sim.add_storage(storage, &info.data).unwrap();
+
+    sim
+}
+
+fn assert_same_state(ctx: &TestContext, sim: &VmSimulator, mem: Pubkey, indices: &[u16]) {
+    let vm = get_vm_account(&ctx.svm, ctx.vm_address);
+    assert_eq!(sim.vm.poh, vm.poh);
+    assert_eq!(sim.vm.slot, vm.slot);
+
+    for index in indices {
+        let expected = get_virtual_account_data(&ctx.svm, mem, *index);
+        let actual = sim.get_memory(&mem).unwrap().get_packed(*index);
+        assert_eq!(actual, expected.as_deref());
+    }
+}
+
+#[test]
+fn run_simulator_matches_program() {
+    let mut ctx = TestContext::new(21);
+
+    let mem_a = ctx.create_memory(100, VirtualDurableNonce::LEN + 1, "mem_nonce_0");
+    let mem_b = ctx.create_memory(100, VirtualTimelockAccount::LEN + 1, "mem_timelock_0");
+    let (storage, _) = create_storage_account(&mut ctx.svm, &ctx.payer, ctx.vm_address, "storage_0");
+
+    let vta_a_ctx = ctx.create_timelock_account(mem_b, 0);
+    let vta_b_ctx = ctx.create_timelock_account(mem_b, 1);
+    let vdn_ctx = ctx.create_durable_nonce_account(mem_a, 0);
+
+    assert!(ctx.deposit_tokens_to_timelock(mem_b, &vta_a_ctx, 100).is_ok());
+
+    let mut sim = load_simulator(&ctx, &[mem_a, mem_b], storage);
+    let indices = [vdn_ctx.index, vta_a_ctx.index, vta_b_ctx.index];
+
+    // Transfer, signed against the state the simulator holds
+    let transfer = |ctx: &mut TestContext, sim: &VmSimulator, amount: u64| {
+        let vta_a = sim.get_virtual_account(&mem_b, vta_a_ctx.index).unwrap();
+        let vta_b = sim.get_virtual_account(&mem_b, vta_b_ctx.index).unwrap();
+        let vdn = sim.get_virtual_account(&mem_a, vdn_ctx.index).unwrap();
+
+        let hash = create_transfer_message(
+            &sim.vm,
+            &vta_a.into_inner_timelock().unwrap(),
+            &vta_b.into_inner_timelock().unwrap(),
+            &vdn.into_inner_nonce().unwrap(),
+            amount,
+        );
+        let signature = vta_a_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+        let data = TransferOp::from_struct(ParsedTransferOp { amount, signature }).to_bytes();
+
+        ctx.get_exec_ix(
+            [Some(mem_a), Some(mem_b), None, None],
+            None, None, None, None, None,
+            data,
+            indices.to_vec(),
+            vec![0, 1, 1],
+        )
+    };
+
+    let ix = transfer(&mut ctx, &sim, 42);
+    assert!(sim.process_instruction(&ix).is_ok());
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert_same_state(&ctx, &sim, mem_b, &indices);
+    assert_same_state(&ctx, &sim, mem_a, &[vdn_ctx.index]);
+
+    // A failing transfer fails the same way and changes nothing
+    let ix = transfer(&mut ctx, &sim, 1_000);
+    let before = sim.clone();
+    assert_eq!(
+        sim.process_instruction(&ix),
+        Err(CodeVmError::InsufficientFunds.into()),
+    );
+    assert_eq!(sim.vm.poh, before.vm.poh);
+    assert!(ctx.ix_send(&[ix]).is_err());
+
+    // Withdraw the rest of the source
+    let vta_a = sim.get_virtual_account(&mem_b, vta_a_ctx.index).unwrap();
+    let vta_b = sim.get_virtual_account(&mem_b, vta_b_ctx.index).unwrap();
+    let vdn = sim.get_virtual_account(&mem_a, vdn_ctx.index).unwrap();
+    let hash = create_withdraw_message(
+        &sim.vm,
+        &vta_a.into_inner_timelock().unwrap(),
+        &vta_b.into_inner_timelock().unwrap(),
+        &vdn.into_inner_nonce().unwrap(),
+    );
+    let signature = vta_a_ctx.key.sign_message(hash.as_ref()).as_ref().try_into().unwrap();
+    let ix = ctx.get_exec_ix(
+        [Some(mem_a), Some(mem_b), None, None],
+        None, None, None, None, None,
+        WithdrawOp { signature }.to_bytes(),
+        indices.to_vec(),
+        vec![0, 1, 1],
+    );
+
+    assert!(sim.process_instruction(&ix).is_ok());
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert_same_state(&ctx, &sim, mem_b, &indices);
+    assert_eq!(
+        sim.get_virtual_account(&mem_b, vta_b_ctx.index).unwrap().get_balance(),
+        100,
+    );
+
+    // Compress the destination, then decompress it at another index
+    let va = sim.get_virtual_account(&mem_b, vta_b_ctx.index).unwrap();
+    let va_hash = va.get_hash();
+    let sig = Signature::new(ctx.payer.sign_message(va_hash.as_ref()).as_ref());
+    let sig_hash = get_storage_leaf_value(&sig, &va_hash, 0);
+
+    let ix = system_account_compress(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        mem_b,
+        storage,
+        vta_b_ctx.index,
+        sig,
+    );
+    assert!(sim.process_instruction(&ix).is_ok());
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert_same_state(&ctx, &sim, mem_b, &indices);
+    assert_eq!(
+        sim.get_storage(&storage).unwrap().compressed_state.get_root(),
+        get_storage_account(&ctx.svm, storage).compressed_state.get_root(),
+    );
+
+    let mut tree = MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::new(&[
+        MERKLE_TREE_SEED,
+        create_name("storage_0").as_ref(),
+        ctx.vm_address.as_ref(),
+    ]);
+    assert!(tree.try_insert(sig_hash).is_ok());
+    let proof = tree.get_merkle_proof(&[sig_hash], 0);
+
+    let vta = va.into_inner_timelock().unwrap();
+    let timelock_address = vta.get_timelock_address(
+        &ctx.vm.get_mint(),
+        &ctx.vm.get_authority(),
+        ctx.vm.get_lock_duration(),
+    );
+    let unlock_address = vta.get_unlock_address(&timelock_address, &ctx.vm_address);
+    let receipt_address = vta.get_withdraw_receipt_address(&unlock_address, &ctx.vm_address);
+
+    let ix = system_account_decompress(
+        ctx.payer.pubkey(),
+        ctx.vm_address,
+        mem_b,
+        storage,
+        Some(unlock_address),
+        Some(receipt_address),
+        42,
+        va.pack(),
+        0,
+        0,
+        proof,
+        sig,
+    );
+    assert!(sim.process_instruction(&ix).is_ok());
+    assert!(ctx.ix_send(&[ix]).is_ok());
+    assert_same_state(&ctx, &sim, mem_b, &[vta_a_ctx.index, vta_b_ctx.index, 42]);
+    assert_eq!(
+        sim.get_storage(&storage).unwrap().compressed_state.get_root(),
+        get_storage_account(&ctx.svm, storage).compressed_state.get_root(),
+    );
+}
@@ -15,7 +15,7 @@ fn run_storage_init_test() {
         create_storage_account(&mut svm, &payer, vm_address, name);
 
     let storage_account = svm.get_account(&vm_storage_address).unwrap();
-    assert!(storage_account.data.len() == StorageAccount::get_size());
+    assert!(storage_account.data.len() == StorageAccount::get_size_with_changelog());
 
     let storage = get_storage_account(&svm, vm_storage_address);
     assert!(storage.vm == vm_address);
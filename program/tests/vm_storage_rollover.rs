@@ -0,0 +1,180 @@
+#![cfg(test)]
+pub mod utils;
+use utils::*;
+
+use code_vm_api::{prelude::*, utils::get_storage_leaf_value};
+use litesvm::LiteSVM;
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+
+fn get_archived_root(svm: &LiteSVM, vm_storage: Pubkey, tree_id: u16) -> Hash {
+    let data = svm.get_account(&vm_storage).unwrap().data;
+    let offset = StorageAccount::get_size_with_archived_trees(tree_id as usize);
+    Hash::new(&data[offset..offset + 32])
+}
+
+#[test]
+fn run_storage_rollover() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN+1;
+
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, capacity, account_size, name);
+
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    let new_tree = || MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::new(&[
+        MERKLE_TREE_SEED,
+        create_name(name).as_ref(),
+        vm_address.as_ref()
+    ]);
+
+    let compress = |svm: &mut LiteSVM, account_index: u16, leaf_index: u64| {
+        let owner = create_keypair().pubkey();
+        assert!(tx_create_virtual_nonce(svm, &payer, vm_address, vm_mem_address, owner, account_index).is_ok());
+
+        let va = get_virtual_account(svm, vm_mem_address, account_index);
+        let va_hash = va.get_hash();
+        let sig = Signature::new(payer.sign_message(va_hash.as_ref()).as_ref());
+        let sig_hash = get_storage_leaf_value(&sig, &va_hash, leaf_index);
+
+        assert!(tx_account_compress(svm, &payer, vm_address, vm_mem_address, vm_storage_address, account_index, sig).is_ok());
+
+        (va, sig, sig_hash)
+    };
+
+    let rollover = vm_storage_rollover(payer.pubkey(), vm_address, vm_storage_address);
+
+    // There is nothing to archive yet
+    assert!(!send_ix(&mut svm, &payer, rollover.clone()));
+
+    let mut tree_0 = new_tree();
+    let (va_a, sig_a, sig_hash_a) = compress(&mut svm, 0, 0);
+    assert!(tree_0.try_insert(sig_hash_a).is_ok());
+
+    // Only the VM authority can roll over its storage
+    let other = create_payer(&mut svm);
+    let ix = vm_storage_rollover(other.pubkey(), vm_address, vm_storage_address);
+    assert!(!send_ix(&mut svm, &other, ix));
+
+    svm.expire_blockhash();
+    assert!(send_ix(&mut svm, &payer, rollover));
+
+    let storage_account = svm.get_account(&vm_storage_address).unwrap();
+    assert_eq!(storage_account.data.len(), StorageAccount::get_size_with_archived_trees(1));
+
+    let storage = get_storage_account(&svm, vm_storage_address);
+    assert_eq!(storage.tree_id, 1);
+    assert_eq!(storage.compressed_state.get_root(), new_tree().get_root());
+    assert_eq!(get_archived_root(&svm, vm_storage_address, 0), tree_0.get_root());
+
+    // New accounts go into the new tree
+    let mut tree_1 = new_tree();
+    let (va_b, sig_b, sig_hash_b) = compress(&mut svm, 1, 0);
+    assert!(tree_1.try_insert(sig_hash_b).is_ok());
+    assert_eq!(get_storage_account(&svm, vm_storage_address).compressed_state.get_root(), tree_1.get_root());
+
+    let leaf = |sig_hash| MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::as_leaf(sig_hash);
+    let proof_a = tree_0.get_merkle_proof(&[leaf(sig_hash_a)], 0);
+    let proof_b = tree_1.get_merkle_proof(&[leaf(sig_hash_b)], 0);
+
+    // The proof must be used with the tree that holds the account
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 40, va_a.pack(), 1, 0, proof_a.clone(), sig_a
+    ).is_err());
+
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 40, va_a.pack(), 2, 0, proof_a.clone(), sig_a
+    ).is_err());
+
+    // The storage account can't be closed while either tree holds an account
+    let destination = create_keypair().pubkey();
+    let close = vm_storage_close(payer.pubkey(), vm_address, vm_storage_address, destination);
+    assert!(!send_ix(&mut svm, &payer, close.clone()));
+
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 41, va_b.pack(), 1, 0, proof_b, sig_b
+    ).is_ok());
+
+    svm.expire_blockhash();
+    assert!(!send_ix(&mut svm, &payer, close.clone()));
+
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 40, va_a.pack(), 0, 0, proof_a, sig_a
+    ).is_ok());
+
+    let storage = get_storage_account(&svm, vm_storage_address);
+    assert_eq!(get_archived_root(&svm, vm_storage_address, 0), storage.compressed_state.get_empty_root());
+
+    assert_eq!(get_virtual_account(&svm, vm_mem_address, 40).get_hash(), va_a.get_hash());
+    assert_eq!(get_virtual_account(&svm, vm_mem_address, 41).get_hash(), va_b.get_hash());
+
+    svm.expire_blockhash();
+    assert!(send_ix(&mut svm, &payer, close));
+}
+
+#[test]
+fn run_legacy_storage_rollover() {
+    let (mut svm, payer, _mint_owner, _mint_pk, vm_address) =
+        setup_svm_with_payer_and_vm(21);
+
+    let name = "test";
+    let capacity = 100;
+    let account_size = VirtualDurableNonce::LEN+1;
+
+    let (vm_mem_address, _) =
+        create_and_resize_memory(&mut svm, &payer, vm_address, capacity, account_size, name);
+
+    let (vm_storage_address, _) =
+        create_storage_account(&mut svm, &payer, vm_address, name);
+
+    let owner = create_keypair().pubkey();
+    assert!(tx_create_virtual_nonce(&mut svm, &payer, vm_address, vm_mem_address, owner, 0).is_ok());
+
+    let va = get_virtual_account(&svm, vm_mem_address, 0);
+    let va_hash = va.get_hash();
+    let sig = Signature::new(payer.sign_message(va_hash.as_ref()).as_ref());
+    let sig_hash = get_storage_leaf_value(&sig, &va_hash, 0);
+
+    assert!(tx_account_compress(&mut svm, &payer, vm_address, vm_mem_address, vm_storage_address, 0, sig).is_ok());
+
+    // Change the storage account to the legacy size, without a changelog
+    let mut info = svm.get_account(&vm_storage_address).unwrap();
+    info.data.truncate(StorageAccount::get_size());
+    svm.set_account(vm_storage_address, info).unwrap();
+
+    let root = get_storage_account(&svm, vm_storage_address).compressed_state.get_root();
+
+    // The changelog and the first archived root are added in one go
+    let ix = vm_storage_rollover(payer.pubkey(), vm_address, vm_storage_address);
+    assert!(send_ix(&mut svm, &payer, ix));
+
+    let storage_account = svm.get_account(&vm_storage_address).unwrap();
+    assert_eq!(storage_account.data.len(), StorageAccount::get_size_with_archived_trees(1));
+    assert_eq!(get_storage_account(&svm, vm_storage_address).tree_id, 1);
+    assert_eq!(get_archived_root(&svm, vm_storage_address, 0), root);
+
+    // The account is still in the archived tree
+    let mut tree_0 = MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::new(&[
+        MERKLE_TREE_SEED,
+        create_name(name).as_ref(),
+        vm_address.as_ref()
+    ]);
+    assert!(tree_0.try_insert(sig_hash).is_ok());
+    let leaf = MerkleTree::<{StorageAccount::MERKLE_TREE_DEPTH}>::as_leaf(sig_hash);
+    let proof = tree_0.get_merkle_proof(&[leaf], 0);
+
+    assert!(tx_account_decompress(
+        &mut svm, &payer, vm_address, vm_mem_address, vm_storage_address,
+        None, None, 40, va.pack(), 0, 0, proof, sig
+    ).is_ok());
+    assert_eq!(get_virtual_account(&svm, vm_mem_address, 40).get_hash(), va.get_hash());
+}